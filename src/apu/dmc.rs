@@ -0,0 +1,160 @@
+use crate::cpu::clock::ClockMode;
+use crate::types::BitRead;
+
+const RATES_NTSC: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+const RATES_PAL: [u16; 16] = [398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50];
+
+/// Delta modulation channel, playing 1-bit delta encoded samples read from CPU memory.
+///
+/// The channel cannot reach the bus itself: it requests sample bytes through `request`
+/// and whoever owns the bus answers with `fill`.
+pub struct Dmc {
+    rates: &'static [u16; 16],
+    irq_enabled: bool,
+    irq_pending: bool,
+    looping: bool,
+    timer_period: u16,
+    timer: u16,
+    level: u8,
+    sample_address: u16,
+    sample_len: u16,
+    address: u16,
+    bytes_remaining: u16,
+    buffer: Option<u8>,
+    shift: u8,
+    bits_remaining: u8,
+    silent: bool,
+}
+
+impl Dmc {
+    pub fn new(clock_mode: ClockMode) -> Self {
+        let rates = match clock_mode {
+            ClockMode::Pal => &RATES_PAL,
+            _ => &RATES_NTSC,
+        };
+
+        Self {
+            rates,
+            irq_enabled: false,
+            irq_pending: false,
+            looping: false,
+            timer_period: rates[0],
+            timer: 0,
+            level: 0,
+            sample_address: 0xC000,
+            sample_len: 1,
+            address: 0xC000,
+            bytes_remaining: 0,
+            buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            silent: true,
+        }
+    }
+
+    /// Handles a write to one of the four channel registers, numbered 0-3.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register & 0b11 {
+            0 => {
+                self.irq_enabled = value.is_bit_set(7);
+                self.looping = value.is_bit_set(6);
+                self.timer_period = self.rates[(value & 0x0F) as usize];
+
+                if !self.irq_enabled {
+                    self.irq_pending = false;
+                }
+            },
+            1 => self.level = value & 0x7F,
+            2 => self.sample_address = 0xC000 | ((value as u16) << 6),
+            _ => self.sample_len = ((value as u16) << 4) + 1,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.irq_pending = false;
+
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    /// Address of the next sample byte, when the sample buffer is waiting for one.
+    pub fn request(&self) -> Option<u16> {
+        if self.buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.address)
+        } else {
+            None
+        }
+    }
+
+    /// Stores the sample byte read from the address given by `request`.
+    pub fn fill(&mut self, value: u8) {
+        self.buffer = Some(value);
+        // sample addresses wrap around to $8000 rather than $0000
+        self.address = self.address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_pending = true;
+            }
+        }
+    }
+
+    /// Clocks the timer, once every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.timer_period - 1;
+
+        if !self.silent {
+            if self.shift.is_bit_set(0) {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            } else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+
+        self.shift >>= 1;
+        self.bits_remaining -= 1;
+
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+
+            match self.buffer.take() {
+                Some(value) => {
+                    self.silent = false;
+                    self.shift = value;
+                },
+                None => self.silent = true,
+            }
+        }
+    }
+
+    /// Current output level, 0-127.
+    pub fn output(&self) -> u8 {
+        self.level
+    }
+
+    fn restart(&mut self) {
+        self.address = self.sample_address;
+        self.bytes_remaining = self.sample_len;
+    }
+}
@@ -0,0 +1,207 @@
+mod dmc;
+mod envelope;
+mod length_counter;
+mod noise;
+mod pulse;
+mod tests;
+mod triangle;
+mod vrc6;
+
+pub use self::dmc::Dmc;
+pub use self::envelope::Envelope;
+pub use self::length_counter::LengthCounter;
+pub use self::noise::Noise;
+pub use self::pulse::{Pulse, PulseChannel};
+pub use self::triangle::Triangle;
+pub use self::vrc6::{Vrc6Pulse, Vrc6Sawtooth};
+
+use crate::cpu::clock::ClockMode;
+use crate::types::BitRead;
+
+/// CPU cycles at which the frame counter clocks the envelopes and linear counter (quarter
+/// frames), and the length counters and sweeps as well on every other one (half frames).
+const FRAME_STEPS_NTSC: [u32; 4] = [7457, 14913, 22371, 29829];
+const FRAME_STEPS_PAL: [u32; 4] = [8313, 16627, 24939, 33253];
+/// Extra step of the 5-step sequence, which replaces the last step of the 4-step one.
+const FRAME_STEP_5_NTSC: u32 = 37281;
+const FRAME_STEP_5_PAL: u32 = 41565;
+
+/// The 2A03 audio processing unit: two pulse channels, a triangle, a noise channel and a
+/// DMC, sequenced by the frame counter and mixed through the non-linear DAC curves.
+pub struct Apu {
+    pulses: [Pulse; 2],
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_steps: [u32; 4],
+    frame_step_5: u32,
+    frame_cycle: u32,
+    frame_5_step: bool,
+    frame_irq_inhibit: bool,
+    frame_irq: bool,
+    odd_cycle: bool,
+}
+
+impl Apu {
+    pub fn new(clock_mode: ClockMode) -> Self {
+        let (frame_steps, frame_step_5) = match clock_mode {
+            ClockMode::Pal => (FRAME_STEPS_PAL, FRAME_STEP_5_PAL),
+            _ => (FRAME_STEPS_NTSC, FRAME_STEP_5_NTSC),
+        };
+
+        Self {
+            pulses: [Pulse::new(PulseChannel::First), Pulse::new(PulseChannel::Second)],
+            triangle: Triangle::default(),
+            noise: Noise::new(clock_mode),
+            dmc: Dmc::new(clock_mode),
+            frame_steps,
+            frame_step_5,
+            frame_cycle: 0,
+            frame_5_step: false,
+            frame_irq_inhibit: false,
+            frame_irq: false,
+            odd_cycle: false,
+        }
+    }
+
+    /// Reads $4015, the channel and IRQ status, which acknowledges the frame IRQ.
+    pub fn read_status(&mut self) -> u8 {
+        let value = self.pulses[0].is_active() as u8
+            | (self.pulses[1].is_active() as u8) << 1
+            | (self.triangle.is_active() as u8) << 2
+            | (self.noise.is_active() as u8) << 3
+            | (self.dmc.is_active() as u8) << 4
+            | (self.frame_irq as u8) << 6
+            | (self.dmc.irq() as u8) << 7;
+
+        self.frame_irq = false;
+        value
+    }
+
+    /// Writes to the channel registers at $4000-$4013, $4015 or the frame counter at $4017.
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x4000..=0x4003 => self.pulses[0].write(address, value),
+            0x4004..=0x4007 => self.pulses[1].write(address, value),
+            0x4008..=0x400B => self.triangle.write(address, value),
+            0x400C..=0x400F => self.noise.write(address, value),
+            0x4010..=0x4013 => self.dmc.write(address, value),
+            0x4015 => {
+                self.pulses[0].set_enabled(value.is_bit_set(0));
+                self.pulses[1].set_enabled(value.is_bit_set(1));
+                self.triangle.set_enabled(value.is_bit_set(2));
+                self.noise.set_enabled(value.is_bit_set(3));
+                self.dmc.set_enabled(value.is_bit_set(4));
+            },
+            0x4017 => {
+                self.frame_5_step = value.is_bit_set(7);
+                self.frame_irq_inhibit = value.is_bit_set(6);
+                self.frame_cycle = 0;
+
+                if self.frame_irq_inhibit {
+                    self.frame_irq = false;
+                }
+
+                // the 5-step sequence clocks everything right away
+                if self.frame_5_step {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Advances the APU by one CPU cycle.
+    pub fn clock(&mut self) {
+        self.odd_cycle = !self.odd_cycle;
+
+        if self.odd_cycle {
+            self.pulses.iter_mut().for_each(Pulse::clock_timer);
+        }
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        self.clock_frame_counter();
+    }
+
+    /// State of the APU's IRQ output, from the frame counter or the end of a DMC sample.
+    pub fn irq(&self) -> bool {
+        self.frame_irq || self.dmc.irq()
+    }
+
+    /// Address of the next DMC sample byte to fetch from the CPU bus, if one is needed.
+    pub fn dmc_request(&self) -> Option<u16> {
+        self.dmc.request()
+    }
+
+    /// Hands the DMC the sample byte fetched for `dmc_request`.
+    pub fn dmc_fill(&mut self, value: u8) {
+        self.dmc.fill(value);
+    }
+
+    /// Current output sample, 0.0-1.0.
+    pub fn output(&self) -> f32 {
+        let pulse = mix_pulse(self.pulses[0].output() + self.pulses[1].output());
+        let tnd = self.triangle.output() as f32 / 8227.0
+            + self.noise.output() as f32 / 12241.0
+            + self.dmc.output() as f32 / 22638.0;
+
+        if tnd == 0.0 {
+            pulse
+        } else {
+            pulse + 159.79 / (1.0 / tnd + 100.0)
+        }
+    }
+
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+        let [quarter_1, half_1, quarter_2, half_2] = self.frame_steps;
+
+        match self.frame_cycle {
+            cycle if cycle == quarter_1 || cycle == quarter_2 => self.clock_quarter_frame(),
+            cycle if cycle == half_1 => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            },
+            cycle if cycle == half_2 && !self.frame_5_step => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+
+                if !self.frame_irq_inhibit {
+                    self.frame_irq = true;
+                }
+
+                self.frame_cycle = 0;
+            },
+            cycle if cycle == self.frame_step_5 => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+                self.frame_cycle = 0;
+            },
+            _ => {},
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulses.iter_mut().for_each(Pulse::clock_quarter_frame);
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulses.iter_mut().for_each(Pulse::clock_half_frame);
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+}
+
+/// Mixes pulse channel levels (0-15 each) with the 2A03's non-linear DAC curve.
+pub fn mix_pulse(level: u8) -> f32 {
+    if level == 0 {
+        0.0
+    } else {
+        95.88 / (8128.0 / level as f32 + 100.0)
+    }
+}
@@ -0,0 +1,90 @@
+use super::{Envelope, LengthCounter};
+use crate::cpu::clock::ClockMode;
+use crate::types::BitRead;
+
+const PERIODS_NTSC: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+const PERIODS_PAL: [u16; 16] = [4, 8, 14, 30, 60, 88, 118, 148, 188, 236, 354, 472, 708, 944, 1890, 3778];
+
+/// Pseudo-random noise channel, driven by a 15-bit linear feedback shift register.
+pub struct Noise {
+    periods: &'static [u16; 16],
+    envelope: Envelope,
+    length_counter: LengthCounter,
+    short_mode: bool,
+    timer_period: u16,
+    timer: u16,
+    shift: u16,
+}
+
+impl Noise {
+    pub fn new(clock_mode: ClockMode) -> Self {
+        Self {
+            periods: match clock_mode {
+                ClockMode::Pal => &PERIODS_PAL,
+                _ => &PERIODS_NTSC,
+            },
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
+            short_mode: false,
+            timer_period: PERIODS_NTSC[0],
+            timer: 0,
+            shift: 1,
+        }
+    }
+
+    /// Handles a write to one of the four channel registers, numbered 0-3.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register & 0b11 {
+            0 => {
+                self.length_counter.set_halted(value.is_bit_set(5));
+                self.envelope.write(value);
+            },
+            1 => {},
+            2 => {
+                self.short_mode = value.is_bit_set(7);
+                self.timer_period = self.periods[(value & 0x0F) as usize];
+            },
+            _ => {
+                self.length_counter.load(value >> 3);
+                self.envelope.restart();
+            },
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length_counter.is_active()
+    }
+
+    /// Clocks the timer, once every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            let tap = if self.short_mode { 6 } else { 1 };
+            let feedback = (self.shift ^ (self.shift >> tap)) & 1;
+            self.shift = (self.shift >> 1) | (feedback << 14);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    /// Current output level, 0-15.
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.is_active() || self.shift & 1 == 1 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use super::{Envelope, LengthCounter};
+use crate::types::BitRead;
+
+const DUTIES: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// Square wave channel, as found twice in the 2A03 and twice more in the MMC5.
+pub struct Pulse {
+    channel: PulseChannel,
+    envelope: Envelope,
+    length_counter: LengthCounter,
+    duty: u8,
+    step: u8,
+    timer_period: u16,
+    timer: u16,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PulseChannel {
+    /// 2A03 pulse 1, whose sweep negates with one's complement.
+    First,
+    /// 2A03 pulse 2, whose sweep negates with two's complement.
+    Second,
+    /// MMC5 pulse, without a sweep unit or muting of low periods.
+    Mmc5,
+}
+
+impl Pulse {
+    pub fn new(channel: PulseChannel) -> Self {
+        Self {
+            channel,
+            envelope: Envelope::default(),
+            length_counter: LengthCounter::default(),
+            duty: 0,
+            step: 0,
+            timer_period: 0,
+            timer: 0,
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+        }
+    }
+
+    /// Handles a write to one of the four channel registers, numbered 0-3.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register & 0b11 {
+            0 => {
+                self.duty = value >> 6;
+                self.length_counter.set_halted(value.is_bit_set(5));
+                self.envelope.write(value);
+            },
+            1 => {
+                if self.channel != PulseChannel::Mmc5 {
+                    self.sweep_enabled = value.is_bit_set(7);
+                    self.sweep_period = (value >> 4) & 0b111;
+                    self.sweep_negate = value.is_bit_set(3);
+                    self.sweep_shift = value & 0b111;
+                    self.sweep_reload = true;
+                }
+            },
+            2 => self.timer_period = (self.timer_period & 0x0700) | value as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((value as u16 & 0b111) << 8);
+                self.length_counter.load(value >> 3);
+                self.envelope.restart();
+                self.step = 0;
+            },
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length_counter.is_active()
+    }
+
+    /// Clocks the timer, once every APU cycle (two CPU cycles).
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+
+        if self.channel == PulseChannel::Mmc5 {
+            return;
+        }
+
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.is_muted() {
+            self.timer_period = self.sweep_target();
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    /// Current output level, 0-15.
+    pub fn output(&self) -> u8 {
+        if !self.length_counter.is_active() || self.is_muted() || DUTIES[self.duty as usize][self.step as usize] == 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+
+    fn sweep_target(&self) -> u16 {
+        let change = self.timer_period >> self.sweep_shift;
+
+        if self.sweep_negate {
+            let complement = (self.channel == PulseChannel::First) as u16;
+            self.timer_period.saturating_sub(change + complement)
+        } else {
+            self.timer_period + change
+        }
+    }
+
+    fn is_muted(&self) -> bool {
+        self.channel != PulseChannel::Mmc5 && (self.timer_period < 8 || self.sweep_target() > 0x07FF)
+    }
+}
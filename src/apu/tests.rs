@@ -0,0 +1,184 @@
+#![cfg(test)]
+
+use super::*;
+
+fn clock(apu: &mut Apu, cycles: u32) {
+    for _ in 0..cycles {
+        apu.clock();
+    }
+}
+
+#[test]
+fn status_length_counters() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    apu.write(0x4003, 0x08);
+    assert_eq!(apu.read_status(), 0);
+
+    apu.write(0x4015, 0b0_1111);
+    apu.write(0x4003, 0x08);
+    apu.write(0x4007, 0x08);
+    apu.write(0x400B, 0x08);
+    apu.write(0x400F, 0x08);
+    assert_eq!(apu.read_status(), 0b0_1111);
+
+    apu.write(0x4015, 0b0_0001);
+    assert_eq!(apu.read_status(), 0b0_0001);
+}
+
+#[test]
+fn length_counter_expires() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    apu.write(0x4015, 0b0_0001);
+    // length index 3 loads 2, clocked by two half frames
+    apu.write(0x4003, 3 << 3);
+    clock(&mut apu, 14913);
+    assert_eq!(apu.read_status() & 1, 1);
+    clock(&mut apu, 29829 - 14913);
+    assert_eq!(apu.read_status() & 1, 0);
+}
+
+#[test]
+fn frame_irq() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    clock(&mut apu, 29828);
+    assert!(!apu.irq());
+    clock(&mut apu, 1);
+    assert!(apu.irq());
+    assert_eq!(apu.read_status(), 0b0100_0000);
+    assert!(!apu.irq());
+
+    apu.write(0x4017, 0b0100_0000);
+    clock(&mut apu, 29829);
+    assert!(!apu.irq());
+
+    apu.write(0x4017, 0b1000_0000);
+    clock(&mut apu, 40000);
+    assert!(!apu.irq());
+}
+
+#[test]
+fn dmc_sample() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    apu.write(0x4010, 0b1000_1111);
+    apu.write(0x4012, 0x01);
+    apu.write(0x4013, 0x00);
+    apu.write(0x4015, 0b1_0000);
+    assert_eq!(apu.read_status(), 0b1_0000);
+    assert_eq!(apu.dmc_request(), Some(0xC040));
+
+    apu.dmc_fill(0xFF);
+    assert_eq!(apu.dmc_request(), None);
+    assert!(apu.irq());
+    assert_eq!(apu.read_status(), 0b1000_0000);
+
+    // the sample is played one bit per 54 cycles once the shift register is empty
+    clock(&mut apu, 54 * 9);
+    assert!(apu.output() > 0.0);
+}
+
+#[test]
+fn dmc_address_wraps() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    apu.write(0x4012, 0xFF);
+    apu.write(0x4013, 0x04);
+    apu.write(0x4015, 0b1_0000);
+    for _ in 0..0x40 {
+        apu.dmc_fill(0);
+        clock(&mut apu, 428 * 8);
+    }
+    assert_eq!(apu.dmc_request(), Some(0x8000));
+}
+
+#[test]
+fn pulse_output() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    apu.write(0x4015, 0b0_0001);
+    apu.write(0x4000, 0b1011_1111);
+    apu.write(0x4002, 0x40);
+    apu.write(0x4003, 0x08);
+
+    let mut levels = Vec::new();
+    for _ in 0..0x41 * 16 {
+        apu.clock();
+        levels.push(apu.pulses[0].output());
+    }
+    assert!(levels.contains(&0));
+    assert!(levels.contains(&15));
+}
+
+#[test]
+fn pulse_sweep_mutes() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    apu.write(0x4015, 0b0_0001);
+    apu.write(0x4000, 0b1011_1111);
+    apu.write(0x4002, 0x07);
+    apu.write(0x4003, 0x08);
+    for _ in 0..100 {
+        apu.clock();
+        assert_eq!(apu.pulses[0].output(), 0);
+    }
+}
+
+#[test]
+fn triangle_linear_counter() {
+    let mut apu = Apu::new(ClockMode::Ntsc);
+    apu.write(0x4015, 0b0_0100);
+    apu.write(0x4008, 0x01);
+    apu.write(0x400A, 0x10);
+    apu.write(0x400B, 0x08);
+
+    // runs until the linear counter expires on the second quarter frame
+    clock(&mut apu, 14913);
+    let level = apu.triangle.output();
+    clock(&mut apu, 100);
+    assert_eq!(apu.triangle.output(), level);
+}
+
+#[test]
+fn noise_shift_register() {
+    let mut noise = Noise::new(ClockMode::Ntsc);
+    noise.set_enabled(true);
+    noise.write(0, 0b0011_1111);
+    noise.write(3, 0x08);
+
+    let mut levels = Vec::new();
+    for _ in 0..4 * 100 {
+        noise.clock_timer();
+        levels.push(noise.output());
+    }
+    assert!(levels.contains(&0));
+    assert!(levels.contains(&15));
+}
+
+#[test]
+fn vrc6_pulse_duty() {
+    let mut pulse = Vrc6Pulse::default();
+    pulse.write(0, 0b0011_1010);
+    pulse.write(1, 0);
+    pulse.write(2, 0x80);
+
+    let levels: Vec<_> = (0..16)
+        .map(|_| {
+            pulse.clock(0);
+            pulse.output()
+        })
+        .collect();
+    assert_eq!(levels.iter().filter(|&&level| level == 10).count(), 4);
+    assert_eq!(levels.iter().filter(|&&level| level == 0).count(), 12);
+}
+
+#[test]
+fn vrc6_sawtooth() {
+    let mut sawtooth = Vrc6Sawtooth::default();
+    sawtooth.write(0, 42);
+    sawtooth.write(1, 0);
+    sawtooth.write(2, 0x80);
+
+    let levels: Vec<_> = (0..14)
+        .map(|_| {
+            sawtooth.clock(0);
+            sawtooth.output()
+        })
+        .collect();
+    assert_eq!(levels, vec![0, 5, 5, 10, 10, 15, 15, 21, 21, 26, 26, 31, 31, 0]);
+}
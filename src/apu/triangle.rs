@@ -0,0 +1,82 @@
+use super::LengthCounter;
+use crate::types::BitRead;
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// Triangle wave channel, gated by both the length counter and its own linear counter.
+#[derive(Default)]
+pub struct Triangle {
+    length_counter: LengthCounter,
+    control: bool,
+    linear_period: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+    timer_period: u16,
+    timer: u16,
+    step: u8,
+}
+
+impl Triangle {
+    /// Handles a write to one of the four channel registers, numbered 0-3.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register & 0b11 {
+            0 => {
+                self.control = value.is_bit_set(7);
+                self.length_counter.set_halted(self.control);
+                self.linear_period = value & 0x7F;
+            },
+            1 => {},
+            2 => self.timer_period = (self.timer_period & 0x0700) | value as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((value as u16 & 0b111) << 8);
+                self.length_counter.load(value >> 3);
+                self.linear_reload = true;
+            },
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.length_counter.set_enabled(enabled);
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.length_counter.is_active()
+    }
+
+    /// Clocks the timer, once every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+
+            if self.length_counter.is_active() && self.linear_counter > 0 {
+                self.step = (self.step + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_period;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length_counter.clock();
+    }
+
+    /// Current output level, 0-15.
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.step as usize]
+    }
+}
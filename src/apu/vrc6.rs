@@ -0,0 +1,118 @@
+use crate::types::BitRead;
+
+/// VRC6 pulse channel: a 16-step square wave with 8 duty cycles and a fixed volume.
+#[derive(Default)]
+pub struct Vrc6Pulse {
+    enabled: bool,
+    ignore_duty: bool,
+    duty: u8,
+    volume: u8,
+    period: u16,
+    timer: u16,
+    step: u8,
+}
+
+impl Vrc6Pulse {
+    /// Handles a write to one of the three channel registers, numbered 0-2.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register & 0b11 {
+            0 => {
+                self.ignore_duty = value.is_bit_set(7);
+                self.duty = (value >> 4) & 0b111;
+                self.volume = value & 0x0F;
+            },
+            1 => self.period = (self.period & 0x0F00) | value as u16,
+            2 => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x0F) << 8);
+                self.enabled = value.is_bit_set(7);
+
+                if !self.enabled {
+                    self.step = 0;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Clocks the timer, once every CPU cycle, with the period shifted right by `shift`.
+    pub fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer == 0 {
+            self.timer = self.period >> shift;
+            self.step = (self.step + 1) % 16;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// Current output level, 0-15.
+    pub fn output(&self) -> u8 {
+        if self.enabled && (self.ignore_duty || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+/// VRC6 sawtooth channel: an accumulator that grows by a fixed rate and resets every 7 steps.
+#[derive(Default)]
+pub struct Vrc6Sawtooth {
+    enabled: bool,
+    rate: u8,
+    period: u16,
+    timer: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl Vrc6Sawtooth {
+    /// Handles a write to one of the three channel registers, numbered 0-2.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register & 0b11 {
+            0 => self.rate = value & 0b0011_1111,
+            1 => self.period = (self.period & 0x0F00) | value as u16,
+            2 => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x0F) << 8);
+                self.enabled = value.is_bit_set(7);
+
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Clocks the timer, once every CPU cycle, with the period shifted right by `shift`.
+    pub fn clock(&mut self, shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+
+        self.timer = self.period >> shift;
+        self.step += 1;
+
+        // the accumulator grows on every other step and is cleared on the 14th
+        if self.step == 14 {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step & 1 == 0 {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    /// Current output level, 0-31.
+    pub fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
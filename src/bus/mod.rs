@@ -0,0 +1,290 @@
+mod scheduler;
+mod tests;
+
+pub use self::scheduler::{Event, Scheduler};
+
+use crate::apu::Apu;
+use crate::cartridge::Cartridge;
+use crate::cpu::clock::{Clock, ClockMode};
+use crate::device::{Device, Ram, OpenBus};
+use crate::mapper::{self, Mapper};
+use crate::ppu::Ppu;
+use crate::types::Result;
+
+const RAM_LEN: usize = 0x0800;
+
+/// The CPU address space, routing each access to the device that owns it:
+///
+/// | Range         | Device                                   |
+/// |---------------|------------------------------------------|
+/// | $0000-$1FFF   | 2 KiB internal RAM, mirrored             |
+/// | $2000-$3FFF   | PPU registers, mirrored every 8 bytes    |
+/// | $4000-$4017   | APU registers, except $4014 and $4016    |
+/// | $4014-$401F   | I/O registers                            |
+/// | $4020-$FFFF   | Cartridge, through its mapper            |
+///
+/// The APU is stepped with every CPU cycle. The PPU and the mapper are left behind instead
+/// and caught up together, in master clock order, whenever the CPU accesses them or an event
+/// scheduled for a change of their interrupt outputs comes due.
+pub struct Bus {
+    ram: Ram,
+    ppu: Ppu,
+    apu: Apu,
+    io: Box<dyn Device>,
+    mapper: Box<dyn Mapper>,
+    scheduler: Scheduler,
+    /// Master clock timestamp of the last PPU dot run.
+    ppu_timestamp: u64,
+    /// Master clock timestamp up to which the mapper has been clocked.
+    mapper_timestamp: u64,
+    open_bus: u8,
+}
+
+impl Bus {
+    pub fn new(mapper: Box<dyn Mapper>) -> Self {
+        Self::with_clock_mode(mapper, ClockMode::Ntsc)
+    }
+
+    pub fn with_cartridge(cartridge: Cartridge) -> Result<Self> {
+        let clock_mode = cartridge.header().clock_mode();
+        let mapper = mapper::from_cartridge(cartridge)?;
+        Ok(Self::with_clock_mode(mapper, clock_mode))
+    }
+
+    pub fn with_clock_mode(mapper: Box<dyn Mapper>, clock_mode: ClockMode) -> Self {
+        let mut bus = Self {
+            ram: Ram::new(RAM_LEN),
+            ppu: Ppu::new(clock_mode),
+            apu: Apu::new(clock_mode),
+            io: Box::new(OpenBus),
+            mapper,
+            scheduler: Scheduler::new(clock_mode),
+            ppu_timestamp: 0,
+            mapper_timestamp: 0,
+            open_bus: 0,
+        };
+
+        bus.reschedule();
+        bus
+    }
+
+    pub fn connect_io(&mut self, device: Box<dyn Device>) {
+        self.io = device;
+    }
+
+    /// The PPU, caught up to the current cycle.
+    pub fn ppu(&mut self) -> &Ppu {
+        self.sync();
+        &self.ppu
+    }
+
+    /// The PPU, caught up to the current cycle.
+    pub fn ppu_mut(&mut self) -> &mut Ppu {
+        self.sync();
+        &mut self.ppu
+    }
+
+    pub fn clock(&self) -> &Clock {
+        self.scheduler.clock()
+    }
+
+    pub fn clock_mode(&self) -> ClockMode {
+        self.scheduler.clock().mode()
+    }
+
+    /// Advances the devices on the bus by the given number of CPU cycles.
+    pub fn tick(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.scheduler.tick();
+            self.apu.clock();
+
+            if let Some(address) = self.apu.dmc_request() {
+                let value = self.read(address);
+                self.apu.dmc_fill(value);
+            }
+
+            while self.scheduler.pop_due().is_some() {
+                self.sync();
+            }
+        }
+    }
+
+    /// Runs the PPU and the mapper up to the current master clock timestamp, with the dots of
+    /// each CPU cycle ahead of the mapper's clock for it, then schedules their next events.
+    fn sync(&mut self) {
+        let now = self.scheduler.timestamp();
+        let cpu_divider = self.clock_mode().cpu_divider();
+        let ppu_divider = self.clock_mode().ppu_divider();
+
+        while self.mapper_timestamp < now {
+            let cycle_end = self.mapper_timestamp + cpu_divider;
+
+            while self.ppu_timestamp + ppu_divider <= cycle_end {
+                self.ppu.tick(&mut *self.mapper);
+                self.ppu_timestamp += ppu_divider;
+            }
+
+            self.mapper.clock_cpu();
+            self.mapper_timestamp = cycle_end;
+        }
+
+        self.reschedule();
+    }
+
+    fn reschedule(&mut self) {
+        let cpu_divider = self.clock_mode().cpu_divider();
+        let ppu_divider = self.clock_mode().ppu_divider();
+
+        let nmi = self.ppu_timestamp + self.ppu.dots_until_nmi_change() * ppu_divider;
+        self.scheduler.schedule(Event::Nmi, nmi);
+
+        match self.mapper.cycles_until_irq() {
+            Some(cycles) => {
+                let irq = self.mapper_timestamp + cycles as u64 * cpu_divider;
+                self.scheduler.schedule(Event::MapperIrq, irq);
+            },
+            None => self.scheduler.cancel(Event::MapperIrq),
+        }
+    }
+
+    /// Whether an access to `address` has to see the PPU and the mapper caught up. Internal
+    /// RAM is left out: boards snoop writes to it no more than to anything else, but none
+    /// cares about their timing.
+    fn is_synced(address: u16) -> bool {
+        address >= 0x2000
+    }
+
+    /// State of the NMI line, asserted by the PPU during vertical blank when enabled.
+    pub fn nmi(&self) -> bool {
+        self.ppu.nmi()
+    }
+
+    /// State of the shared IRQ line, asserted while any device on the bus pulls it low.
+    pub fn irq(&self) -> bool {
+        self.apu.irq() || self.mapper.irq()
+    }
+
+    /// Raw contents of the cartridge's PRG RAM, in the layout used by `.sav` files.
+    pub fn save_data(&self) -> &[u8] {
+        self.mapper.prg_ram()
+    }
+
+    /// Replaces the cartridge's PRG RAM with raw save data. Saves of another size, like the
+    /// 8 KiB files other emulators write regardless of the header, are truncated or zero-filled.
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        let prg_ram = self.mapper.prg_ram_mut();
+        let len = data.len().min(prg_ram.len());
+
+        prg_ram[..len].copy_from_slice(&data[..len]);
+        prg_ram[len..].iter_mut().for_each(|byte| *byte = 0);
+    }
+
+    /// Current audio sample: the APU output mixed with the cartridge's expansion audio.
+    pub fn audio_output(&mut self) -> f32 {
+        self.sync();
+        self.apu.output() + self.mapper.audio_output()
+    }
+
+    pub fn read(&mut self, address: u16) -> u8 {
+        let synced = Self::is_synced(address);
+
+        if synced {
+            self.sync();
+        }
+
+        let value = match address {
+            0x0000..=0x1FFF => self.ram.read(address),
+            0x2000..=0x3FFF => Some(self.ppu.read_register(address, &mut *self.mapper)),
+            0x4015 => Some(self.apu.read_status()),
+            0x4000..=0x401F => self.io.read(address),
+            0x4020..=0xFFFF => self.mapper.read_cpu(address),
+        };
+
+        if synced {
+            self.reschedule();
+        }
+
+        self.open_bus = value.unwrap_or(self.open_bus);
+        self.open_bus
+    }
+
+    /// Reads a little-endian word, wrapping around from $FFFF to $0000.
+    pub fn read_u16(&mut self, address: u16) -> u16 {
+        let bytes = [self.read(address), self.read(address.wrapping_add(1))];
+        u16::from_le_bytes(bytes)
+    }
+
+    /// Reads a little-endian word without carrying into the high byte of the address, like the
+    /// 6502 does for pointers in the zero page and the NMOS one for `JMP ($xxFF)`.
+    pub fn read_u16_in_page(&mut self, address: u16) -> u16 {
+        let bytes = [self.read(address), self.read(increment_in_page(address))];
+        u16::from_le_bytes(bytes)
+    }
+
+    pub fn read_n(&mut self, address: u16, n: u16) -> Result<Vec<u8>> {
+        if address.checked_add(n).is_some() {
+            let mut bytes = vec![];
+
+            for i in 0..n {
+                bytes.push(self.read(address + i));
+            }
+
+            Ok(bytes)
+        } else {
+            Err(anyhow!("address + n out of bounds"))
+        }
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        let synced = Self::is_synced(address);
+        self.open_bus = value;
+
+        if synced {
+            self.sync();
+        }
+
+        match address {
+            0x0000..=0x1FFF => self.ram.write(address, value),
+            0x2000..=0x3FFF => self.ppu.write_register(address, value, &mut *self.mapper),
+            0x4000..=0x4013 | 0x4015 | 0x4017 => self.apu.write(address, value),
+            0x4000..=0x401F => self.io.write(address, value),
+            0x4020..=0xFFFF => self.mapper.write_cpu(address, value),
+        }
+
+        if address < 0x4020 {
+            self.mapper.observe_cpu_write(address, value);
+        }
+
+        if synced {
+            self.reschedule();
+        }
+    }
+
+    pub fn write_u16(&mut self, address: u16, value: u16) -> Result {
+        if address.checked_add(1).is_some() {
+            let bytes = value.to_le_bytes();
+            self.write(address, bytes[0]);
+            self.write(address + 1, bytes[1]);
+            Ok(())
+        } else {
+            Err(anyhow!("address out of bounds"))
+        }
+    }
+
+    pub fn write_n(&mut self, address: u16, bytes: &[u8]) -> Result {
+        if address.checked_add(bytes.len() as u16).is_some() {
+            for (i, &byte) in bytes.iter().enumerate() {
+                self.write(address + i as u16, byte);
+            }
+
+            Ok(())
+        } else {
+            Err(anyhow!("address + byte array length out of bounds"))
+        }
+    }
+}
+
+/// The address after `address` within its page, wrapping from $xxFF to $xx00.
+pub fn increment_in_page(address: u16) -> u16 {
+    (address & 0xFF00) | (address as u8).wrapping_add(1) as u16
+}
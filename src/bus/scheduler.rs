@@ -0,0 +1,62 @@
+use crate::cpu::clock::{Clock, ClockMode};
+
+/// Points in time at which the components that run behind the CPU have to be caught up.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Event {
+    /// The PPU's NMI output may change by itself.
+    Nmi,
+    /// The mapper may assert its IRQ output.
+    MapperIrq,
+}
+
+/// Keeps the master clock and the events scheduled on it.
+///
+/// Every event is scheduled at most once; scheduling it again moves it.
+pub struct Scheduler {
+    clock: Clock,
+    events: Vec<(u64, Event)>,
+}
+
+impl Scheduler {
+    pub fn new(mode: ClockMode) -> Self {
+        Self {
+            clock: Clock::new(mode),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Current master clock timestamp.
+    pub fn timestamp(&self) -> u64 {
+        self.clock.master_cycles()
+    }
+
+    /// Advances the master clock by one CPU cycle.
+    pub fn tick(&mut self) {
+        self.clock.tick(1);
+    }
+
+    /// Schedules `event` for the master clock `timestamp`, replacing any earlier schedule of it.
+    pub fn schedule(&mut self, event: Event, timestamp: u64) {
+        self.cancel(event);
+        self.events.push((timestamp, event));
+    }
+
+    pub fn cancel(&mut self, event: Event) {
+        self.events.retain(|&(_, scheduled)| scheduled != event);
+    }
+
+    /// Removes and returns the earliest event due by now.
+    pub fn pop_due(&mut self) -> Option<Event> {
+        let now = self.timestamp();
+        let (index, _) = self.events.iter()
+            .enumerate()
+            .filter(|(_, &(timestamp, _))| timestamp <= now)
+            .min_by_key(|(_, &(timestamp, _))| timestamp)?;
+
+        Some(self.events.remove(index).1)
+    }
+}
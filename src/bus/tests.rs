@@ -0,0 +1,348 @@
+#![cfg(test)]
+
+use super::*;
+use crate::cartridge::Mirroring;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+type Accesses = Rc<RefCell<Vec<(u16, Option<u8>)>>>;
+
+/// Records every access, reading back the low byte of the address.
+struct RecordingDevice {
+    accesses: Accesses,
+}
+
+impl Device for RecordingDevice {
+    fn read(&mut self, address: u16) -> Option<u8> {
+        self.accesses.borrow_mut().push((address, None));
+        Some(address as u8)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.accesses.borrow_mut().push((address, Some(value)));
+    }
+}
+
+/// Drives the bus for ROM at $8000-$FFFF only, like a board without PRG RAM.
+struct RomMapper {
+    accesses: Accesses,
+}
+
+impl Mapper for RomMapper {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        self.accesses.borrow_mut().push((address, None));
+
+        if address >= 0x8000 {
+            Some((address >> 8) as u8)
+        } else {
+            None
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        self.accesses.borrow_mut().push((address, Some(value)));
+    }
+
+    fn read_ppu(&mut self, _address: u16) -> u8 {
+        0
+    }
+
+    fn write_ppu(&mut self, _address: u16, _value: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Horizontal
+    }
+}
+
+fn bus() -> (Bus, Accesses) {
+    let accesses = Accesses::default();
+    let bus = Bus::new(Box::new(RomMapper { accesses: accesses.clone() }));
+    (bus, accesses)
+}
+
+#[test]
+fn ram_mirrored() {
+    let (mut bus, _) = bus();
+
+    bus.write(0x0012, 0x34);
+    assert_eq!(bus.read(0x0812), 0x34);
+    assert_eq!(bus.read(0x1012), 0x34);
+    assert_eq!(bus.read(0x1812), 0x34);
+
+    bus.write(0x1FFF, 0x56);
+    assert_eq!(bus.read(0x07FF), 0x56);
+}
+
+#[test]
+fn ppu_registers_mirrored() {
+    let (mut bus, _) = bus();
+
+    // PPUADDR and PPUDATA through their mirrors
+    bus.write(0x3FFE, 0x21);
+    bus.write(0x200E, 0x08);
+    bus.write(0x3FFF, 0x55);
+    bus.write(0x2006, 0x21);
+    bus.write(0x2006, 0x08);
+    bus.read(0x2007);
+    assert_eq!(bus.read(0x200F), 0x55);
+}
+
+#[test]
+fn io_registers() {
+    let (mut bus, _) = bus();
+    let accesses = Accesses::default();
+    bus.connect_io(Box::new(RecordingDevice { accesses: accesses.clone() }));
+
+    bus.write(0x4014, 0x02);
+    assert_eq!(bus.read(0x4016), 0x16);
+    assert_eq!(*accesses.borrow(), vec![(0x4014, Some(0x02)), (0x4016, None)]);
+}
+
+#[test]
+fn cartridge_space_routed_to_mapper() {
+    let (mut bus, accesses) = bus();
+
+    bus.write(0x4020, 0x01);
+    bus.write(0x8000, 0x02);
+    assert_eq!(bus.read(0xC000), 0xC0);
+    assert_eq!(*accesses.borrow(), vec![
+        (0x4020, Some(0x01)),
+        (0x8000, Some(0x02)),
+        (0xC000, None),
+    ]);
+}
+
+#[test]
+fn open_bus() {
+    let (mut bus, _) = bus();
+
+    assert_eq!(bus.read(0xFFFC), 0xFF);
+    assert_eq!(bus.read(0x6000), 0xFF);
+    assert_eq!(bus.read(0x4018), 0xFF);
+
+    bus.write(0x0000, 0x42);
+    assert_eq!(bus.read(0x4000), 0x42);
+}
+
+#[test]
+fn read_write_u16() {
+    let (mut bus, _) = bus();
+
+    bus.write_u16(0x0100, 0x1234).unwrap();
+    assert_eq!(bus.read(0x0100), 0x34);
+    assert_eq!(bus.read(0x0101), 0x12);
+    assert_eq!(bus.read_u16(0x0100), 0x1234);
+    assert_eq!(bus.read_u16(0xFFFC), 0xFFFF);
+}
+
+#[test]
+fn read_u16_wrapping() {
+    let (mut bus, _) = bus();
+
+    bus.write(0x0000, 0x12);
+    assert_eq!(bus.read_u16(0xFFFF), 0x12FF);
+
+    bus.write_n(0x0100, &[0x12, 0x56]).unwrap();
+    bus.write(0x01FF, 0x34);
+    bus.write(0x0200, 0x78);
+    assert_eq!(bus.read_u16(0x01FF), 0x7834);
+    assert_eq!(bus.read_u16_in_page(0x01FF), 0x1234);
+    assert_eq!(bus.read_u16_in_page(0x0100), 0x5612);
+}
+
+#[test]
+fn apu_registers() {
+    let (mut bus, _) = bus();
+    let accesses = Accesses::default();
+    bus.connect_io(Box::new(RecordingDevice { accesses: accesses.clone() }));
+
+    bus.write(0x4015, 0b0_0001);
+    bus.write(0x4003, 0x08);
+    bus.write(0x4017, 0x40);
+    assert_eq!(bus.read(0x4015), 0b0_0001);
+    assert!(accesses.borrow().is_empty());
+}
+
+#[test]
+fn apu_dmc_fetches_through_bus() {
+    let (mut bus, accesses) = bus();
+    bus.write(0x4010, 0x80);
+    bus.write(0x4012, 0x00);
+    bus.write(0x4013, 0x00);
+    bus.write(0x4015, 0b1_0000);
+    accesses.borrow_mut().clear();
+
+    bus.tick(1);
+    assert_eq!(*accesses.borrow(), vec![(0xC000, None)]);
+    assert!(bus.irq());
+}
+
+#[test]
+fn save_data_resized() {
+    let (mut bus, _) = bus();
+    assert!(bus.save_data().is_empty());
+    bus.load_save_data(&[0; 0x2000]);
+    assert!(bus.save_data().is_empty());
+
+    let mut bus = Bus::new(Box::new(SramMapper { prg_ram: vec![0xFF; 4] }));
+    bus.load_save_data(&[1, 2]);
+    assert_eq!(bus.save_data(), &[1, 2, 0, 0]);
+    bus.load_save_data(&[1, 2, 3, 4, 5, 6]);
+    assert_eq!(bus.save_data(), &[1, 2, 3, 4]);
+}
+
+/// Holds battery-backed PRG RAM without mapping it anywhere.
+struct SramMapper {
+    prg_ram: Vec<u8>,
+}
+
+impl Mapper for SramMapper {
+    fn read_cpu(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    fn write_cpu(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ppu(&mut self, _address: u16) -> u8 {
+        0
+    }
+
+    fn write_ppu(&mut self, _address: u16, _value: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Horizontal
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        &self.prg_ram
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        &mut self.prg_ram
+    }
+}
+
+/// Counts down CPU cycles to an IRQ, which it schedules ahead.
+struct TimerMapper {
+    remaining: u32,
+    clocked: Rc<Cell<u32>>,
+}
+
+impl Mapper for TimerMapper {
+    fn read_cpu(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    fn write_cpu(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ppu(&mut self, _address: u16) -> u8 {
+        0
+    }
+
+    fn write_ppu(&mut self, _address: u16, _value: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Horizontal
+    }
+
+    fn clock_cpu(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+        self.clocked.set(self.clocked.get() + 1);
+    }
+
+    fn irq(&self) -> bool {
+        self.remaining == 0
+    }
+
+    fn cycles_until_irq(&self) -> Option<u32> {
+        Some(self.remaining).filter(|&remaining| remaining > 0)
+    }
+}
+
+#[test]
+fn ppu_caught_up_lazily() {
+    let (mut bus, _) = bus();
+    bus.tick(100);
+    assert_eq!((bus.ppu.scanline(), bus.ppu.dot()), (0, 0));
+    assert_eq!((bus.ppu().scanline(), bus.ppu().dot()), (0, 300));
+
+    // accessing its registers catches it up too
+    bus.tick(100);
+    bus.read(0x2002);
+    assert_eq!(bus.ppu.dot(), 600 - 341);
+}
+
+#[test]
+fn ppu_dots_per_cycle() {
+    for &(clock_mode, cycles, dots) in &[(ClockMode::Ntsc, 10, 30), (ClockMode::Pal, 10, 32), (ClockMode::Dendy, 10, 30)] {
+        let mut bus = Bus::with_clock_mode(Box::new(RomMapper { accesses: Accesses::default() }), clock_mode);
+        bus.tick(cycles);
+        assert_eq!(bus.ppu().dot(), dots);
+        assert_eq!(bus.clock().master_cycles(), cycles as u64 * clock_mode.cpu_divider());
+    }
+}
+
+#[test]
+fn nmi_scheduled() {
+    let (mut bus, _) = bus();
+    bus.write(0x2000, 0x80);
+
+    // the output rises after dot 3 of scanline 241 has run
+    let cycles = ((241 * 341 + 4) as f64 / 3.0).ceil() as u32;
+
+    for _ in 1..cycles {
+        bus.tick(1);
+        assert!(!bus.nmi());
+    }
+
+    bus.tick(1);
+    assert!(bus.nmi());
+    assert_eq!(bus.ppu.scanline(), 241);
+
+    // and falls at the end of vertical blank, once dot 1 of the pre-render line has run
+    let cycles_fall = ((261 * 341 + 2) as f64 / 3.0).ceil() as u32;
+
+    for _ in cycles + 1..cycles_fall {
+        bus.tick(1);
+        assert!(bus.nmi());
+    }
+
+    bus.tick(1);
+    assert!(!bus.nmi());
+}
+
+#[test]
+fn mapper_irq_scheduled() {
+    let clocked = Rc::new(Cell::new(0));
+    let mut bus = Bus::new(Box::new(TimerMapper { remaining: 1000, clocked: clocked.clone() }));
+
+    bus.tick(200);
+    assert_eq!(clocked.get(), 0);
+
+    for _ in 200..999 {
+        bus.tick(1);
+        assert!(!bus.irq());
+    }
+
+    bus.tick(1);
+    assert!(bus.irq());
+    assert_eq!(clocked.get(), 1000);
+}
+
+#[test]
+fn scheduler_events() {
+    let mut scheduler = Scheduler::new(ClockMode::Ntsc);
+    scheduler.schedule(Event::Nmi, 24);
+    scheduler.schedule(Event::MapperIrq, 12);
+    scheduler.schedule(Event::Nmi, 36);
+    assert_eq!(scheduler.pop_due(), None);
+
+    scheduler.tick();
+    assert_eq!(scheduler.pop_due(), Some(Event::MapperIrq));
+    assert_eq!(scheduler.pop_due(), None);
+
+    scheduler.tick();
+    scheduler.tick();
+    scheduler.cancel(Event::Nmi);
+    assert_eq!(scheduler.pop_due(), None);
+}
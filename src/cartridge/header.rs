@@ -0,0 +1,231 @@
+use crate::cpu::clock::ClockMode;
+use crate::types::{Result, BitRead};
+
+pub const HEADER_LEN: usize = 16;
+pub const TRAINER_LEN: usize = 512;
+pub const PRG_ROM_BANK_LEN: usize = 0x4000;
+pub const CHR_ROM_BANK_LEN: usize = 0x2000;
+pub const PRG_RAM_BANK_LEN: usize = 0x2000;
+pub const CHR_RAM_LEN: usize = 0x2000;
+
+const MAGIC: [u8; 4] = *b"NES\x1A";
+
+#[derive(Debug, Clone, Eq, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub")]
+pub struct Header {
+    format: HeaderFormat,
+    prg_rom_len: usize,
+    chr_rom_len: usize,
+    prg_ram_len: usize,
+    prg_nvram_len: usize,
+    chr_ram_len: usize,
+    chr_nvram_len: usize,
+    mapper: u16,
+    submapper: u8,
+    mirroring: Mirroring,
+    battery: bool,
+    trainer: bool,
+    timing: Timing,
+    console_type: ConsoleType,
+    /// Default expansion device as numbered by the NES 2.0 specification, `0` if unspecified.
+    expansion_device: u8,
+}
+
+impl Header {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LEN {
+            return Err(anyhow!("header too short: expected {} bytes, found {}", HEADER_LEN, bytes.len()));
+        }
+
+        if bytes[0..4] != MAGIC {
+            return Err(anyhow!("invalid magic number `{:02X?}`, expected `{:02X?}`", &bytes[0..4], MAGIC));
+        }
+
+        if bytes[7] & 0b0000_1100 == 0b0000_1000 {
+            Self::parse_nes2(bytes)
+        } else {
+            Self::parse_ines(bytes)
+        }
+    }
+
+    pub fn clock_mode(&self) -> ClockMode {
+        match self.timing {
+            Timing::Ntsc | Timing::MultiRegion => ClockMode::Ntsc,
+            Timing::Pal => ClockMode::Pal,
+            Timing::Dendy => ClockMode::Dendy,
+        }
+    }
+
+    fn parse_ines(bytes: &[u8]) -> Result<Self> {
+        let flags_6 = bytes[6];
+
+        // Headers written by old tools (e.g. "DiskDude!") contain garbage in bytes 7-15,
+        // in which case nothing past byte 6 can be trusted.
+        let trusted = bytes[12..16].iter().all(|&byte| byte == 0);
+        let flags_7 = if trusted { bytes[7] } else { 0 };
+        let prg_ram_banks = if trusted { bytes[8] } else { 0 };
+        let timing = if trusted && bytes[9].is_bit_set(0) { Timing::Pal } else { Timing::Ntsc };
+
+        let prg_rom_len = bytes[4] as usize * PRG_ROM_BANK_LEN;
+        let chr_rom_len = bytes[5] as usize * CHR_ROM_BANK_LEN;
+
+        if prg_rom_len == 0 {
+            return Err(anyhow!("header declares no PRG ROM"));
+        }
+
+        // iNES 1.0 cannot express the absence of PRG RAM, assume at least one bank
+        let prg_ram_len = prg_ram_banks.max(1) as usize * PRG_RAM_BANK_LEN;
+        let battery = flags_6.is_bit_set(1);
+
+        let console_type = if flags_7.is_bit_set(0) {
+            ConsoleType::VsSystem
+        } else if flags_7.is_bit_set(1) {
+            ConsoleType::Playchoice
+        } else {
+            ConsoleType::Nes
+        };
+
+        Ok(Self {
+            format: HeaderFormat::INes,
+            prg_rom_len,
+            chr_rom_len,
+            prg_ram_len: if battery { 0 } else { prg_ram_len },
+            prg_nvram_len: if battery { prg_ram_len } else { 0 },
+            chr_ram_len: if chr_rom_len == 0 { CHR_RAM_LEN } else { 0 },
+            chr_nvram_len: 0,
+            mapper: ((flags_7 & 0xF0) | (flags_6 >> 4)) as u16,
+            submapper: 0,
+            mirroring: Self::parse_mirroring(flags_6),
+            battery,
+            trainer: flags_6.is_bit_set(2),
+            timing,
+            console_type,
+            expansion_device: 0,
+        })
+    }
+
+    fn parse_nes2(bytes: &[u8]) -> Result<Self> {
+        let flags_6 = bytes[6];
+        let flags_7 = bytes[7];
+
+        let prg_rom_len = Self::parse_nes2_rom_len(bytes[4], bytes[9] & 0x0F, PRG_ROM_BANK_LEN);
+        let chr_rom_len = Self::parse_nes2_rom_len(bytes[5], bytes[9] >> 4, CHR_ROM_BANK_LEN);
+
+        if prg_rom_len == 0 {
+            return Err(anyhow!("header declares no PRG ROM"));
+        }
+
+        let timing = match bytes[12] & 0b11 {
+            0 => Timing::Ntsc,
+            1 => Timing::Pal,
+            2 => Timing::MultiRegion,
+            _ => Timing::Dendy,
+        };
+
+        let console_type = match flags_7 & 0b11 {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice,
+            _ => ConsoleType::Extended(bytes[13] & 0x0F),
+        };
+
+        Ok(Self {
+            format: HeaderFormat::Nes2,
+            prg_rom_len,
+            chr_rom_len,
+            prg_ram_len: Self::parse_nes2_ram_len(bytes[10] & 0x0F),
+            prg_nvram_len: Self::parse_nes2_ram_len(bytes[10] >> 4),
+            chr_ram_len: Self::parse_nes2_ram_len(bytes[11] & 0x0F),
+            chr_nvram_len: Self::parse_nes2_ram_len(bytes[11] >> 4),
+            mapper: ((bytes[8] & 0x0F) as u16) << 8 | ((flags_7 & 0xF0) | (flags_6 >> 4)) as u16,
+            submapper: bytes[8] >> 4,
+            mirroring: Self::parse_mirroring(flags_6),
+            battery: flags_6.is_bit_set(1),
+            trainer: flags_6.is_bit_set(2),
+            timing,
+            console_type,
+            expansion_device: bytes[15] & 0b0011_1111,
+        })
+    }
+
+    fn parse_mirroring(flags_6: u8) -> Mirroring {
+        if flags_6.is_bit_set(3) {
+            Mirroring::FourScreen
+        } else if flags_6.is_bit_set(0) {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        }
+    }
+
+    /// Decodes a NES 2.0 ROM size, either a 12-bit bank count or, when the upper nibble
+    /// is $F, the exponent-multiplier notation `2^E * (MM * 2 + 1)` stored as `EEEEEEMM`.
+    fn parse_nes2_rom_len(lsb: u8, msb: u8, bank_len: usize) -> usize {
+        if msb == 0x0F {
+            let exponent = (lsb >> 2) as u32;
+            let multiplier = (lsb & 0b11) as usize * 2 + 1;
+            2usize.checked_pow(exponent).map_or(usize::MAX, |len| len.saturating_mul(multiplier))
+        } else {
+            ((msb as usize) << 8 | lsb as usize) * bank_len
+        }
+    }
+
+    /// Decodes a NES 2.0 RAM size stored as a shift count, `64 << shift` or none at all.
+    fn parse_nes2_ram_len(shift: u8) -> usize {
+        if shift == 0 {
+            0
+        } else {
+            64 << shift
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HeaderFormat {
+    INes,
+    Nes2,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    FourScreen,
+    SingleScreenLower,
+    SingleScreenUpper,
+    /// CIRAM page for each of the four nametables, for boards that arrange them freely.
+    Custom([u8; 4]),
+}
+
+impl Mirroring {
+    /// Nametable memory page (1K) backing the nametable at PPU address `address`.
+    pub fn page(self, address: u16) -> usize {
+        let nametable = ((address >> 10) & 0b11) as usize;
+
+        match self {
+            Mirroring::Horizontal => nametable >> 1,
+            Mirroring::Vertical => nametable & 1,
+            Mirroring::FourScreen => nametable,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            Mirroring::Custom(pages) => pages[nametable] as usize,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ConsoleType {
+    Nes,
+    VsSystem,
+    Playchoice,
+    /// Extended console type as numbered by the NES 2.0 specification.
+    Extended(u8),
+}
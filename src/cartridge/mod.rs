@@ -0,0 +1,58 @@
+mod header;
+mod tests;
+
+pub use self::header::{Header, HeaderFormat, Mirroring, Timing, ConsoleType};
+
+use self::header::{HEADER_LEN, TRAINER_LEN};
+use crate::types::Result;
+use std::fs;
+use std::path::Path;
+
+#[derive(Getters)]
+#[getset(get = "pub")]
+pub struct Cartridge {
+    header: Header,
+    trainer: Option<Vec<u8>>,
+    prg_rom: Vec<u8>,
+    chr_rom: Vec<u8>,
+}
+
+impl Cartridge {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|e| anyhow!("failed to read ROM `{}`: {}", path.display(), e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let header = Header::parse(bytes)?;
+
+        let trainer_len = if header.trainer() { TRAINER_LEN } else { 0 };
+        let prg_rom_start = HEADER_LEN + trainer_len;
+        let chr_rom_start = prg_rom_start.saturating_add(header.prg_rom_len());
+        let len_expected = chr_rom_start.saturating_add(header.chr_rom_len());
+
+        if bytes.len() < len_expected {
+            return Err(anyhow!("ROM truncated: header declares {} bytes, found {}", len_expected, bytes.len()));
+        }
+
+        let trainer = if header.trainer() {
+            Some(bytes[HEADER_LEN..prg_rom_start].to_vec())
+        } else {
+            None
+        };
+
+        Ok(Self {
+            trainer,
+            prg_rom: bytes[prg_rom_start..chr_rom_start].to_vec(),
+            chr_rom: bytes[chr_rom_start..len_expected].to_vec(),
+            header,
+        })
+    }
+
+    /// Splits the cartridge into its PRG and CHR ROM, to be owned by a mapper.
+    pub fn into_rom(self) -> (Vec<u8>, Vec<u8>) {
+        (self.prg_rom, self.chr_rom)
+    }
+}
@@ -0,0 +1,201 @@
+#![cfg(test)]
+
+use super::*;
+use crate::cpu::clock::ClockMode;
+use crate::types::BitRead;
+
+const PRG_ROM_BANKS: u8 = 2;
+const CHR_ROM_BANKS: u8 = 1;
+
+fn header_bytes(prg_rom_banks: u8, chr_rom_banks: u8, flags_6: u8, flags_7: u8) -> Vec<u8> {
+    let mut bytes = b"NES\x1A".to_vec();
+    bytes.extend(&[prg_rom_banks, chr_rom_banks, flags_6, flags_7]);
+    bytes.resize(HEADER_LEN, 0);
+    bytes
+}
+
+fn rom(flags_6: u8, flags_7: u8) -> Vec<u8> {
+    let mut bytes = header_bytes(PRG_ROM_BANKS, CHR_ROM_BANKS, flags_6, flags_7);
+
+    if flags_6.is_bit_set(2) {
+        bytes.extend(vec![0x77; TRAINER_LEN]);
+    }
+
+    let prg_rom_start = bytes.len();
+    bytes.resize(prg_rom_start + PRG_ROM_BANKS as usize * 0x4000, 0);
+    bytes[prg_rom_start] = 0x11;
+    bytes[prg_rom_start + 0x7FFC] = 0x00;
+    bytes[prg_rom_start + 0x7FFD] = 0x80;
+
+    let chr_rom_start = bytes.len();
+    bytes.resize(chr_rom_start + CHR_ROM_BANKS as usize * 0x2000, 0);
+    bytes[chr_rom_start] = 0x22;
+    bytes
+}
+
+#[test]
+fn parse_header() {
+    let header = Header::parse(&header_bytes(2, 1, 0b0001_0011, 0b0100_0000)).unwrap();
+    assert_eq!(header.prg_rom_len(), 0x8000);
+    assert_eq!(header.chr_rom_len(), 0x2000);
+    assert_eq!(header.mapper(), 0x41);
+    assert_eq!(header.mirroring(), Mirroring::Vertical);
+    assert!(header.battery());
+    assert!(!header.trainer());
+}
+
+#[test]
+fn parse_header_mirroring() {
+    let header = Header::parse(&header_bytes(1, 1, 0b0000_0000, 0)).unwrap();
+    assert_eq!(header.mirroring(), Mirroring::Horizontal);
+
+    let header = Header::parse(&header_bytes(1, 1, 0b0000_1001, 0)).unwrap();
+    assert_eq!(header.mirroring(), Mirroring::FourScreen);
+}
+
+#[test]
+fn parse_header_garbage_ignores_flags_7() {
+    let mut bytes = header_bytes(1, 1, 0b0010_0000, 0b0100_0000);
+    bytes[7..16].copy_from_slice(b"DiskDude!");
+
+    let header = Header::parse(&bytes).unwrap();
+    assert_eq!(header.mapper(), 0x02);
+}
+
+#[test]
+fn parse_header_too_short() {
+    assert!(Header::parse(b"NES\x1A").is_err());
+}
+
+#[test]
+fn parse_header_invalid_magic() {
+    let mut bytes = header_bytes(1, 1, 0, 0);
+    bytes[3] = 0x00;
+    assert!(Header::parse(&bytes).is_err());
+}
+
+#[test]
+fn parse_header_no_prg_rom() {
+    assert!(Header::parse(&header_bytes(0, 1, 0, 0)).is_err());
+}
+
+#[test]
+fn cartridge_from_bytes() {
+    let cartridge = Cartridge::from_bytes(&rom(0, 0)).unwrap();
+    assert!(cartridge.trainer().is_none());
+    assert_eq!(cartridge.prg_rom().len(), 0x8000);
+    assert_eq!(cartridge.prg_rom()[0], 0x11);
+    assert_eq!(cartridge.chr_rom().len(), 0x2000);
+    assert_eq!(cartridge.chr_rom()[0], 0x22);
+}
+
+#[test]
+fn cartridge_from_bytes_trainer() {
+    let cartridge = Cartridge::from_bytes(&rom(0b0000_0100, 0)).unwrap();
+    assert_eq!(cartridge.trainer().as_ref().unwrap(), &vec![0x77; TRAINER_LEN]);
+    assert_eq!(cartridge.prg_rom()[0], 0x11);
+    assert_eq!(cartridge.chr_rom()[0], 0x22);
+}
+
+#[test]
+fn cartridge_from_bytes_truncated() {
+    let mut bytes = rom(0, 0);
+    bytes.pop();
+    assert!(Cartridge::from_bytes(&bytes).is_err());
+}
+
+fn header_bytes_nes2(bytes_8_15: [u8; 8]) -> Vec<u8> {
+    let mut bytes = header_bytes(2, 1, 0b0100_0010, 0b0001_1000);
+    bytes[8..16].copy_from_slice(&bytes_8_15);
+    bytes
+}
+
+#[test]
+fn parse_header_ines_defaults() {
+    let header = Header::parse(&header_bytes(1, 0, 0, 0)).unwrap();
+    assert_eq!(header.format(), HeaderFormat::INes);
+    assert_eq!(header.prg_ram_len(), 0x2000);
+    assert_eq!(header.prg_nvram_len(), 0);
+    assert_eq!(header.chr_ram_len(), 0x2000);
+    assert_eq!(header.timing(), Timing::Ntsc);
+    assert_eq!(header.clock_mode(), ClockMode::Ntsc);
+    assert_eq!(header.console_type(), ConsoleType::Nes);
+}
+
+#[test]
+fn parse_header_ines_battery() {
+    let header = Header::parse(&header_bytes(1, 1, 0b0000_0010, 0)).unwrap();
+    assert_eq!(header.prg_ram_len(), 0);
+    assert_eq!(header.prg_nvram_len(), 0x2000);
+    assert_eq!(header.chr_ram_len(), 0);
+}
+
+#[test]
+fn parse_header_nes2() {
+    let header = Header::parse(&header_bytes_nes2([0x52, 0x00, 0x70, 0x07, 0x01, 0x00, 0x00, 0x01])).unwrap();
+    assert_eq!(header.format(), HeaderFormat::Nes2);
+    assert_eq!(header.mapper(), 0x214);
+    assert_eq!(header.submapper(), 5);
+    assert_eq!(header.prg_rom_len(), 0x8000);
+    assert_eq!(header.chr_rom_len(), 0x2000);
+    assert_eq!(header.prg_ram_len(), 0);
+    assert_eq!(header.prg_nvram_len(), 0x2000);
+    assert_eq!(header.chr_ram_len(), 0x2000);
+    assert_eq!(header.chr_nvram_len(), 0);
+    assert!(header.battery());
+    assert_eq!(header.timing(), Timing::Pal);
+    assert_eq!(header.clock_mode(), ClockMode::Pal);
+    assert_eq!(header.console_type(), ConsoleType::Nes);
+    assert_eq!(header.expansion_device(), 1);
+}
+
+#[test]
+fn parse_header_nes2_timing() {
+    let header = Header::parse(&header_bytes_nes2([0, 0, 0, 0, 0x02, 0, 0, 0])).unwrap();
+    assert_eq!(header.timing(), Timing::MultiRegion);
+    assert_eq!(header.clock_mode(), ClockMode::Ntsc);
+
+    let header = Header::parse(&header_bytes_nes2([0, 0, 0, 0, 0x03, 0, 0, 0])).unwrap();
+    assert_eq!(header.timing(), Timing::Dendy);
+    assert_eq!(header.clock_mode(), ClockMode::Dendy);
+}
+
+#[test]
+fn parse_header_nes2_console_type() {
+    let mut bytes = header_bytes_nes2([0, 0, 0, 0, 0, 0x03, 0, 0]);
+    bytes[7] |= 0b01;
+    assert_eq!(Header::parse(&bytes).unwrap().console_type(), ConsoleType::VsSystem);
+
+    bytes[7] |= 0b11;
+    assert_eq!(Header::parse(&bytes).unwrap().console_type(), ConsoleType::Extended(3));
+}
+
+#[test]
+fn parse_header_nes2_rom_len_msb() {
+    let header = Header::parse(&header_bytes_nes2([0, 0x21, 0, 0, 0, 0, 0, 0])).unwrap();
+    assert_eq!(header.prg_rom_len(), 0x102 * 0x4000);
+    assert_eq!(header.chr_rom_len(), 0x201 * 0x2000);
+}
+
+#[test]
+fn parse_header_nes2_rom_len_exponent() {
+    let mut bytes = header_bytes_nes2([0, 0x0F, 0, 0, 0, 0, 0, 0]);
+    // 2^4 * (1 * 2 + 1)
+    bytes[4] = 0b0001_0001;
+
+    let header = Header::parse(&bytes).unwrap();
+    assert_eq!(header.prg_rom_len(), 48);
+}
+
+#[test]
+fn parse_header_nes2_ignores_garbage_heuristic() {
+    let header = Header::parse(&header_bytes_nes2([0, 0, 0, 0, 0, 0, 0x01, 0])).unwrap();
+    assert_eq!(header.mapper(), 0x14);
+}
+
+#[test]
+fn cartridge_from_bytes_rom_len_unaddressable() {
+    let mut bytes = header_bytes_nes2([0, 0x0F, 0, 0, 0, 0, 0, 0]);
+    bytes[4] = 0xFF;
+    assert!(Cartridge::from_bytes(&bytes).is_err());
+}
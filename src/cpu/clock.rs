@@ -1,13 +1,17 @@
 #[derive(CopyGetters)]
 #[getset(get_copy = "pub")]
 pub struct Clock {
+    mode: ClockMode,
+    /// CPU cycles per second.
     speed: u32,
+    /// CPU cycles since power-on.
     cycles: u64,
 }
 
 impl Clock {
     pub fn new(mode: ClockMode) -> Self {
         Self {
+            mode,
             speed: Self::determine_speed(mode),
             cycles: 0,
         }
@@ -17,18 +21,45 @@ impl Clock {
         self.cycles += cycles as u64;
     }
 
+    /// Master clock cycles since power-on, the timestamp all components are scheduled by.
+    pub fn master_cycles(&self) -> u64 {
+        self.cycles * self.mode.cpu_divider()
+    }
+
     fn determine_speed(mode: ClockMode) -> u32 {
         match mode {
-            ClockMode::Ntsc => 1 / 1_789_773,
-            ClockMode::Pal => 1 / 1_662_607,
-            ClockMode::Dendy => 1 / 1_773_448,
+            ClockMode::Ntsc => 1_789_773,
+            ClockMode::Pal => 1_662_607,
+            ClockMode::Dendy => 1_773_448,
         }
     }
 }
 
-#[derive(Copy, Clone)]
+/// The CPU and the PPU both divide down one master clock: by 12 and 4 on NTSC, giving 3 dots
+/// per CPU cycle, and by 16 and 5 on PAL, giving 3.2. Dendy clones run the PAL crystal with
+/// dividers of 15 and 5, so they are back at 3 dots per CPU cycle.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ClockMode {
     Ntsc,
     Pal,
     Dendy,
 }
+
+impl ClockMode {
+    /// Master clock cycles per CPU cycle.
+    pub fn cpu_divider(self) -> u64 {
+        match self {
+            ClockMode::Ntsc => 12,
+            ClockMode::Pal => 16,
+            ClockMode::Dendy => 15,
+        }
+    }
+
+    /// Master clock cycles per PPU dot.
+    pub fn ppu_divider(self) -> u64 {
+        match self {
+            ClockMode::Ntsc => 4,
+            ClockMode::Pal | ClockMode::Dendy => 5,
+        }
+    }
+}
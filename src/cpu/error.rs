@@ -0,0 +1,41 @@
+use std::error::Error;
+use std::fmt;
+
+pub type CpuResult<T = ()> = std::result::Result<T, CpuError>;
+
+/// Why the CPU stopped executing, until it is reset.
+///
+/// There is no bus fault: unmapped reads return open bus and writes are dropped, so every
+/// access succeeds, including fetches next to the interrupt vectors.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CpuError {
+    /// An undocumented opcode, refused under `IllegalOpcodePolicy::Halt`.
+    InvalidOpcode { opcode: u8, address: u16 },
+    /// A KIL opcode locked the CPU up, while the rest of the system keeps running.
+    Jammed { address: u16 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::InvalidOpcode { opcode, address } => {
+                write!(f, "invalid opcode ${:02X} at ${:04X}", opcode, address)
+            },
+            CpuError::Jammed { address } => write!(f, "CPU jammed at ${:04X}", address),
+        }
+    }
+}
+
+impl Error for CpuError {}
+
+/// What the CPU does with the opcodes its variant doesn't document: the NMOS undocumented
+/// instructions, the duplicate NOPs and SBC, and the unused 65C02 opcodes.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IllegalOpcodePolicy {
+    /// Stops with `CpuError::InvalidOpcode`, leaving PC at the opcode.
+    Halt,
+    /// Skips the instruction as a NOP reading its operand through the same addressing mode.
+    Nop,
+    /// Runs the instruction like the hardware does, KIL jamming the CPU.
+    Emulate,
+}
@@ -1,47 +1,145 @@
-use crate::bus::Bus;
-use crate::types::Result;
+use super::{CpuVariant, StatusFlags};
 
-#[derive(Debug, CopyGetters)]
+/// Instructions of the 2A03 and the NMOS 6502, which share their opcodes, indexed by opcode.
+static INSTRUCTIONS_NMOS: [Instruction; 256] = Instruction::table(CpuVariant::Nmos6502);
+/// Instructions of the 65C02, indexed by opcode.
+static INSTRUCTIONS_CMOS: [Instruction; 256] = Instruction::table(CpuVariant::Cmos65C02);
+
+#[derive(Debug, Copy, Clone, CopyGetters)]
 #[getset(get_copy = "pub")]
 pub struct Instruction {
+    mnemonic: &'static str,
     operation: InstructionOperation,
     mode: InstructionMode,
-    len: u8,
+    len_bytes: u8,
     cycles_base: u8,
+    /// Whether an indexed read takes a cycle more when indexing crosses a page.
+    page_penalty: bool,
+    /// Flags the outcome depends on, P as a whole for the instructions pushing it.
+    flags_read: StatusFlags,
+    /// Flags that may be changed, P as a whole for the instructions pulling it.
+    flags_written: StatusFlags,
+    /// Whether the opcode is documented by the manufacturer of the variant.
+    official: bool,
 }
 
 macro_rules! instruction {
-    ($operation:ident, $mode:ident, $cycles_base:literal) => {{
+    ($operation:ident, $mode:ident, $cycles_base:literal) => {
+        instruction!($operation, $mode, $cycles_base, false)
+    };
+    ($operation:ident, $mode:ident, $cycles_base:literal, $page_penalty:literal) => {{
+        let operation = InstructionOperation::$operation;
         let mode = InstructionMode::$mode;
 
         Instruction {
-            operation: InstructionOperation::$operation,
+            mnemonic: operation.mnemonic(),
+            operation,
             mode,
-            len: mode.len_bytes(),
+            len_bytes: mode.len_bytes(),
             cycles_base: $cycles_base,
+            page_penalty: $page_penalty,
+            flags_read: operation.flags_read(),
+            flags_written: operation.flags_written(),
+            official: operation.is_official(),
         }
     }};
 }
 
+/// A set of status flags, usable in constants.
+macro_rules! flags {
+    ($($flag:ident),*) => {
+        StatusFlags::from_bits_truncate(0 $(| StatusFlags::$flag.bits())*)
+    };
+}
+
+/// All of P, leaving out the break bits, which have no storage.
+const FLAGS_ALL: StatusFlags = flags!(NEGATIVE, OVERFLOW, DECIMAL, INTERRUPT_DISABLE, ZERO, CARRY);
+
 impl Instruction {
-    pub fn from_opcode(opcode: u8) -> Instruction {
+    pub fn from_opcode(opcode: u8, variant: CpuVariant) -> Instruction {
+        Self::table_for(variant)[opcode as usize]
+    }
+
+    /// The instruction turned into a NOP that reads its operand through the same addressing
+    /// mode, so it skips the same bytes.
+    pub fn as_nop(self) -> Instruction {
+        let page_penalty = matches!(self.mode,
+            InstructionMode::AbsoluteX | InstructionMode::AbsoluteY | InstructionMode::IndirectY
+        );
+
+        Instruction {
+            mnemonic: InstructionOperation::Nop.mnemonic(),
+            operation: InstructionOperation::Nop,
+            cycles_base: self.mode.cycles_read(),
+            page_penalty,
+            flags_read: StatusFlags::empty(),
+            flags_written: StatusFlags::empty(),
+            ..self
+        }
+    }
+
+    /// Every instruction of a variant, indexed by opcode.
+    pub fn table_for(variant: CpuVariant) -> &'static [Instruction; 256] {
+        match variant {
+            CpuVariant::Ricoh2A03 | CpuVariant::Nmos6502 => &INSTRUCTIONS_NMOS,
+            CpuVariant::Cmos65C02 => &INSTRUCTIONS_CMOS,
+        }
+    }
+
+    const fn table(variant: CpuVariant) -> [Instruction; 256] {
+        let mut table = [instruction!(Kil, Implied, 0); 256];
+        let mut opcode = 0;
+
+        while opcode < table.len() {
+            table[opcode] = Self::decode(opcode as u8, variant);
+            opcode += 1;
+        }
+
+        table
+    }
+
+    /// Decodes an opcode, with the metadata that depends on more than the operation fixed up.
+    const fn decode(opcode: u8, variant: CpuVariant) -> Instruction {
+        let mut instruction = match variant {
+            CpuVariant::Ricoh2A03 | CpuVariant::Nmos6502 => Self::decode_nmos(opcode),
+            CpuVariant::Cmos65C02 => Self::decode_cmos(opcode),
+        };
+
+        match (instruction.operation, instruction.mode, variant) {
+            // only $EA is documented among the NOPs, and $E9 among the SBCs
+            (InstructionOperation::Nop, _, _) => instruction.official = opcode == 0xEA,
+            (InstructionOperation::Sbc, _, _) => instruction.official = opcode != 0xEB,
+            // BIT #imm has no memory operand for N and V to come from
+            (InstructionOperation::Bit, InstructionMode::Immediate, _) => {
+                instruction.flags_written = flags!(ZERO);
+            },
+            (InstructionOperation::Brk, _, CpuVariant::Cmos65C02) => {
+                instruction.flags_written = flags!(DECIMAL, INTERRUPT_DISABLE);
+            },
+            _ => {},
+        }
+
+        instruction
+    }
+
+    const fn decode_nmos(opcode: u8) -> Instruction {
         match opcode {
             0x69 => instruction!(Adc, Immediate,   2),
             0x65 => instruction!(Adc, ZeroPage,    3),
             0x75 => instruction!(Adc, ZeroPageX,   4),
             0x6D => instruction!(Adc, Absolute,    4),
-            0x7D => instruction!(Adc, AbsoluteX,   4),
-            0x79 => instruction!(Adc, AbsoluteY,   4),
+            0x7D => instruction!(Adc, AbsoluteX,   4, true),
+            0x79 => instruction!(Adc, AbsoluteY,   4, true),
             0x61 => instruction!(Adc, IndirectX,   6),
-            0x71 => instruction!(Adc, IndirectY,   5),
+            0x71 => instruction!(Adc, IndirectY,   5, true),
             0x29 => instruction!(And, Immediate,   2),
             0x25 => instruction!(And, ZeroPage,    3),
             0x35 => instruction!(And, ZeroPageX,   4),
             0x2D => instruction!(And, Absolute,    4),
-            0x3D => instruction!(And, AbsoluteX,   4),
-            0x39 => instruction!(And, AbsoluteY,   4),
+            0x3D => instruction!(And, AbsoluteX,   4, true),
+            0x39 => instruction!(And, AbsoluteY,   4, true),
             0x21 => instruction!(And, IndirectX,   6),
-            0x31 => instruction!(And, IndirectY,   5),
+            0x31 => instruction!(And, IndirectY,   5, true),
             0x0A => instruction!(Asl, Accumulator, 2),
             0x06 => instruction!(Asl, ZeroPage,    5),
             0x16 => instruction!(Asl, ZeroPageX,   6),
@@ -58,18 +156,18 @@ impl Instruction {
             0x00 => instruction!(Brk, Implied,     7),
             0x50 => instruction!(Bvc, Relative,    2),
             0x70 => instruction!(Bvs, Relative,    2),
-            0x18 => instruction!(Clc, Implied,     1),
-            0xD8 => instruction!(Cld, Implied,     1),
-            0x58 => instruction!(Cli, Implied,     1),
-            0xB8 => instruction!(Clv, Implied,     1),
+            0x18 => instruction!(Clc, Implied,     2),
+            0xD8 => instruction!(Cld, Implied,     2),
+            0x58 => instruction!(Cli, Implied,     2),
+            0xB8 => instruction!(Clv, Implied,     2),
             0xC9 => instruction!(Cmp, Immediate,   2),
             0xC5 => instruction!(Cmp, ZeroPage,    3),
             0xD5 => instruction!(Cmp, ZeroPageX,   4),
             0xCD => instruction!(Cmp, Absolute,    4),
-            0xDD => instruction!(Cmp, AbsoluteX,   4),
-            0xD9 => instruction!(Cmp, AbsoluteY,   4),
+            0xDD => instruction!(Cmp, AbsoluteX,   4, true),
+            0xD9 => instruction!(Cmp, AbsoluteY,   4, true),
             0xC1 => instruction!(Cmp, IndirectX,   6),
-            0xD1 => instruction!(Cmp, IndirectY,   5),
+            0xD1 => instruction!(Cmp, IndirectY,   5, true),
             0xE0 => instruction!(Cpx, Immediate,   2),
             0xE4 => instruction!(Cpx, ZeroPage,    3),
             0xEC => instruction!(Cpx, Absolute,    4),
@@ -86,10 +184,10 @@ impl Instruction {
             0x45 => instruction!(Eor, ZeroPage,    3),
             0x55 => instruction!(Eor, ZeroPageX,   4),
             0x4D => instruction!(Eor, Absolute,    4),
-            0x5D => instruction!(Eor, AbsoluteX,   4),
-            0x59 => instruction!(Eor, AbsoluteY,   4),
+            0x5D => instruction!(Eor, AbsoluteX,   4, true),
+            0x59 => instruction!(Eor, AbsoluteY,   4, true),
             0x41 => instruction!(Eor, IndirectX,   6),
-            0x51 => instruction!(Eor, IndirectY,   5),
+            0x51 => instruction!(Eor, IndirectY,   5, true),
             0xE6 => instruction!(Inc, ZeroPage,    5),
             0xF6 => instruction!(Inc, ZeroPageX,   6),
             0xEE => instruction!(Inc, Absolute,    6),
@@ -103,20 +201,20 @@ impl Instruction {
             0xA5 => instruction!(Lda, ZeroPage,    3),
             0xB5 => instruction!(Lda, ZeroPageX,   4),
             0xAD => instruction!(Lda, Absolute,    4),
-            0xBD => instruction!(Lda, AbsoluteX,   4),
-            0xB9 => instruction!(Lda, AbsoluteY,   4),
+            0xBD => instruction!(Lda, AbsoluteX,   4, true),
+            0xB9 => instruction!(Lda, AbsoluteY,   4, true),
             0xA1 => instruction!(Lda, IndirectX,   6),
-            0xB1 => instruction!(Lda, IndirectY,   5),
+            0xB1 => instruction!(Lda, IndirectY,   5, true),
             0xA2 => instruction!(Ldx, Immediate,   2),
             0xA6 => instruction!(Ldx, ZeroPage,    3),
             0xB6 => instruction!(Ldx, ZeroPageY,   4),
             0xAE => instruction!(Ldx, Absolute,    4),
-            0xBE => instruction!(Ldx, AbsoluteY,   4),
+            0xBE => instruction!(Ldx, AbsoluteY,   4, true),
             0xA0 => instruction!(Ldy, Immediate,   2),
             0xA4 => instruction!(Ldy, ZeroPage,    3),
             0xB4 => instruction!(Ldy, ZeroPageX,   4),
             0xAC => instruction!(Ldy, Absolute,    4),
-            0xBC => instruction!(Ldy, AbsoluteX,   4),
+            0xBC => instruction!(Ldy, AbsoluteX,   4, true),
             0x4A => instruction!(Lsr, Accumulator, 2),
             0x46 => instruction!(Lsr, ZeroPage,    5),
             0x56 => instruction!(Lsr, ZeroPageX,   6),
@@ -127,10 +225,10 @@ impl Instruction {
             0x05 => instruction!(Ora, ZeroPage,    3),
             0x15 => instruction!(Ora, ZeroPageX,   4),
             0x0D => instruction!(Ora, Absolute,    4),
-            0x1D => instruction!(Ora, AbsoluteX,   4),
-            0x19 => instruction!(Ora, AbsoluteY,   4),
+            0x1D => instruction!(Ora, AbsoluteX,   4, true),
+            0x19 => instruction!(Ora, AbsoluteY,   4, true),
             0x01 => instruction!(Ora, IndirectX,   6),
-            0x11 => instruction!(Ora, IndirectY,   5),
+            0x11 => instruction!(Ora, IndirectY,   5, true),
             0x48 => instruction!(Pha, Implied,     3),
             0x08 => instruction!(Php, Implied,     3),
             0x68 => instruction!(Pla, Implied,     4),
@@ -151,10 +249,10 @@ impl Instruction {
             0xE5 => instruction!(Sbc, ZeroPage,    3),
             0xF5 => instruction!(Sbc, ZeroPageX,   4),
             0xED => instruction!(Sbc, Absolute,    4),
-            0xFD => instruction!(Sbc, AbsoluteX,   4),
-            0xF9 => instruction!(Sbc, AbsoluteY,   4),
+            0xFD => instruction!(Sbc, AbsoluteX,   4, true),
+            0xF9 => instruction!(Sbc, AbsoluteY,   4, true),
             0xE1 => instruction!(Sbc, IndirectX,   6),
-            0xF1 => instruction!(Sbc, IndirectY,   5),
+            0xF1 => instruction!(Sbc, IndirectY,   5, true),
             0x38 => instruction!(Sec, Implied,     2),
             0xF8 => instruction!(Sed, Implied,     2),
             0x78 => instruction!(Sei, Implied,     2),
@@ -177,7 +275,128 @@ impl Instruction {
             0x8A => instruction!(Txa, Implied,     2),
             0x9A => instruction!(Txs, Implied,     2),
             0x98 => instruction!(Tya, Implied,     2),
-            _ => unimplemented!("no instruction found for opcode `${:02X}`", opcode),
+
+            // unofficial
+            0x4B => instruction!(Alr, Immediate,   2),
+            0x0B => instruction!(Anc, Immediate,   2),
+            0x2B => instruction!(Anc, Immediate,   2),
+            0x6B => instruction!(Arr, Immediate,   2),
+            0xCB => instruction!(Axs, Immediate,   2),
+            0xC7 => instruction!(Dcp, ZeroPage,    5),
+            0xD7 => instruction!(Dcp, ZeroPageX,   6),
+            0xCF => instruction!(Dcp, Absolute,    6),
+            0xDF => instruction!(Dcp, AbsoluteX,   7),
+            0xDB => instruction!(Dcp, AbsoluteY,   7),
+            0xC3 => instruction!(Dcp, IndirectX,   8),
+            0xD3 => instruction!(Dcp, IndirectY,   8),
+            0xE7 => instruction!(Isc, ZeroPage,    5),
+            0xF7 => instruction!(Isc, ZeroPageX,   6),
+            0xEF => instruction!(Isc, Absolute,    6),
+            0xFF => instruction!(Isc, AbsoluteX,   7),
+            0xFB => instruction!(Isc, AbsoluteY,   7),
+            0xE3 => instruction!(Isc, IndirectX,   8),
+            0xF3 => instruction!(Isc, IndirectY,   8),
+            0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xB2 | 0xD2 | 0xF2 => {
+                instruction!(Kil, Implied, 2)
+            },
+            0xBB => instruction!(Las, AbsoluteY,   4, true),
+            0xA7 => instruction!(Lax, ZeroPage,    3),
+            0xB7 => instruction!(Lax, ZeroPageY,   4),
+            0xAF => instruction!(Lax, Absolute,    4),
+            0xBF => instruction!(Lax, AbsoluteY,   4, true),
+            0xA3 => instruction!(Lax, IndirectX,   6),
+            0xB3 => instruction!(Lax, IndirectY,   5, true),
+            0xAB => instruction!(Lxa, Immediate,   2),
+            0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA => instruction!(Nop, Implied, 2),
+            0x80 | 0x82 | 0x89 | 0xC2 | 0xE2 => instruction!(Nop, Immediate, 2),
+            0x04 | 0x44 | 0x64 => instruction!(Nop, ZeroPage, 3),
+            0x14 | 0x34 | 0x54 | 0x74 | 0xD4 | 0xF4 => instruction!(Nop, ZeroPageX, 4),
+            0x0C => instruction!(Nop, Absolute,    4),
+            0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => instruction!(Nop, AbsoluteX, 4, true),
+            0x27 => instruction!(Rla, ZeroPage,    5),
+            0x37 => instruction!(Rla, ZeroPageX,   6),
+            0x2F => instruction!(Rla, Absolute,    6),
+            0x3F => instruction!(Rla, AbsoluteX,   7),
+            0x3B => instruction!(Rla, AbsoluteY,   7),
+            0x23 => instruction!(Rla, IndirectX,   8),
+            0x33 => instruction!(Rla, IndirectY,   8),
+            0x67 => instruction!(Rra, ZeroPage,    5),
+            0x77 => instruction!(Rra, ZeroPageX,   6),
+            0x6F => instruction!(Rra, Absolute,    6),
+            0x7F => instruction!(Rra, AbsoluteX,   7),
+            0x7B => instruction!(Rra, AbsoluteY,   7),
+            0x63 => instruction!(Rra, IndirectX,   8),
+            0x73 => instruction!(Rra, IndirectY,   8),
+            0x87 => instruction!(Sax, ZeroPage,    3),
+            0x97 => instruction!(Sax, ZeroPageY,   4),
+            0x8F => instruction!(Sax, Absolute,    4),
+            0x83 => instruction!(Sax, IndirectX,   6),
+            0xEB => instruction!(Sbc, Immediate,   2),
+            0x9F => instruction!(Sha, AbsoluteY,   5),
+            0x93 => instruction!(Sha, IndirectY,   6),
+            0x9E => instruction!(Shx, AbsoluteY,   5),
+            0x9C => instruction!(Shy, AbsoluteX,   5),
+            0x07 => instruction!(Slo, ZeroPage,    5),
+            0x17 => instruction!(Slo, ZeroPageX,   6),
+            0x0F => instruction!(Slo, Absolute,    6),
+            0x1F => instruction!(Slo, AbsoluteX,   7),
+            0x1B => instruction!(Slo, AbsoluteY,   7),
+            0x03 => instruction!(Slo, IndirectX,   8),
+            0x13 => instruction!(Slo, IndirectY,   8),
+            0x47 => instruction!(Sre, ZeroPage,    5),
+            0x57 => instruction!(Sre, ZeroPageX,   6),
+            0x4F => instruction!(Sre, Absolute,    6),
+            0x5F => instruction!(Sre, AbsoluteX,   7),
+            0x5B => instruction!(Sre, AbsoluteY,   7),
+            0x43 => instruction!(Sre, IndirectX,   8),
+            0x53 => instruction!(Sre, IndirectY,   8),
+            0x9B => instruction!(Tas, AbsoluteY,   5),
+            0x8B => instruction!(Xaa, Immediate,   2),
+        }
+    }
+
+    /// The 65C02 keeps the documented NMOS opcodes, and turns the undocumented ones into new
+    /// instructions or NOPs, those in columns 3, 7, B and F taking a single cycle.
+    const fn decode_cmos(opcode: u8) -> Instruction {
+        match opcode {
+            0x72 => instruction!(Adc, ZeroPageIndirect,         5),
+            0x32 => instruction!(And, ZeroPageIndirect,         5),
+            0x89 => instruction!(Bit, Immediate,                2),
+            0x34 => instruction!(Bit, ZeroPageX,                4),
+            0x3C => instruction!(Bit, AbsoluteX,                4, true),
+            0x80 => instruction!(Bra, Relative,                 2),
+            0xD2 => instruction!(Cmp, ZeroPageIndirect,         5),
+            0x3A => instruction!(Dec, Accumulator,              2),
+            0x52 => instruction!(Eor, ZeroPageIndirect,         5),
+            0x1A => instruction!(Inc, Accumulator,              2),
+            0x6C => instruction!(Jmp, Indirect,                 6),
+            0x7C => instruction!(Jmp, AbsoluteIndexedIndirect,  6),
+            0xB2 => instruction!(Lda, ZeroPageIndirect,         5),
+            0x12 => instruction!(Ora, ZeroPageIndirect,         5),
+            0xDA => instruction!(Phx, Implied,                  3),
+            0x5A => instruction!(Phy, Implied,                  3),
+            0xFA => instruction!(Plx, Implied,                  4),
+            0x7A => instruction!(Ply, Implied,                  4),
+            0xF2 => instruction!(Sbc, ZeroPageIndirect,         5),
+            0x92 => instruction!(Sta, ZeroPageIndirect,         5),
+            0x64 => instruction!(Stz, ZeroPage,                 3),
+            0x74 => instruction!(Stz, ZeroPageX,                4),
+            0x9C => instruction!(Stz, Absolute,                 4),
+            0x9E => instruction!(Stz, AbsoluteX,                5),
+            0x14 => instruction!(Trb, ZeroPage,                 5),
+            0x1C => instruction!(Trb, Absolute,                 6),
+            0x04 => instruction!(Tsb, ZeroPage,                 5),
+            0x0C => instruction!(Tsb, Absolute,                 6),
+            0x02 | 0x22 | 0x42 | 0x62 | 0x82 | 0xC2 | 0xE2
+                => instruction!(Nop, Immediate, 2),
+            0x44 => instruction!(Nop, ZeroPage,                 3),
+            0x54 | 0xD4 | 0xF4
+                => instruction!(Nop, ZeroPageX, 4),
+            // the hardware spends 8 cycles on 0x5C, only the first 4 of which are modelled
+            0x5C | 0xDC | 0xFC
+                => instruction!(Nop, Absolute, 4),
+            _ if opcode & 0x03 == 0x03 => instruction!(Nop, Implied, 1),
+            _ => Self::decode_nmos(opcode),
         }
     }
 }
@@ -188,6 +407,254 @@ pub enum InstructionOperation {
     Cld, Cli, Clv, Cmp, Cpx, Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jmp,
     Jsr, Lda, Ldx, Ldy, Lsr, Nop, Ora, Pha, Php, Pla, Plp, Rol, Ror, Rti,
     Rts, Sbc, Sec, Sed, Sei, Sta, Stx, Sty, Tax, Tay, Tsx, Txa, Txs, Tya,
+
+    // unofficial
+    Alr, Anc, Arr, Axs, Dcp, Isc, Kil, Las, Lax, Lxa, Rla, Rra, Sax, Sha,
+    Shx, Shy, Slo, Sre, Tas, Xaa,
+
+    // 65C02
+    Bra, Phx, Phy, Plx, Ply, Stz, Trb, Tsb,
+}
+
+impl InstructionOperation {
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            InstructionOperation::Adc => "ADC",
+            InstructionOperation::And => "AND",
+            InstructionOperation::Asl => "ASL",
+            InstructionOperation::Bcc => "BCC",
+            InstructionOperation::Bcs => "BCS",
+            InstructionOperation::Beq => "BEQ",
+            InstructionOperation::Bit => "BIT",
+            InstructionOperation::Bmi => "BMI",
+            InstructionOperation::Bne => "BNE",
+            InstructionOperation::Bpl => "BPL",
+            InstructionOperation::Brk => "BRK",
+            InstructionOperation::Bvc => "BVC",
+            InstructionOperation::Bvs => "BVS",
+            InstructionOperation::Clc => "CLC",
+            InstructionOperation::Cld => "CLD",
+            InstructionOperation::Cli => "CLI",
+            InstructionOperation::Clv => "CLV",
+            InstructionOperation::Cmp => "CMP",
+            InstructionOperation::Cpx => "CPX",
+            InstructionOperation::Cpy => "CPY",
+            InstructionOperation::Dec => "DEC",
+            InstructionOperation::Dex => "DEX",
+            InstructionOperation::Dey => "DEY",
+            InstructionOperation::Eor => "EOR",
+            InstructionOperation::Inc => "INC",
+            InstructionOperation::Inx => "INX",
+            InstructionOperation::Iny => "INY",
+            InstructionOperation::Jmp => "JMP",
+            InstructionOperation::Jsr => "JSR",
+            InstructionOperation::Lda => "LDA",
+            InstructionOperation::Ldx => "LDX",
+            InstructionOperation::Ldy => "LDY",
+            InstructionOperation::Lsr => "LSR",
+            InstructionOperation::Nop => "NOP",
+            InstructionOperation::Ora => "ORA",
+            InstructionOperation::Pha => "PHA",
+            InstructionOperation::Php => "PHP",
+            InstructionOperation::Pla => "PLA",
+            InstructionOperation::Plp => "PLP",
+            InstructionOperation::Rol => "ROL",
+            InstructionOperation::Ror => "ROR",
+            InstructionOperation::Rti => "RTI",
+            InstructionOperation::Rts => "RTS",
+            InstructionOperation::Sbc => "SBC",
+            InstructionOperation::Sec => "SEC",
+            InstructionOperation::Sed => "SED",
+            InstructionOperation::Sei => "SEI",
+            InstructionOperation::Sta => "STA",
+            InstructionOperation::Stx => "STX",
+            InstructionOperation::Sty => "STY",
+            InstructionOperation::Tax => "TAX",
+            InstructionOperation::Tay => "TAY",
+            InstructionOperation::Tsx => "TSX",
+            InstructionOperation::Txa => "TXA",
+            InstructionOperation::Txs => "TXS",
+            InstructionOperation::Tya => "TYA",
+            InstructionOperation::Alr => "ALR",
+            InstructionOperation::Anc => "ANC",
+            InstructionOperation::Arr => "ARR",
+            InstructionOperation::Axs => "AXS",
+            InstructionOperation::Dcp => "DCP",
+            InstructionOperation::Isc => "ISC",
+            InstructionOperation::Kil => "KIL",
+            InstructionOperation::Las => "LAS",
+            InstructionOperation::Lax => "LAX",
+            InstructionOperation::Lxa => "LXA",
+            InstructionOperation::Rla => "RLA",
+            InstructionOperation::Rra => "RRA",
+            InstructionOperation::Sax => "SAX",
+            InstructionOperation::Sha => "SHA",
+            InstructionOperation::Shx => "SHX",
+            InstructionOperation::Shy => "SHY",
+            InstructionOperation::Slo => "SLO",
+            InstructionOperation::Sre => "SRE",
+            InstructionOperation::Tas => "TAS",
+            InstructionOperation::Xaa => "XAA",
+            InstructionOperation::Bra => "BRA",
+            InstructionOperation::Phx => "PHX",
+            InstructionOperation::Phy => "PHY",
+            InstructionOperation::Plx => "PLX",
+            InstructionOperation::Ply => "PLY",
+            InstructionOperation::Stz => "STZ",
+            InstructionOperation::Trb => "TRB",
+            InstructionOperation::Tsb => "TSB",
+        }
+    }
+
+    /// Whether the operation is documented, which the NMOS undocumented ones never are.
+    pub const fn is_official(self) -> bool {
+        !matches!(self,
+            InstructionOperation::Alr
+                | InstructionOperation::Anc
+                | InstructionOperation::Arr
+                | InstructionOperation::Axs
+                | InstructionOperation::Dcp
+                | InstructionOperation::Isc
+                | InstructionOperation::Kil
+                | InstructionOperation::Las
+                | InstructionOperation::Lax
+                | InstructionOperation::Lxa
+                | InstructionOperation::Rla
+                | InstructionOperation::Rra
+                | InstructionOperation::Sax
+                | InstructionOperation::Sha
+                | InstructionOperation::Shx
+                | InstructionOperation::Shy
+                | InstructionOperation::Slo
+                | InstructionOperation::Sre
+                | InstructionOperation::Tas
+                | InstructionOperation::Xaa
+        )
+    }
+
+    pub const fn flags_read(self) -> StatusFlags {
+        match self {
+            InstructionOperation::Adc
+                | InstructionOperation::Sbc
+                | InstructionOperation::Isc
+                | InstructionOperation::Rra
+                => flags!(DECIMAL, CARRY),
+            InstructionOperation::Bcc
+                | InstructionOperation::Bcs
+                | InstructionOperation::Rol
+                | InstructionOperation::Ror
+                | InstructionOperation::Arr
+                | InstructionOperation::Rla
+                => flags!(CARRY),
+            InstructionOperation::Beq | InstructionOperation::Bne => flags!(ZERO),
+            InstructionOperation::Bmi | InstructionOperation::Bpl => flags!(NEGATIVE),
+            InstructionOperation::Bvc | InstructionOperation::Bvs => flags!(OVERFLOW),
+            InstructionOperation::Brk | InstructionOperation::Php => FLAGS_ALL,
+            _ => StatusFlags::empty(),
+        }
+    }
+
+    pub const fn flags_written(self) -> StatusFlags {
+        match self {
+            InstructionOperation::And
+                | InstructionOperation::Dec
+                | InstructionOperation::Dex
+                | InstructionOperation::Dey
+                | InstructionOperation::Eor
+                | InstructionOperation::Inc
+                | InstructionOperation::Inx
+                | InstructionOperation::Iny
+                | InstructionOperation::Lda
+                | InstructionOperation::Ldx
+                | InstructionOperation::Ldy
+                | InstructionOperation::Ora
+                | InstructionOperation::Pla
+                | InstructionOperation::Tax
+                | InstructionOperation::Tay
+                | InstructionOperation::Tsx
+                | InstructionOperation::Txa
+                | InstructionOperation::Tya
+                | InstructionOperation::Las
+                | InstructionOperation::Lax
+                | InstructionOperation::Lxa
+                | InstructionOperation::Xaa
+                | InstructionOperation::Plx
+                | InstructionOperation::Ply
+                => flags!(NEGATIVE, ZERO),
+            InstructionOperation::Asl
+                | InstructionOperation::Cmp
+                | InstructionOperation::Cpx
+                | InstructionOperation::Cpy
+                | InstructionOperation::Lsr
+                | InstructionOperation::Rol
+                | InstructionOperation::Ror
+                | InstructionOperation::Alr
+                | InstructionOperation::Anc
+                | InstructionOperation::Axs
+                | InstructionOperation::Dcp
+                | InstructionOperation::Rla
+                | InstructionOperation::Slo
+                | InstructionOperation::Sre
+                => flags!(NEGATIVE, ZERO, CARRY),
+            InstructionOperation::Adc
+                | InstructionOperation::Sbc
+                | InstructionOperation::Arr
+                | InstructionOperation::Isc
+                | InstructionOperation::Rra
+                => flags!(NEGATIVE, OVERFLOW, ZERO, CARRY),
+            InstructionOperation::Bit => flags!(NEGATIVE, OVERFLOW, ZERO),
+            InstructionOperation::Trb | InstructionOperation::Tsb => flags!(ZERO),
+            InstructionOperation::Clc | InstructionOperation::Sec => flags!(CARRY),
+            InstructionOperation::Cld | InstructionOperation::Sed => flags!(DECIMAL),
+            InstructionOperation::Cli
+                | InstructionOperation::Sei
+                | InstructionOperation::Brk
+                => flags!(INTERRUPT_DISABLE),
+            InstructionOperation::Clv => flags!(OVERFLOW),
+            InstructionOperation::Plp | InstructionOperation::Rti => FLAGS_ALL,
+            _ => StatusFlags::empty(),
+        }
+    }
+
+    pub fn access(self) -> InstructionAccess {
+        match self {
+            InstructionOperation::Sta
+                | InstructionOperation::Stx
+                | InstructionOperation::Sty
+                | InstructionOperation::Sax
+                | InstructionOperation::Sha
+                | InstructionOperation::Shx
+                | InstructionOperation::Shy
+                | InstructionOperation::Tas
+                | InstructionOperation::Stz
+                => InstructionAccess::Write,
+            InstructionOperation::Asl
+                | InstructionOperation::Dec
+                | InstructionOperation::Inc
+                | InstructionOperation::Lsr
+                | InstructionOperation::Rol
+                | InstructionOperation::Ror
+                | InstructionOperation::Dcp
+                | InstructionOperation::Isc
+                | InstructionOperation::Rla
+                | InstructionOperation::Rra
+                | InstructionOperation::Slo
+                | InstructionOperation::Sre
+                | InstructionOperation::Trb
+                | InstructionOperation::Tsb
+                => InstructionAccess::ReadModifyWrite,
+            _ => InstructionAccess::Read,
+        }
+    }
+}
+
+/// How an instruction accesses its operand, which decides the bus cycles its addressing mode
+/// takes. Implied instructions count as reads.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InstructionAccess {
+    Read,
+    Write,
+    ReadModifyWrite,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -205,10 +672,14 @@ pub enum InstructionMode {
     Indirect,
     IndirectX,
     IndirectY,
+    /// `(zp)` on the 65C02: IndirectY without the index.
+    ZeroPageIndirect,
+    /// `(abs,X)` on the 65C02, only used by JMP.
+    AbsoluteIndexedIndirect,
 }
 
 impl InstructionMode {
-    pub fn len_bytes(&self) -> u8 {
+    pub const fn len_bytes(self) -> u8 {
         match self {
             InstructionMode::Implied | InstructionMode::Accumulator => 1,
             InstructionMode::Immediate
@@ -218,45 +689,37 @@ impl InstructionMode {
                 | InstructionMode::ZeroPageY
                 | InstructionMode::IndirectX
                 | InstructionMode::IndirectY
+                | InstructionMode::ZeroPageIndirect
                 => 2,
             InstructionMode::Absolute
                 | InstructionMode::AbsoluteX
                 | InstructionMode::AbsoluteY
                 | InstructionMode::Indirect
+                | InstructionMode::AbsoluteIndexedIndirect
                 => 3,
         }
     }
-}
 
-#[derive(Debug, PartialEq)]
-pub enum InstructionInput {
-    Implied,
-    Byte(u8),
-    Location(InstructionInputLocation),
-}
-
-#[derive(Debug, PartialEq)]
-pub enum InstructionInputLocation {
-    Accumulator,
-    Address(u16),
-}
-
-impl InstructionInput {
-    pub fn from_address(address: u16) -> Self {
-        Self::Location(InstructionInputLocation::Address(address))
-    }
-
-    pub fn unwrap_location(self) -> Result<InstructionInputLocation> {
+    /// Cycles an instruction reading its operand through the mode takes, not counting the page
+    /// penalty. Control flow instructions have cycles of their own, and are given the minimum.
+    pub fn cycles_read(self) -> u8 {
         match self {
-            InstructionInput::Location(location) => Ok(location),
-            _ => Err(anyhow!("input is not a location")),
-        }
-    }
-
-    pub fn unwrap_address(self) -> Result<u16> {
-        match self.unwrap_location()? {
-            InstructionInputLocation::Address(address) => Ok(address),
-            _ => Err(anyhow!("input is not an address")),
+            InstructionMode::Implied
+                | InstructionMode::Accumulator
+                | InstructionMode::Immediate
+                | InstructionMode::Relative
+                | InstructionMode::Indirect
+                | InstructionMode::AbsoluteIndexedIndirect
+                => 2,
+            InstructionMode::ZeroPage => 3,
+            InstructionMode::ZeroPageX
+                | InstructionMode::ZeroPageY
+                | InstructionMode::Absolute
+                | InstructionMode::AbsoluteX
+                | InstructionMode::AbsoluteY
+                => 4,
+            InstructionMode::IndirectY | InstructionMode::ZeroPageIndirect => 5,
+            InstructionMode::IndirectX => 6,
         }
     }
 }
@@ -1,319 +1,955 @@
-mod clock;
-mod instruction;
+pub mod clock;
+mod error;
+pub mod instruction;
 mod tests;
+mod variant;
 
-use self::clock::{Clock, ClockMode};
-use self::instruction::{
-    Instruction,
-    InstructionOperation,
-    InstructionMode,
-    InstructionInput,
-    InstructionInputLocation,
-};
-use crate::bus::Bus;
+pub use self::error::{CpuError, CpuResult, IllegalOpcodePolicy};
+pub use self::variant::CpuVariant;
+
+use self::instruction::{Instruction, InstructionAccess, InstructionOperation, InstructionMode};
+use crate::bus::{self, Bus};
 use crate::types::{Result, BitRead};
 
 const ADDRESS_VECTOR_NMI: u16 = 0xFFFA;
 const ADDRESS_VECTOR_RESET: u16 = 0xFFFC;
 const ADDRESS_VECTOR_IRQ: u16 = 0xFFFE;
+/// Value the unstable XAA and LXA OR into A before using it, which varies between chips and
+/// even with temperature; $FF makes them behave like their stable counterparts.
+const UNSTABLE_MAGIC: u8 = 0xFF;
 
+/// A 6502 core stepped one cycle at a time, with every bus access, dummy ones included, made
+/// on the cycle the hardware makes it.
 pub struct Cpu {
     bus: Bus,
+    variant: CpuVariant,
+    illegal_opcode_policy: IllegalOpcodePolicy,
     registers: RegisterSet,
-    vectors: VectorSet,
-    clock: Clock,
+    /// Level of the NMI input seen on the last cycle, which the edge detector compares against.
+    nmi_line: bool,
+    /// Set by the edge detector when the NMI line is asserted, until the NMI is serviced.
+    nmi_pending: bool,
+    /// Whether an interrupt was requested at the end of the second-to-last cycle, the one
+    /// that decides whether an interrupt follows the instruction.
+    interrupt_polled: bool,
+    /// Whether an interrupt was requested at the end of the last cycle.
+    interrupt_polled_last: bool,
+    /// Instruction, interrupt or reset in progress, `None` between instructions.
+    sequence: Option<Sequence>,
+    /// Cycle of the sequence in progress, counting from 1 for the opcode fetch.
+    cycle: u8,
+    /// Cycle on which the addressing mode finished resolving `address`.
+    cycle_addressed: Option<u8>,
+    /// Effective address, assembled over the addressing cycles.
+    address: u16,
+    /// Effective address before it was indexed.
+    address_base: u16,
+    /// Byte latched between cycles: a pointer, a branch offset or an operand.
+    data: u8,
+    /// Why the CPU stopped, until reset. The rest of the system only keeps running when it
+    /// jammed, that being a state of the hardware rather than of the emulation.
+    halt: Option<CpuError>,
 }
 
 impl Cpu {
+    /// An NES CPU as powered up, which starts with the reset sequence.
     pub fn new(bus: Bus) -> Result<Self> {
-        let vectors = VectorSet {
-            nmi: bus.read_u16(ADDRESS_VECTOR_NMI)?,
-            reset: bus.read_u16(ADDRESS_VECTOR_RESET)?,
-            irq: bus.read_u16(ADDRESS_VECTOR_IRQ)?,
+        Self::with_variant(bus, CpuVariant::Ricoh2A03)
+    }
+
+    pub fn with_variant(bus: Bus, variant: CpuVariant) -> Result<Self> {
+        Ok(Self {
+            bus,
+            variant,
+            illegal_opcode_policy: IllegalOpcodePolicy::Emulate,
+            registers: RegisterSet::new(),
+            nmi_line: false,
+            nmi_pending: false,
+            interrupt_polled: false,
+            interrupt_polled_last: false,
+            sequence: Some(Sequence::Reset),
+            cycle: 0,
+            cycle_addressed: None,
+            address: 0,
+            address_base: 0,
+            data: 0,
+            halt: None,
+        })
+    }
+
+    /// Asserts the reset line, which aborts whatever the CPU is doing, even when jammed, and
+    /// makes it run the reset sequence.
+    pub fn reset(&mut self) {
+        self.sequence = Some(Sequence::Reset);
+        self.cycle = 0;
+        self.cycle_addressed = None;
+        self.halt = None;
+    }
+
+    pub fn illegal_opcode_policy(&self) -> IllegalOpcodePolicy {
+        self.illegal_opcode_policy
+    }
+
+    pub fn set_illegal_opcode_policy(&mut self, policy: IllegalOpcodePolicy) {
+        self.illegal_opcode_policy = policy;
+    }
+
+    /// Runs until the CPU halts, returning why.
+    pub fn start(&mut self) -> CpuResult {
+        loop {
+            self.step()?;
+        }
+    }
+
+    /// Runs until the end of the next instruction, servicing a pending interrupt on the way.
+    pub fn step(&mut self) -> CpuResult {
+        loop {
+            let instruction = match self.sequence {
+                None | Some(Sequence::Instruction(_)) => true,
+                Some(Sequence::Interrupt) | Some(Sequence::Reset) => false,
+            };
+
+            self.tick()?;
+
+            if instruction && self.sequence.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Runs a single CPU cycle, along with the devices on the bus.
+    pub fn tick(&mut self) -> CpuResult {
+        if let Some(error) = self.halt {
+            if let CpuError::Jammed { .. } = error {
+                self.bus.tick(1);
+            }
+
+            return Err(error);
+        }
+
+        self.bus.tick(1);
+        self.cycle += 1;
+
+        let done = match self.sequence {
+            None => self.begin_sequence(),
+            Some(Sequence::Instruction(instruction)) => self.run_instruction_cycle(instruction),
+            Some(Sequence::Interrupt) => self.run_interrupt_cycle(BreakType::Internal),
+            Some(Sequence::Reset) => self.run_reset_cycle(),
         };
 
-        let mut registers = RegisterSet::new();
-        registers.pc = vectors.reset;
+        // the line is polled before this cycle's edge is detected, so an NMI raised on the
+        // second-to-last cycle only follows the next instruction
+        if self.is_polling_interrupts(done) {
+            self.interrupt_polled = self.interrupt_polled_last;
+            self.interrupt_polled_last = self.nmi_pending
+                || (self.bus.irq() && !self.registers.p.contains(StatusFlags::INTERRUPT_DISABLE));
+        } else if done {
+            // the first instruction of a handler always runs
+            self.interrupt_polled = false;
+            self.interrupt_polled_last = false;
+        }
+
+        self.sample_nmi();
 
-        // TODO: hard-coded
-        let clock = Clock::new(ClockMode::Ntsc);
+        if done {
+            self.sequence = None;
+            self.cycle = 0;
+            self.cycle_addressed = None;
+        }
 
-        Ok(Self { bus, registers, vectors, clock })
+        match self.halt {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
     }
 
-    pub fn start(&mut self) -> Result {
-        while let Some(instruction) = self.determine_instruction_next() {
-            self.process_instruction(instruction)?;
+    /// Whether the interrupt lines are polled at the end of the current cycle. That's the case
+    /// on every cycle of an instruction, though only the poll on its second-to-last one counts,
+    /// except for the offset fetch of a taken branch: one that stays on its page thus goes by
+    /// the lines as they were before its operand. The interrupt sequences, BRK included, don't
+    /// poll at all.
+    fn is_polling_interrupts(&self, done: bool) -> bool {
+        match self.sequence {
+            None => true,
+            Some(Sequence::Instruction(instruction)) => match instruction.mode() {
+                _ if instruction.operation() == InstructionOperation::Brk => false,
+                InstructionMode::Relative => {
+                    done || self.cycle != 2 || !self.is_branch_taken(instruction.operation())
+                },
+                _ => true,
+            },
+            Some(Sequence::Interrupt) | Some(Sequence::Reset) => false,
         }
+    }
+
+    pub fn variant(&self) -> CpuVariant {
+        self.variant
+    }
 
-        Ok(())
+    pub fn bus(&self) -> &Bus {
+        &self.bus
     }
 
-    fn determine_instruction_next(&self) -> Option<Instruction> {
-        let opcode = self.bus.read(self.registers.pc);
-        let instruction = Instruction::from_opcode(opcode);
+    pub fn bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
 
-        // TODO: check if correct
-        if (self.registers.pc + instruction.len() as u16) < ADDRESS_VECTOR_NMI {
-            Some(instruction)
-        } else {
-            None
+    /// Reads the byte at PC and steps past it.
+    fn fetch(&mut self) -> u8 {
+        let value = self.bus.read(self.registers.pc);
+        self.registers.pc = self.registers.pc.wrapping_add(1);
+        value
+    }
+
+    /// The NMI input is edge-sensitive: only its assertion raises an NMI, however long the
+    /// line is then held.
+    fn sample_nmi(&mut self) {
+        let line = self.bus.nmi();
+
+        if line && !self.nmi_line {
+            self.nmi_pending = true;
         }
+
+        self.nmi_line = line;
     }
 
-    fn process_instruction(&mut self, instruction: Instruction) -> Result {
-        let len = instruction.len() as u16;
-        let bytes = self.bus.read_n(self.registers.pc, len)?;
+    /// First cycle of a sequence: enters an interrupt if one was polled during the last
+    /// instruction, with the opcode fetch being discarded and PC left alone; otherwise decodes
+    /// the opcode. `true` for the single cycle NOPs of the 65C02, which are done already.
+    fn begin_sequence(&mut self) -> bool {
+        let address = self.registers.pc;
+        let opcode = self.bus.read(address);
+
+        if self.interrupt_polled {
+            self.sequence = Some(Sequence::Interrupt);
+            return false;
+        }
 
-        // TODO: calculate final cycles
-        self.clock.tick(instruction.cycles_base());
-        self.call_instruction(instruction, &bytes)?;
-        self.registers.pc = self.registers.pc.wrapping_add(len);
+        let mut instruction = Instruction::from_opcode(opcode, self.variant);
+
+        if !instruction.official() {
+            match self.illegal_opcode_policy {
+                IllegalOpcodePolicy::Halt => {
+                    self.halt = Some(CpuError::InvalidOpcode { opcode, address });
+                    return false;
+                },
+                IllegalOpcodePolicy::Nop => instruction = instruction.as_nop(),
+                IllegalOpcodePolicy::Emulate => {},
+            }
+        }
 
-        Ok(())
+        self.sequence = Some(Sequence::Instruction(instruction));
+        self.registers.pc = address.wrapping_add(1);
+        instruction.cycles_base() == 1
+    }
+
+    /// Runs a cycle of the current instruction; `true` on its last one.
+    fn run_instruction_cycle(&mut self, instruction: Instruction) -> bool {
+        let operation = instruction.operation();
+
+        match operation {
+            InstructionOperation::Bcc
+                | InstructionOperation::Bcs
+                | InstructionOperation::Beq
+                | InstructionOperation::Bmi
+                | InstructionOperation::Bne
+                | InstructionOperation::Bpl
+                | InstructionOperation::Bvc
+                | InstructionOperation::Bvs
+                | InstructionOperation::Bra
+                => self.run_branch_cycle(operation),
+            InstructionOperation::Brk => self.run_brk_cycle(),
+            InstructionOperation::Jmp => self.run_jmp_cycle(instruction.mode()),
+            InstructionOperation::Jsr => self.run_jsr_cycle(),
+            InstructionOperation::Kil => self.run_kil_cycle(),
+            InstructionOperation::Pha
+                | InstructionOperation::Php
+                | InstructionOperation::Phx
+                | InstructionOperation::Phy
+                => self.run_push_cycle(operation),
+            InstructionOperation::Pla
+                | InstructionOperation::Plp
+                | InstructionOperation::Plx
+                | InstructionOperation::Ply
+                => self.run_pull_cycle(operation),
+            InstructionOperation::Rti => self.run_rti_cycle(),
+            InstructionOperation::Rts => self.run_rts_cycle(),
+            _ => self.run_access_cycle(instruction),
+        }
     }
 
-    fn call_instruction(&mut self, instruction: Instruction, bytes: &[u8]) -> Result {
-        let input = self.determine_input(instruction.mode(), bytes)?;
+    /// Runs a cycle of an instruction that goes through its addressing mode to read, write or
+    /// modify its operand.
+    fn run_access_cycle(&mut self, instruction: Instruction) -> bool {
+        let operation = instruction.operation();
+        let access = operation.access();
+
+        let cycle_addressed = match self.cycle_addressed {
+            Some(cycle) => cycle,
+            None => {
+                if !self.run_addressing_cycle(instruction.mode(), access) {
+                    return false;
+                }
+
+                self.cycle_addressed = Some(self.cycle);
+                self.cycle
+            },
+        };
 
-        match instruction.operation() {
-            InstructionOperation::Adc => self.run_adc(self.resolve_input_byte(input)?),
-            InstructionOperation::And => self.run_and(self.resolve_input_byte(input)?),
-            InstructionOperation::Asl => self.run_asl(input.unwrap_location()?),
-            InstructionOperation::Bcc => self.run_bcc(input.unwrap_address()?),
-            InstructionOperation::Bcs => self.run_bcs(input.unwrap_address()?),
-            InstructionOperation::Beq => self.run_beq(input.unwrap_address()?),
-            InstructionOperation::Bit => self.run_bit(self.resolve_input_byte(input)?),
-            InstructionOperation::Bmi => self.run_bmi(input.unwrap_address()?),
-            InstructionOperation::Bne => self.run_bne(input.unwrap_address()?),
-            InstructionOperation::Bpl => self.run_bpl(input.unwrap_address()?),
-            InstructionOperation::Brk => self.run_brk(),
-            InstructionOperation::Bvc => self.run_bvc(input.unwrap_address()?),
-            InstructionOperation::Bvs => self.run_bvs(input.unwrap_address()?),
+        match (instruction.mode(), access, self.cycle - cycle_addressed) {
+            (InstructionMode::Implied, _, _) => {
+                self.bus.read(self.registers.pc);
+                self.run_read(operation, 0);
+                true
+            },
+            (InstructionMode::Accumulator, _, _) => {
+                self.bus.read(self.registers.pc);
+                self.registers.a = self.run_modify(operation, self.registers.a);
+                true
+            },
+            // BIT #imm on the 65C02 only sets Z, N and V being about memory
+            (InstructionMode::Immediate, _, _) if operation == InstructionOperation::Bit => {
+                let value = self.fetch();
+                self.set_status_flag_zero(self.registers.a & value);
+                true
+            },
+            (InstructionMode::Immediate, _, _) => {
+                let value = self.fetch();
+                self.run_read(operation, value);
+                true
+            },
+            (_, InstructionAccess::Read, _) => {
+                let value = self.bus.read(self.address);
+                self.run_read(operation, value);
+                true
+            },
+            (_, InstructionAccess::Write, _) => {
+                let value = self.run_write(operation);
+                self.bus.write(self.address, value);
+                true
+            },
+            (_, InstructionAccess::ReadModifyWrite, 0) => {
+                self.data = self.bus.read(self.address);
+                false
+            },
+            // the unmodified value is written back while the result is computed, which the
+            // 65C02 replaces with another read
+            (_, InstructionAccess::ReadModifyWrite, 1) => {
+                if self.variant == CpuVariant::Cmos65C02 {
+                    self.bus.read(self.address);
+                } else {
+                    self.bus.write(self.address, self.data);
+                }
+
+                self.data = self.run_modify(operation, self.data);
+                false
+            },
+            (_, InstructionAccess::ReadModifyWrite, _) => {
+                self.bus.write(self.address, self.data);
+                true
+            },
+        }
+    }
+
+    /// Runs a cycle of resolving the effective address; `true` once it is resolved, with the
+    /// cycle left to the access itself.
+    fn run_addressing_cycle(&mut self, mode: InstructionMode, access: InstructionAccess) -> bool {
+        match (mode, self.cycle) {
+            (InstructionMode::Implied, _)
+                | (InstructionMode::Accumulator, _)
+                | (InstructionMode::Immediate, _)
+                => true,
+            (InstructionMode::ZeroPage, 2)
+                | (InstructionMode::ZeroPageX, 2)
+                | (InstructionMode::ZeroPageY, 2)
+                | (InstructionMode::Absolute, 2)
+                | (InstructionMode::AbsoluteX, 2)
+                | (InstructionMode::AbsoluteY, 2)
+                => {
+                self.address = self.fetch() as u16;
+                false
+            },
+            (InstructionMode::ZeroPage, _) => true,
+            (InstructionMode::ZeroPageX, 3) => {
+                self.bus.read(self.address);
+                self.address = (self.address as u8).wrapping_add(self.registers.x) as u16;
+                false
+            },
+            (InstructionMode::ZeroPageY, 3) => {
+                self.bus.read(self.address);
+                self.address = (self.address as u8).wrapping_add(self.registers.y) as u16;
+                false
+            },
+            (InstructionMode::ZeroPageX, _) | (InstructionMode::ZeroPageY, _) => true,
+            (InstructionMode::Absolute, 3)
+                | (InstructionMode::AbsoluteX, 3)
+                | (InstructionMode::AbsoluteY, 3)
+                => {
+                self.address |= (self.fetch() as u16) << 8;
+                false
+            },
+            (InstructionMode::Absolute, _) => true,
+            (InstructionMode::AbsoluteX, 4) => self.index_address(self.registers.x, access),
+            (InstructionMode::AbsoluteY, 4) => self.index_address(self.registers.y, access),
+            (InstructionMode::AbsoluteX, _) | (InstructionMode::AbsoluteY, _) => true,
+            (InstructionMode::IndirectX, 2)
+                | (InstructionMode::IndirectY, 2)
+                | (InstructionMode::ZeroPageIndirect, 2)
+                => {
+                self.data = self.fetch();
+                false
+            },
+            (InstructionMode::IndirectX, 3) => {
+                self.bus.read(self.data as u16);
+                self.data = self.data.wrapping_add(self.registers.x);
+                false
+            },
+            (InstructionMode::IndirectX, 4)
+                | (InstructionMode::IndirectY, 3)
+                | (InstructionMode::ZeroPageIndirect, 3)
+                => {
+                self.address = self.bus.read(self.data as u16) as u16;
+                false
+            },
+            (InstructionMode::IndirectX, 5)
+                | (InstructionMode::IndirectY, 4)
+                | (InstructionMode::ZeroPageIndirect, 4)
+                => {
+                self.address |= (self.bus.read(self.data.wrapping_add(1) as u16) as u16) << 8;
+                false
+            },
+            (InstructionMode::IndirectY, 5) => self.index_address(self.registers.y, access),
+            (InstructionMode::IndirectX, _)
+                | (InstructionMode::IndirectY, _)
+                | (InstructionMode::ZeroPageIndirect, _)
+                => true,
+            // only used by control flow instructions, which have cycles of their own
+            (InstructionMode::Relative, _)
+                | (InstructionMode::Indirect, _)
+                | (InstructionMode::AbsoluteIndexedIndirect, _)
+                => true,
+        }
+    }
+
+    /// Adds an index register to the base address. The high byte is fixed up a cycle later,
+    /// meanwhile reading from the address with only the low byte indexed; reads skip that
+    /// cycle unless a page was crossed, writes never do.
+    fn index_address(&mut self, index: u8, access: InstructionAccess) -> bool {
+        let address = self.address.wrapping_add(index as u16);
+        let address_unfixed = (self.address & 0xFF00) | (address & 0x00FF);
+        self.address_base = self.address;
+        self.address = address;
+
+        if access == InstructionAccess::Read && address == address_unfixed {
+            return true;
+        }
+
+        self.bus.read(address_unfixed);
+        false
+    }
+
+    /// Branches take a cycle more when taken, to add the offset to PC while the next opcode
+    /// is read, and yet another one to fix its high byte when that crossed a page.
+    fn run_branch_cycle(&mut self, operation: InstructionOperation) -> bool {
+        match self.cycle {
+            2 => {
+                self.data = self.fetch();
+                !self.is_branch_taken(operation)
+            },
+            3 => {
+                let next = self.registers.pc;
+                self.bus.read(next);
+
+                let target = next.wrapping_add(self.data as i8 as u16);
+                self.address = (next & 0xFF00) | (target & 0x00FF);
+                self.registers.pc = target;
+                self.address == target
+            },
+            _ => {
+                self.bus.read(self.address);
+                true
+            },
+        }
+    }
+
+    fn is_branch_taken(&self, operation: InstructionOperation) -> bool {
+        let p = self.registers.p;
+
+        match operation {
+            InstructionOperation::Bcc => !p.contains(StatusFlags::CARRY),
+            InstructionOperation::Bcs => p.contains(StatusFlags::CARRY),
+            InstructionOperation::Beq => p.contains(StatusFlags::ZERO),
+            InstructionOperation::Bmi => p.contains(StatusFlags::NEGATIVE),
+            InstructionOperation::Bne => !p.contains(StatusFlags::ZERO),
+            InstructionOperation::Bpl => !p.contains(StatusFlags::NEGATIVE),
+            InstructionOperation::Bvc => !p.contains(StatusFlags::OVERFLOW),
+            InstructionOperation::Bvs => p.contains(StatusFlags::OVERFLOW),
+            InstructionOperation::Bra => true,
+            _ => false,
+        }
+    }
+
+    fn run_brk_cycle(&mut self) -> bool {
+        if self.cycle == 2 {
+            // the padding byte is skipped, so returning from the handler doesn't execute it
+            self.fetch();
+            return false;
+        }
+
+        self.run_interrupt_cycle(BreakType::Program)
+    }
+
+    /// Runs a cycle of entering an interrupt, its first cycle being the discarded opcode fetch.
+    /// The vector is only picked while P is pushed, so a pending NMI hijacks a BRK or IRQ that
+    /// got that far, which then push P as they would have otherwise.
+    fn run_interrupt_cycle(&mut self, break_type: BreakType) -> bool {
+        let pc = self.registers.pc.to_le_bytes();
+
+        match self.cycle {
+            1 | 2 => {
+                self.bus.read(self.registers.pc);
+                false
+            },
+            3 => {
+                self.stack_push(pc[1]);
+                false
+            },
+            4 => {
+                self.stack_push(pc[0]);
+                false
+            },
+            5 => {
+                self.address = if self.nmi_pending {
+                    self.nmi_pending = false;
+                    ADDRESS_VECTOR_NMI
+                } else {
+                    ADDRESS_VECTOR_IRQ
+                };
+
+                self.stack_push(self.registers.p.pushed(break_type));
+                false
+            },
+            6 => {
+                self.data = self.bus.read(self.address);
+                self.enter_handler();
+                false
+            },
+            _ => {
+                let high = self.bus.read(self.address.wrapping_add(1));
+                self.registers.pc = u16::from_le_bytes([self.data, high]);
+                true
+            },
+        }
+    }
+
+    /// The reset sequence is an interrupt sequence with its writes turned into reads, so S is
+    /// still decremented three times, though nothing is pushed.
+    fn run_reset_cycle(&mut self) -> bool {
+        match self.cycle {
+            1 | 2 => {
+                self.bus.read(self.registers.pc);
+                false
+            },
+            3..=5 => {
+                self.bus.read(self.stack_determine_address());
+                self.registers.s = self.registers.s.wrapping_sub(1);
+                false
+            },
+            6 => {
+                self.data = self.bus.read(ADDRESS_VECTOR_RESET);
+                self.enter_handler();
+                false
+            },
+            _ => {
+                let high = self.bus.read(ADDRESS_VECTOR_RESET.wrapping_add(1));
+                self.registers.pc = u16::from_le_bytes([self.data, high]);
+                true
+            },
+        }
+    }
+
+    /// Indirect jumps take a cycle more on the 65C02, re-reading the high byte of the pointer
+    /// address while indexing it for `(abs,X)`.
+    fn run_jmp_cycle(&mut self, mode: InstructionMode) -> bool {
+        let cycle_pointer = match self.variant {
+            CpuVariant::Cmos65C02 => 5,
+            _ => 4,
+        };
+
+        match (mode, self.cycle) {
+            (InstructionMode::Absolute, 2) => {
+                self.data = self.fetch();
+                false
+            },
+            (InstructionMode::Absolute, _) => {
+                let high = self.bus.read(self.registers.pc);
+                self.registers.pc = u16::from_le_bytes([self.data, high]);
+                true
+            },
+            (_, 2) => {
+                self.address = self.fetch() as u16;
+                false
+            },
+            (_, 3) => {
+                self.address |= (self.fetch() as u16) << 8;
+                false
+            },
+            (_, cycle) if cycle < cycle_pointer => {
+                self.bus.read(self.registers.pc.wrapping_sub(1));
+
+                if let InstructionMode::AbsoluteIndexedIndirect = mode {
+                    self.address = self.address.wrapping_add(self.registers.x as u16);
+                }
+
+                false
+            },
+            (_, cycle) if cycle == cycle_pointer => {
+                self.data = self.bus.read(self.address);
+                false
+            },
+            // the NMOS 6502 doesn't carry into the high byte of the pointer, so `JMP ($xxFF)`
+            // takes the high byte of its target from $xx00
+            _ => {
+                let address = match self.variant {
+                    CpuVariant::Cmos65C02 => self.address.wrapping_add(1),
+                    _ => bus::increment_in_page(self.address),
+                };
+
+                let high = self.bus.read(address);
+                self.registers.pc = u16::from_le_bytes([self.data, high]);
+                true
+            },
+        }
+    }
+
+    /// KIL reads its operand byte like any implied instruction, then jams the CPU.
+    fn run_kil_cycle(&mut self) -> bool {
+        self.bus.read(self.registers.pc);
+        self.halt = Some(CpuError::Jammed { address: self.registers.pc.wrapping_sub(1) });
+        false
+    }
+
+    /// JSR pushes the address of its last byte, which it only reads once that's done.
+    fn run_jsr_cycle(&mut self) -> bool {
+        let pc = self.registers.pc.to_le_bytes();
+
+        match self.cycle {
+            2 => {
+                self.data = self.fetch();
+                false
+            },
+            3 => {
+                self.bus.read(self.stack_determine_address());
+                false
+            },
+            4 => {
+                self.stack_push(pc[1]);
+                false
+            },
+            5 => {
+                self.stack_push(pc[0]);
+                false
+            },
+            _ => {
+                let high = self.bus.read(self.registers.pc);
+                self.registers.pc = u16::from_le_bytes([self.data, high]);
+                true
+            },
+        }
+    }
+
+    /// RTS pulls the address pushed by JSR, then steps past it.
+    fn run_rts_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.bus.read(self.registers.pc);
+                false
+            },
+            3 => {
+                self.bus.read(self.stack_determine_address());
+                false
+            },
+            4 => {
+                self.data = self.stack_pull();
+                false
+            },
+            5 => {
+                self.registers.pc = u16::from_le_bytes([self.data, self.stack_pull()]);
+                false
+            },
+            _ => {
+                self.fetch();
+                true
+            },
+        }
+    }
+
+    /// RTI pulls P and then PC, returning to the pushed address itself.
+    fn run_rti_cycle(&mut self) -> bool {
+        match self.cycle {
+            2 => {
+                self.bus.read(self.registers.pc);
+                false
+            },
+            3 => {
+                self.bus.read(self.stack_determine_address());
+                false
+            },
+            4 => {
+                self.registers.p = StatusFlags::pulled(self.stack_pull());
+                false
+            },
+            5 => {
+                self.data = self.stack_pull();
+                false
+            },
+            _ => {
+                let high = self.stack_pull();
+                self.registers.pc = u16::from_le_bytes([self.data, high]);
+                true
+            },
+        }
+    }
+
+    fn run_push_cycle(&mut self, operation: InstructionOperation) -> bool {
+        if self.cycle == 2 {
+            self.bus.read(self.registers.pc);
+            return false;
+        }
+
+        match operation {
+            InstructionOperation::Pha => self.run_pha(),
+            InstructionOperation::Phx => self.run_phx(),
+            InstructionOperation::Phy => self.run_phy(),
+            _ => self.run_php(),
+        }
+
+        true
+    }
+
+    fn run_pull_cycle(&mut self, operation: InstructionOperation) -> bool {
+        match self.cycle {
+            2 => {
+                self.bus.read(self.registers.pc);
+                false
+            },
+            3 => {
+                self.bus.read(self.stack_determine_address());
+                false
+            },
+            _ => {
+                match operation {
+                    InstructionOperation::Pla => self.run_pla(),
+                    InstructionOperation::Plx => self.run_plx(),
+                    InstructionOperation::Ply => self.run_ply(),
+                    _ => self.run_plp(),
+                }
+
+                true
+            },
+        }
+    }
+
+    /// Applies an instruction reading its operand, or an implied one, which ignores it.
+    fn run_read(&mut self, operation: InstructionOperation, input: u8) {
+        match operation {
+            InstructionOperation::Adc => self.run_adc(input),
+            InstructionOperation::And => self.run_and(input),
+            InstructionOperation::Bit => self.run_bit(input),
             InstructionOperation::Clc => self.run_clc(),
             InstructionOperation::Cld => self.run_cld(),
             InstructionOperation::Cli => self.run_cli(),
             InstructionOperation::Clv => self.run_clv(),
-            InstructionOperation::Cmp => self.run_cmp(self.resolve_input_byte(input)?),
-            InstructionOperation::Cpx => self.run_cpx(self.resolve_input_byte(input)?),
-            InstructionOperation::Cpy => self.run_cpy(self.resolve_input_byte(input)?),
-            InstructionOperation::Dec => self.run_dec(input.unwrap_address()?),
+            InstructionOperation::Cmp => self.run_cmp(input),
+            InstructionOperation::Cpx => self.run_cpx(input),
+            InstructionOperation::Cpy => self.run_cpy(input),
             InstructionOperation::Dex => self.run_dex(),
             InstructionOperation::Dey => self.run_dey(),
-            InstructionOperation::Eor => self.run_eor(self.resolve_input_byte(input)?),
-            InstructionOperation::Inc => self.run_inc(input.unwrap_address()?),
+            InstructionOperation::Eor => self.run_eor(input),
             InstructionOperation::Inx => self.run_inx(),
             InstructionOperation::Iny => self.run_iny(),
-            InstructionOperation::Jmp => self.run_jmp(input.unwrap_address()?),
-            InstructionOperation::Jsr => self.run_jsr(input.unwrap_address()?, instruction.len()),
-            InstructionOperation::Lda => self.run_lda(self.resolve_input_byte(input)?),
-            InstructionOperation::Ldx => self.run_ldx(self.resolve_input_byte(input)?),
-            InstructionOperation::Ldy => self.run_ldy(self.resolve_input_byte(input)?),
-            InstructionOperation::Lsr => self.run_lsr(input.unwrap_location()?),
-            InstructionOperation::Nop => {},
-            InstructionOperation::Ora => self.run_ora(self.resolve_input_byte(input)?),
-            InstructionOperation::Pha => self.run_pha(),
-            InstructionOperation::Php => self.run_php(),
-            InstructionOperation::Pla => self.run_pla(),
-            InstructionOperation::Plp => self.run_plp(),
-            InstructionOperation::Rol => self.run_rol(input.unwrap_location()?),
-            InstructionOperation::Ror => self.run_ror(input.unwrap_location()?),
-            InstructionOperation::Rti => self.run_rti(),
-            InstructionOperation::Rts => self.run_rts(),
-            InstructionOperation::Sbc => self.run_sbc(self.resolve_input_byte(input)?),
+            InstructionOperation::Lda => self.run_lda(input),
+            InstructionOperation::Ldx => self.run_ldx(input),
+            InstructionOperation::Ldy => self.run_ldy(input),
+            InstructionOperation::Ora => self.run_ora(input),
+            InstructionOperation::Sbc => self.run_sbc(input),
             InstructionOperation::Sec => self.run_sec(),
             InstructionOperation::Sed => self.run_sed(),
             InstructionOperation::Sei => self.run_sei(),
-            InstructionOperation::Sta => self.run_sta(input.unwrap_address()?),
-            InstructionOperation::Stx => self.run_stx(input.unwrap_address()?),
-            InstructionOperation::Sty => self.run_sty(input.unwrap_address()?),
             InstructionOperation::Tax => self.run_tax(),
             InstructionOperation::Tay => self.run_tay(),
             InstructionOperation::Tsx => self.run_tsx(),
             InstructionOperation::Txa => self.run_txa(),
             InstructionOperation::Txs => self.run_txs(),
             InstructionOperation::Tya => self.run_tya(),
+            InstructionOperation::Alr => self.run_alr(input),
+            InstructionOperation::Anc => self.run_anc(input),
+            InstructionOperation::Arr => self.run_arr(input),
+            InstructionOperation::Axs => self.run_axs(input),
+            InstructionOperation::Las => self.run_las(input),
+            InstructionOperation::Lax => self.run_lax(input),
+            InstructionOperation::Lxa => self.run_lxa(input),
+            InstructionOperation::Xaa => self.run_xaa(input),
+            _ => {},
         }
+    }
 
-        Ok(())
-    }
-
-    fn determine_input(&self, mode: InstructionMode, bytes: &[u8]) -> Result<InstructionInput> {
-        let input = match mode {
-            InstructionMode::Implied => InstructionInput::Implied,
-            InstructionMode::Accumulator => {
-                InstructionInput::Location(InstructionInputLocation::Accumulator)
-            },
-            InstructionMode::Immediate => {
-                Self::assert_input_len(2, bytes);
-                InstructionInput::Byte(bytes[1])
-            },
-            InstructionMode::Relative => {
-                Self::assert_input_len(2, bytes);
-                let offset = i32::from(bytes[1] as i8);
-                let address = (self.registers.pc as i32).wrapping_add(offset) as u16;
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::ZeroPage => {
-                Self::assert_input_len(2, bytes);
-                InstructionInput::from_address(bytes[1] as u16)
-            },
-            InstructionMode::ZeroPageX => {
-                Self::assert_input_len(2, bytes);
-                let address = bytes[1].wrapping_add(self.registers.x) as u16;
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::ZeroPageY => {
-                Self::assert_input_len(2, bytes);
-                let address = bytes[1].wrapping_add(self.registers.y) as u16;
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::Absolute => {
-                Self::assert_input_len(3, bytes);
-                let address = u16::from_le_bytes([bytes[1], bytes[2]]);
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::AbsoluteX => {
-                let input = self.determine_input(InstructionMode::Absolute, bytes)?;
-                let address = input.unwrap_address()?.wrapping_add(self.registers.x as u16);
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::AbsoluteY => {
-                let input = self.determine_input(InstructionMode::Absolute, bytes)?;
-                let address = input.unwrap_address()?.wrapping_add(self.registers.y as u16);
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::Indirect => {
-                Self::assert_input_len(3, bytes);
-                let address_indirect = u16::from_le_bytes([bytes[1], bytes[2]]);
-                let address = self.bus.read_u16(address_indirect)?;
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::IndirectX => {
-                Self::assert_input_len(2, bytes);
-                let address_indirect = bytes[1].wrapping_add(self.registers.x) as u16;
-                let address = self.bus.read_u16(address_indirect)?;
-                InstructionInput::from_address(address)
-            },
-            InstructionMode::IndirectY => {
-                Self::assert_input_len(2, bytes);
-                let address = self.bus.read_u16(bytes[1].into())?
-                    .wrapping_add(self.registers.y as u16);
-                InstructionInput::from_address(address)
-            },
-        };
-
-        Ok(input)
+    /// Applies a read-modify-write instruction to its operand, returning the result.
+    fn run_modify(&mut self, operation: InstructionOperation, input: u8) -> u8 {
+        match operation {
+            InstructionOperation::Asl => self.run_asl(input),
+            InstructionOperation::Dec => self.run_dec(input),
+            InstructionOperation::Inc => self.run_inc(input),
+            InstructionOperation::Lsr => self.run_lsr(input),
+            InstructionOperation::Rol => self.run_rol(input),
+            InstructionOperation::Ror => self.run_ror(input),
+            InstructionOperation::Dcp => self.run_dcp(input),
+            InstructionOperation::Isc => self.run_isc(input),
+            InstructionOperation::Rla => self.run_rla(input),
+            InstructionOperation::Rra => self.run_rra(input),
+            InstructionOperation::Slo => self.run_slo(input),
+            InstructionOperation::Sre => self.run_sre(input),
+            InstructionOperation::Trb => self.run_trb(input),
+            InstructionOperation::Tsb => self.run_tsb(input),
+            _ => input,
+        }
     }
 
-    fn resolve_input_byte(&self, input: InstructionInput) -> Result<u8> {
-        let value = match input {
-            InstructionInput::Byte(value) => value,
-            InstructionInput::Location(location) => match location {
-                InstructionInputLocation::Address(address) => self.bus.read(address),
-                _ => return Err(anyhow!("cannot resolve input byte for the current variant")),
+    /// Value written to memory by a store instruction.
+    fn run_write(&mut self, operation: InstructionOperation) -> u8 {
+        match operation {
+            InstructionOperation::Sax => self.registers.a & self.registers.x,
+            InstructionOperation::Sha => self.run_sh(self.registers.a & self.registers.x),
+            InstructionOperation::Shx => self.run_sh(self.registers.x),
+            InstructionOperation::Shy => self.run_sh(self.registers.y),
+            InstructionOperation::Stx => self.registers.x,
+            InstructionOperation::Sty => self.registers.y,
+            InstructionOperation::Stz => 0,
+            InstructionOperation::Tas => {
+                self.registers.s = self.registers.a & self.registers.x;
+                self.run_sh(self.registers.s)
             },
-            _ => return Err(anyhow!("cannot resolve input byte for the current variant")),
-        };
-
-        Ok(value)
+            _ => self.registers.a,
+        }
     }
 
-    fn assert_input_len(len_expected: usize, bytes: &[u8]) {
-        assert!(len_expected == bytes.len(), "expected args to have length `{}`, received `{}`", len_expected, bytes.len());
+    fn run_adc(&mut self, input: u8) {
+        if self.is_decimal_mode() {
+            self.run_adc_decimal(input);
+        } else {
+            self.run_adc_binary(input);
+        }
     }
 
-    fn run_adc(&mut self, input: u8) {
+    fn run_adc_binary(&mut self, input: u8) {
         let a_old = self.registers.a;
         let carry = (self.registers.p & StatusFlags::CARRY).bits();
-        let result = self.registers.a.wrapping_add(input).wrapping_add(carry);
+        let sum = a_old as u16 + input as u16 + carry as u16;
+        let result = sum as u8;
         self.registers.a = result;
-        self.set_status_flag_carry(input, result);
+        self.registers.p.set(StatusFlags::CARRY, sum > 0xFF);
         self.set_status_flag_zero(result);
-        self.set_status_flag_overflow(a_old, result);
+        self.set_status_flag_overflow(a_old, input, result);
         self.set_status_flag_negative(result);
     }
 
-    fn run_and(&mut self, input: u8) {
-        self.registers.a &= input;
-        self.set_status_flag_zero(self.registers.a);
-        self.set_status_flag_negative(self.registers.a);
-    }
-
-    fn run_asl(&mut self, target: InstructionInputLocation) {
-        let input = match target {
-            InstructionInputLocation::Accumulator => self.registers.a,
-            InstructionInputLocation::Address(address) => self.bus.read(address),
-        };
-        let result = input.wrapping_shl(1);
-        self.persist_result_by_location(result, target);
+    /// BCD addition, which adjusts the low digit before adding the high ones. The NMOS 6502
+    /// takes N and V from the sum before the high digit is adjusted and Z from the binary sum,
+    /// the 65C02 only V.
+    fn run_adc_decimal(&mut self, input: u8) {
+        let a_old = self.registers.a;
+        let carry = (self.registers.p & StatusFlags::CARRY).bits();
+        let binary = a_old.wrapping_add(input).wrapping_add(carry);
 
-        self.set_status_flag_carry(input, result);
-        self.set_status_flag_zero(result);
-        self.set_status_flag_negative(result);
-    }
+        let mut low = (a_old & 0x0F) as u16 + (input & 0x0F) as u16 + carry as u16;
 
-    fn run_bcc(&mut self, target: u16) {
-        if !self.registers.p.contains(StatusFlags::CARRY) {
-            self.registers.pc = target;
+        if low > 0x09 {
+            low = ((low + 0x06) & 0x0F) + 0x10;
         }
-    }
 
-    fn run_bcs(&mut self, target: u16) {
-        if self.registers.p.contains(StatusFlags::CARRY) {
-            self.registers.pc = target;
-        }
-    }
+        let mut sum = (a_old & 0xF0) as u16 + (input & 0xF0) as u16 + low;
+        self.set_status_flag_zero(binary);
+        self.set_status_flag_overflow(a_old, input, sum as u8);
+        self.set_status_flag_negative(sum as u8);
 
-    fn run_beq(&mut self, target: u16) {
-        if self.registers.p.contains(StatusFlags::ZERO) {
-            self.registers.pc = target;
+        if sum > 0x9F {
+            sum += 0x60;
         }
-    }
 
-    fn run_bit(&mut self, input: u8) {
-        self.set_status_flag_zero(self.registers.a & input);
-        self.registers.p.set(StatusFlags::OVERFLOW, input.is_bit_set(6));
-        self.registers.p.set(StatusFlags::NEGATIVE, input.is_bit_set(7));
-    }
+        self.registers.a = sum as u8;
+        self.registers.p.set(StatusFlags::CARRY, sum > 0xFF);
 
-    fn run_bmi(&mut self, target: u16) {
-        if self.registers.p.contains(StatusFlags::NEGATIVE) {
-            self.registers.pc = target;
+        if self.variant == CpuVariant::Cmos65C02 {
+            self.set_status_flag_zero(self.registers.a);
+            self.set_status_flag_negative(self.registers.a);
         }
     }
 
-    fn run_bne(&mut self, target: u16) {
-        if !self.registers.p.contains(StatusFlags::ZERO) {
-            self.registers.pc = target;
-        }
-    }
+    /// BCD subtraction, which leaves the flags of the binary one on the NMOS 6502; the 65C02
+    /// adjusts the binary difference instead, and sets N and Z by the result.
+    fn run_sbc_decimal(&mut self, input: u8) {
+        let a_old = self.registers.a;
+        let borrow = !self.registers.p.contains(StatusFlags::CARRY) as i16;
+        self.run_adc_binary(!input);
+
+        let low = (a_old & 0x0F) as i16 - (input & 0x0F) as i16 - borrow;
+
+        let result = match self.variant {
+            CpuVariant::Cmos65C02 => {
+                let mut difference = a_old as i16 - input as i16 - borrow;
+
+                if difference < 0 {
+                    difference -= 0x60;
+                }
+
+                if low < 0 {
+                    difference -= 0x06;
+                }
+
+                difference
+            },
+            _ => {
+                let low = if low < 0 { ((low - 0x06) & 0x0F) - 0x10 } else { low };
+                let mut difference = (a_old & 0xF0) as i16 - (input & 0xF0) as i16 + low;
+
+                if difference < 0 {
+                    difference -= 0x60;
+                }
+
+                difference
+            },
+        };
 
-    fn run_bpl(&mut self, target: u16) {
-        if !self.registers.p.contains(StatusFlags::NEGATIVE) {
-            self.registers.pc = target;
+        self.registers.a = result as u8;
+
+        if self.variant == CpuVariant::Cmos65C02 {
+            self.set_status_flag_zero(self.registers.a);
+            self.set_status_flag_negative(self.registers.a);
         }
     }
 
-    fn run_brk(&mut self) {
-        if !self.registers.p.contains(StatusFlags::INTERRUPT_DISABLE) {
-            self.generate_interrupt(BreakType::Program);
+    fn is_decimal_mode(&self) -> bool {
+        self.variant.has_decimal_mode() && self.registers.p.contains(StatusFlags::DECIMAL)
+    }
 
-            // TODO: hacky, find better way to account for instruction length being added
-            self.registers.pc = self.registers.pc.wrapping_sub(1);
-        }
+    fn run_and(&mut self, input: u8) {
+        self.registers.a &= input;
+        self.set_status_flag_zero(self.registers.a);
+        self.set_status_flag_negative(self.registers.a);
     }
 
-    fn run_bvc(&mut self, target: u16) {
-        if !self.registers.p.contains(StatusFlags::OVERFLOW) {
-            self.registers.pc = target;
-        }
+    fn run_asl(&mut self, input: u8) -> u8 {
+        let result = input.wrapping_shl(1);
+        self.registers.p.set(StatusFlags::CARRY, input.is_bit_set(7));
+        self.set_status_flag_zero(result);
+        self.set_status_flag_negative(result);
+        result
     }
 
-    fn run_bvs(&mut self, target: u16) {
-        if self.registers.p.contains(StatusFlags::OVERFLOW) {
-            self.registers.pc = target;
-        }
+    fn run_bit(&mut self, input: u8) {
+        self.set_status_flag_zero(self.registers.a & input);
+        self.registers.p.set(StatusFlags::OVERFLOW, input.is_bit_set(6));
+        self.registers.p.set(StatusFlags::NEGATIVE, input.is_bit_set(7));
     }
 
     fn run_clc(&mut self) {
@@ -353,11 +989,11 @@ impl Cpu {
         self.registers.p.set(StatusFlags::NEGATIVE, result.is_bit_set(7));
     }
 
-    fn run_dec(&mut self, target: u16) {
-        let result = self.bus.read(target).wrapping_sub(1);
-        self.bus.write(target, result);
+    fn run_dec(&mut self, input: u8) -> u8 {
+        let result = input.wrapping_sub(1);
         self.set_status_flag_zero(result);
         self.set_status_flag_negative(result);
+        result
     }
 
     fn run_dex(&mut self) {
@@ -378,11 +1014,11 @@ impl Cpu {
         self.set_status_flag_negative(self.registers.a);
     }
 
-    fn run_inc(&mut self, target: u16) {
-        let result = self.bus.read(target).wrapping_add(1);
-        self.bus.write(target, result);
+    fn run_inc(&mut self, input: u8) -> u8 {
+        let result = input.wrapping_add(1);
         self.set_status_flag_zero(result);
         self.set_status_flag_negative(result);
+        result
     }
 
     fn run_inx(&mut self) {
@@ -397,19 +1033,6 @@ impl Cpu {
         self.set_status_flag_negative(self.registers.y);
     }
 
-    fn run_jmp(&mut self, target: u16) {
-        // TODO: hacky, find better way to account for instruction length being added
-        self.registers.pc = target - 3;
-    }
-
-    fn run_jsr(&mut self, target: u16, instruction_len: u8) {
-        let stack_address = self.registers.pc.wrapping_add(instruction_len as u16).wrapping_sub(1);
-        self.stack_push_u16(stack_address);
-
-        // TODO: hacky, find better way to account for instruction length being added
-        self.registers.pc = target - instruction_len as u16;
-    }
-
     fn run_lda(&mut self, input: u8) {
         self.registers.a = input;
         self.set_status_flag_zero(input);
@@ -428,19 +1051,14 @@ impl Cpu {
         self.set_status_flag_negative(input);
     }
 
-    fn run_lsr(&mut self, target: InstructionInputLocation) {
-        let input = match target {
-            InstructionInputLocation::Accumulator => self.registers.a,
-            InstructionInputLocation::Address(address) => self.bus.read(address),
-        };
+    fn run_lsr(&mut self, input: u8) -> u8 {
         let result = input.wrapping_shr(1);
-        self.persist_result_by_location(result, target);
-
         self.registers.p.set(StatusFlags::CARRY, input.is_bit_set(0));
         self.set_status_flag_zero(result);
 
         // TODO: is this correct? bit 7 seems to never be set
         self.registers.p.remove(StatusFlags::NEGATIVE);
+        result
     }
 
     fn run_ora(&mut self, input: u8) {
@@ -454,7 +1072,15 @@ impl Cpu {
     }
 
     fn run_php(&mut self) {
-        self.stack_push(self.registers.p.bits());
+        self.stack_push(self.registers.p.pushed(BreakType::Program));
+    }
+
+    fn run_phx(&mut self) {
+        self.stack_push(self.registers.x);
+    }
+
+    fn run_phy(&mut self) {
+        self.stack_push(self.registers.y);
     }
 
     fn run_pla(&mut self) {
@@ -464,54 +1090,46 @@ impl Cpu {
     }
 
     fn run_plp(&mut self) {
-        self.registers.p = StatusFlags::from_bits(self.stack_pull()).unwrap();
+        self.registers.p = StatusFlags::pulled(self.stack_pull());
     }
 
-    fn run_rol(&mut self, target: InstructionInputLocation) {
-        let input = match target {
-            InstructionInputLocation::Accumulator => self.registers.a,
-            InstructionInputLocation::Address(address) => self.bus.read(address),
-        };
+    fn run_plx(&mut self) {
+        self.registers.x = self.stack_pull();
+        self.set_status_flag_zero(self.registers.x);
+        self.set_status_flag_negative(self.registers.x);
+    }
+
+    fn run_ply(&mut self) {
+        self.registers.y = self.stack_pull();
+        self.set_status_flag_zero(self.registers.y);
+        self.set_status_flag_negative(self.registers.y);
+    }
+
+    fn run_rol(&mut self, input: u8) -> u8 {
         let carry = (self.registers.p & StatusFlags::CARRY).bits();
         let result = input.wrapping_shl(1) + carry;
-        self.persist_result_by_location(result, target);
-
         self.registers.p.set(StatusFlags::CARRY, input.is_bit_set(7));
         self.set_status_flag_zero(result);
         self.set_status_flag_negative(result);
+        result
     }
 
-    fn run_ror(&mut self, target: InstructionInputLocation) {
-        let input = match target {
-            InstructionInputLocation::Accumulator => self.registers.a,
-            InstructionInputLocation::Address(address) => self.bus.read(address),
-        };
+    fn run_ror(&mut self, input: u8) -> u8 {
         let carry = (self.registers.p & StatusFlags::CARRY).bits();
         let result = input.wrapping_shr(1) + (carry << 7);
-        self.persist_result_by_location(result, target);
-
         self.registers.p.set(StatusFlags::CARRY, input.is_bit_set(0));
         self.set_status_flag_zero(result);
         self.set_status_flag_negative(result);
+        result
     }
 
-    fn run_rti(&mut self) {
-        self.registers.p = StatusFlags::from_bits(self.stack_pull()).unwrap();
-
-        // TODO: hacky, find better way to account for instruction length being added
-        self.registers.pc = self.stack_pull_u16().wrapping_sub(1);
-    }
-
-    fn run_rts(&mut self) {
-        let address = self.stack_pull_u16();
-
-        // TODO: hacky, find better way to account for instruction length being added
-        self.registers.pc = address.wrapping_sub(1);
-    }
-
-    // TODO: figure out how exactly this bad boy works with flags C/V
+    /// Subtraction is addition of the one's complement, the carry acting as an inverted borrow.
     fn run_sbc(&mut self, input: u8) {
-        self.run_adc(!input);
+        if self.is_decimal_mode() {
+            self.run_sbc_decimal(input);
+        } else {
+            self.run_adc_binary(!input);
+        }
     }
 
     fn run_sec(&mut self) {
@@ -526,18 +1144,6 @@ impl Cpu {
         self.registers.p.insert(StatusFlags::INTERRUPT_DISABLE);
     }
 
-    fn run_sta(&mut self, target: u16) {
-        self.bus.write(target, self.registers.a);
-    }
-
-    fn run_stx(&mut self, target: u16) {
-        self.bus.write(target, self.registers.x);
-    }
-
-    fn run_sty(&mut self, target: u16) {
-        self.bus.write(target, self.registers.y);
-    }
-
     fn run_tax(&mut self) {
         self.registers.x = self.registers.a;
         self.set_status_flag_zero(self.registers.x);
@@ -572,26 +1178,132 @@ impl Cpu {
         self.set_status_flag_negative(self.registers.a);
     }
 
-    fn set_status_flag_carry(&mut self, input: u8, result: u8) {
-        self.registers.p.set(StatusFlags::CARRY, result < input);
+    fn run_alr(&mut self, input: u8) {
+        self.run_and(input);
+        self.registers.a = self.run_lsr(self.registers.a);
+    }
+
+    fn run_anc(&mut self, input: u8) {
+        self.run_and(input);
+        self.registers.p.set(StatusFlags::CARRY, self.registers.a.is_bit_set(7));
+    }
+
+    fn run_arr(&mut self, input: u8) {
+        let carry = (self.registers.p & StatusFlags::CARRY).bits();
+        let result = (self.registers.a & input).wrapping_shr(1) | (carry << 7);
+        self.registers.a = result;
+        self.set_status_flag_zero(result);
+        self.set_status_flag_negative(result);
+        self.registers.p.set(StatusFlags::CARRY, result.is_bit_set(6));
+        self.registers.p.set(StatusFlags::OVERFLOW, result.is_bit_set(6) != result.is_bit_set(5));
+    }
+
+    fn run_axs(&mut self, input: u8) {
+        let value = self.registers.a & self.registers.x;
+        self.registers.x = value.wrapping_sub(input);
+        self.registers.p.set(StatusFlags::CARRY, value >= input);
+        self.set_status_flag_zero(self.registers.x);
+        self.set_status_flag_negative(self.registers.x);
+    }
+
+    fn run_dcp(&mut self, input: u8) -> u8 {
+        let result = input.wrapping_sub(1);
+        self.run_cmp(result);
+        result
+    }
+
+    fn run_isc(&mut self, input: u8) -> u8 {
+        let result = input.wrapping_add(1);
+        self.run_sbc(result);
+        result
+    }
+
+    fn run_las(&mut self, input: u8) {
+        let result = input & self.registers.s;
+        self.registers.s = result;
+        self.run_lax(result);
+    }
+
+    fn run_lax(&mut self, input: u8) {
+        self.run_lda(input);
+        self.registers.x = input;
+    }
+
+    fn run_lxa(&mut self, input: u8) {
+        self.run_lax((self.registers.a | UNSTABLE_MAGIC) & input);
+    }
+
+    fn run_rla(&mut self, input: u8) -> u8 {
+        let result = self.run_rol(input);
+        self.run_and(result);
+        result
+    }
+
+    fn run_rra(&mut self, input: u8) -> u8 {
+        let result = self.run_ror(input);
+        self.run_adc(result);
+        result
+    }
+
+    /// The unstable stores AND their value with the high byte of the base address plus one,
+    /// and when indexing crossed a page, that value replaces the high byte of the address.
+    fn run_sh(&mut self, value: u8) -> u8 {
+        let high = (self.address_base >> 8) as u8;
+        let result = value & high.wrapping_add(1);
+
+        if self.address & 0xFF00 != self.address_base & 0xFF00 {
+            self.address = ((result as u16) << 8) | (self.address & 0x00FF);
+        }
+
+        result
+    }
+
+    fn run_slo(&mut self, input: u8) -> u8 {
+        let result = self.run_asl(input);
+        self.run_ora(result);
+        result
+    }
+
+    fn run_sre(&mut self, input: u8) -> u8 {
+        let result = self.run_lsr(input);
+        self.run_eor(result);
+        result
+    }
+
+    fn run_trb(&mut self, input: u8) -> u8 {
+        self.set_status_flag_zero(self.registers.a & input);
+        input & !self.registers.a
+    }
+
+    fn run_tsb(&mut self, input: u8) -> u8 {
+        self.set_status_flag_zero(self.registers.a & input);
+        input | self.registers.a
+    }
+
+    fn run_xaa(&mut self, input: u8) {
+        self.run_lda((self.registers.a | UNSTABLE_MAGIC) & self.registers.x & input);
     }
 
     fn set_status_flag_zero(&mut self, value: u8) {
         self.registers.p.set(StatusFlags::ZERO, value == 0);
     }
 
-    fn set_status_flag_overflow(&mut self, value_old: u8, value_new: u8) {
-        self.registers.p.set(StatusFlags::OVERFLOW, value_old.read_bit(7) != value_new.read_bit(7));
+    /// Signed overflow of an addition: both operands share a sign the result doesn't have.
+    fn set_status_flag_overflow(&mut self, left: u8, right: u8, result: u8) {
+        let overflow = (left ^ result) & (right ^ result);
+        self.registers.p.set(StatusFlags::OVERFLOW, overflow.is_bit_set(7));
     }
 
     fn set_status_flag_negative(&mut self, value: u8) {
         self.registers.p.set(StatusFlags::NEGATIVE, value.is_bit_set(7));
     }
 
-    fn persist_result_by_location(&mut self, result: u8, target: InstructionInputLocation) {
-        match target {
-            InstructionInputLocation::Accumulator => self.registers.a = result,
-            InstructionInputLocation::Address(address) => self.bus.write(address, result),
+    /// Masks interrupts on the way into a handler; the 65C02 also leaves decimal mode.
+    fn enter_handler(&mut self) {
+        self.registers.p.insert(StatusFlags::INTERRUPT_DISABLE);
+
+        if self.variant == CpuVariant::Cmos65C02 {
+            self.registers.p.remove(StatusFlags::DECIMAL);
         }
     }
 
@@ -600,37 +1312,16 @@ impl Cpu {
         self.registers.s = self.registers.s.wrapping_sub(1);
     }
 
-    fn stack_push_u16(&mut self, value: u16) {
-        let bytes = value.to_le_bytes();
-        self.stack_push(bytes[0]);
-        self.stack_push(bytes[1]);
-    }
-
     fn stack_pull(&mut self) -> u8 {
         let address = self.stack_determine_address().wrapping_add(1);
         let value = self.bus.read(address);
-        self.bus.write(address, 0);
         self.registers.s = self.registers.s.wrapping_add(1);
         value
     }
 
-    fn stack_pull_u16(&mut self) -> u16 {
-        let mut bytes = [self.stack_pull(), self.stack_pull()];
-        bytes.reverse();
-        u16::from_le_bytes(bytes)
-    }
-
     fn stack_determine_address(&self) -> u16 {
         0x0100 + self.registers.s as u16
     }
-
-    // TODO: unit test separately?
-    fn generate_interrupt(&mut self, break_type: BreakType) {
-        self.stack_push_u16(self.registers.pc);
-        self.stack_push(self.registers.p.bits());
-        self.registers.pc = self.vectors.irq;
-        self.registers.p.set_break(break_type);
-    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -649,21 +1340,16 @@ impl RegisterSet {
             a: 0,
             x: 0,
             y: 0,
-            s: 0xFF,
+            s: 0x00,
             p: StatusFlags::default(),
             pc: 0,
         }
     }
 }
 
-struct VectorSet {
-    nmi: u16,
-    reset: u16,
-    irq: u16,
-}
-
 bitflags! {
-    struct StatusFlags: u8 {
+    /// The P register. The break bits are only ever set in the copies of it on the stack.
+    pub struct StatusFlags: u8 {
         const NEGATIVE = 0b1000_0000;
         const OVERFLOW = 0b0100_0000;
         const BREAK_LEFT = 0b0010_0000;
@@ -675,18 +1361,20 @@ bitflags! {
     }
 }
 
+/// The break bits have no storage in P, they only exist in the copies pushed to the stack:
+/// bit 5 is always set there, and B tells BRK and PHP apart from IRQ and NMI.
 impl StatusFlags {
-    pub fn set_break(&mut self, break_type: BreakType) {
-        match break_type {
-            BreakType::Internal => {
-                self.insert(StatusFlags::BREAK_LEFT);
-                self.remove(StatusFlags::BREAK_RIGHT);
-            },
-            BreakType::Program => {
-                self.insert(StatusFlags::BREAK_LEFT);
-                self.insert(StatusFlags::BREAK_RIGHT);
-            },
-        }
+    fn pushed(self, break_type: BreakType) -> u8 {
+        let flags = match break_type {
+            BreakType::Internal => self | StatusFlags::BREAK_LEFT,
+            BreakType::Program => self | StatusFlags::BREAK_LEFT | StatusFlags::BREAK_RIGHT,
+        };
+
+        flags.bits()
+    }
+
+    fn pulled(value: u8) -> Self {
+        Self::from_bits_truncate(value) - (StatusFlags::BREAK_LEFT | StatusFlags::BREAK_RIGHT)
     }
 }
 
@@ -698,7 +1386,17 @@ impl Default for StatusFlags {
     }
 }
 
+#[derive(Copy, Clone)]
 enum BreakType {
     Internal,
     Program,
 }
+
+/// What the CPU is busy with until the next opcode fetch.
+#[derive(Copy, Clone)]
+enum Sequence {
+    Instruction(Instruction),
+    /// An NMI or IRQ.
+    Interrupt,
+    Reset,
+}
@@ -3,10 +3,14 @@
 #![cfg(test)]
 
 use super::*;
+use crate::cartridge::Mirroring;
+use crate::mapper::Mapper;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 
 const ADDRESS_PRG: u16 = 0x8000;
 const ADDRESS_IRQ: u16 = 0x5555;
-const INPUT_OPCODE: u8 = 0xFF;
+const ADDRESS_NMI: u16 = 0x6666;
 const INPUT_BYTE: u8 = 0x4F;
 const INPUT_ADDRESS_ZP: u16 = 0x0040;
 const INPUT_ADDRESS: u16 = 0x4020;
@@ -18,22 +22,121 @@ const INPUT_ADDRESS_INDIRECT_HIGH: u8 = INPUT_ADDRESS_INDIRECT.to_le_bytes()[1];
 const OFFSET_REGISTER_X: u8 = 0x12;
 const OFFSET_REGISTER_Y: u8 = 0x24;
 
+/// Writable memory across the whole cartridge space, so programs can be placed at $8000,
+/// logging every access the CPU makes to it.
+struct RamMapper {
+    bytes: Vec<u8>,
+    irq: Rc<Cell<bool>>,
+    accesses: AccessLog,
+}
+
+type AccessLog = Rc<RefCell<Vec<Access>>>;
+
+#[derive(Debug, PartialEq)]
+enum Access {
+    Read(u16),
+    Write(u16, u8),
+}
+
+impl Mapper for RamMapper {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        self.accesses.borrow_mut().push(Access::Read(address));
+        Some(self.bytes[address as usize])
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        self.accesses.borrow_mut().push(Access::Write(address, value));
+        self.bytes[address as usize] = value;
+    }
+
+    fn read_ppu(&mut self, _address: u16) -> u8 {
+        0
+    }
+
+    fn write_ppu(&mut self, _address: u16, _value: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Horizontal
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.get()
+    }
+}
+
 fn bus() -> Bus {
-    let mut bus = Bus::new();
+    bus_irq().0
+}
+
+fn bus_irq() -> (Bus, Rc<Cell<bool>>) {
+    let (bus, irq, _) = bus_parts();
+    (bus, irq)
+}
+
+fn bus_accesses() -> (Bus, AccessLog) {
+    let (bus, _, accesses) = bus_parts();
+    (bus, accesses)
+}
+
+fn bus_parts() -> (Bus, Rc<Cell<bool>>, AccessLog) {
+    let irq = Rc::new(Cell::new(false));
+    let accesses = Rc::new(RefCell::new(vec![]));
+    let mapper = RamMapper { bytes: vec![0; 0x10000], irq: irq.clone(), accesses: accesses.clone() };
+    let mut bus = Bus::new(Box::new(mapper));
     bus.write_u16(ADDRESS_VECTOR_RESET, ADDRESS_PRG).unwrap();
-    bus
+    bus.write_u16(ADDRESS_VECTOR_IRQ, ADDRESS_IRQ).unwrap();
+    (bus, irq, accesses)
 }
 
+/// A CPU spinning in a `JMP` loop at the program start, with another one as its NMI handler.
+fn cpu_nmi() -> Cpu {
+    let mut bus = bus();
+    bus.write_u16(ADDRESS_VECTOR_NMI, ADDRESS_NMI).unwrap();
+    bus.write_n(ADDRESS_PRG, &[0x4C, ADDRESS_PRG as u8, (ADDRESS_PRG >> 8) as u8]).unwrap();
+    bus.write_n(ADDRESS_NMI, &[0x4C, ADDRESS_NMI as u8, (ADDRESS_NMI >> 8) as u8]).unwrap();
+    cpu(bus)
+}
+
+/// A CPU past its reset sequence, with an empty stack and interrupts enabled.
 fn cpu(bus: Bus) -> Cpu {
-    let mut cpu = Cpu::new(bus).unwrap();
+    cpu_variant(bus, CpuVariant::Ricoh2A03)
+}
+
+fn cpu_variant(bus: Bus, variant: CpuVariant) -> Cpu {
+    let mut cpu = Cpu::with_variant(bus, variant).unwrap();
+
+    for _ in 0..7 {
+        cpu.tick().unwrap();
+    }
+
+    cpu.registers.s = 0xFF;
     cpu.registers.p.remove(StatusFlags::INTERRUPT_DISABLE);
     cpu
 }
 
 fn process_instruction(cpu: &mut Cpu, bytes: &[u8]) {
-    cpu.bus.write_n(cpu.registers.pc, bytes);
-    let instruction = cpu.determine_instruction_next().unwrap();
-    cpu.process_instruction(instruction).unwrap();
+    cpu.bus.write_n(cpu.registers.pc, bytes).unwrap();
+    cpu.step().unwrap();
+}
+
+/// Runs a single instruction and returns the accesses it made to the cartridge space.
+fn instruction_accesses(cpu: &mut Cpu, accesses: &RefCell<Vec<Access>>, bytes: &[u8]) -> Vec<Access> {
+    cpu.bus.write_n(cpu.registers.pc, bytes).unwrap();
+    accesses.borrow_mut().clear();
+    cpu.step().unwrap();
+    accesses.replace(vec![])
+}
+
+impl Cpu {
+    fn stack_push_u16(&mut self, value: u16) {
+        let bytes = value.to_le_bytes();
+        self.stack_push(bytes[1]);
+        self.stack_push(bytes[0]);
+    }
+
+    fn stack_pull_u16(&mut self) -> u16 {
+        u16::from_le_bytes([self.stack_pull(), self.stack_pull()])
+    }
 }
 
 fn lda_no_flags(cpu: &mut Cpu, value: u8) {
@@ -63,10 +166,13 @@ fn stack_push_pull() {
 
     assert_eq!(cpu.stack_pull(), 0x30);
     assert_eq!(cpu.stack_pull(), 0x20);
-    assert_eq!(cpu.bus.read(0x01FF), 0x10);
     assert_eq!(cpu.stack_pull(), 0x10);
-    assert_eq!(cpu.bus.read(0x01FF), 0);
     assert_eq!(cpu.registers.s, 0xFF);
+
+    // pulling leaves the stack contents alone
+    assert_eq!(cpu.bus.read(0x01FD), 0x30);
+    assert_eq!(cpu.bus.read(0x01FE), 0x20);
+    assert_eq!(cpu.bus.read(0x01FF), 0x10);
 }
 
 #[test]
@@ -75,13 +181,14 @@ fn stack_push_pull_u16() {
     cpu.stack_push_u16(0x2040);
     cpu.stack_push_u16(0x4080);
 
-    assert_eq!(cpu.stack_pull_u16(), 0x4080);
-    assert_eq!(cpu.bus.read(0x01FC), 0);
-    assert_eq!(cpu.bus.read(0x01FD), 0);
+    // the high byte is pushed first
+    assert_eq!(cpu.bus.read(0x01FF), 0x20);
+    assert_eq!(cpu.bus.read(0x01FE), 0x40);
+    assert_eq!(cpu.bus.read(0x01FD), 0x40);
+    assert_eq!(cpu.bus.read(0x01FC), 0x80);
 
+    assert_eq!(cpu.stack_pull_u16(), 0x4080);
     assert_eq!(cpu.stack_pull_u16(), 0x2040);
-    assert_eq!(cpu.bus.read(0x01FE), 0);
-    assert_eq!(cpu.bus.read(0x01FF), 0);
 }
 
 #[test]
@@ -106,166 +213,193 @@ fn stack_underflow() {
 }
 
 #[test]
-fn determine_input_implied() {
-    let cpu = cpu(bus());
-    let input = cpu.determine_input(
-        InstructionMode::Implied,
-        &[INPUT_OPCODE],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::Implied);
+fn addressing_implied() {
+    let (bus, accesses) = bus_accesses();
+    let mut cpu = cpu(bus);
+
+    // INX, with a dummy read of the next byte
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0xE8]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+    ]);
+    assert_eq!(cpu.registers.x, 1);
 }
 
 #[test]
-fn determine_input_accumulator() {
-    let cpu = cpu(bus());
-    let input = cpu.determine_input(
-        InstructionMode::Accumulator,
-        &[INPUT_OPCODE],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::Location(InstructionInputLocation::Accumulator));
+fn addressing_accumulator() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x21;
+    cpu.bus.write(INPUT_ADDRESS, INPUT_BYTE);
+
+    // ASL A
+    process_instruction(&mut cpu, &[0x0A]);
+    assert_eq!(cpu.registers.a, 0x42);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS), INPUT_BYTE);
 }
 
 // TODO: constants
 #[test]
-fn determine_input_relative_positive() {
-    let cpu = cpu(bus());
-    let input = cpu.determine_input(
-        InstructionMode::Relative,
-        &[INPUT_OPCODE, 0x0F],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(0x800F));
+fn addressing_relative_positive() {
+    let mut cpu = cpu(bus());
+
+    // BNE, taken
+    process_instruction(&mut cpu, &[0xD0, 0x0F]);
+    assert_eq!(cpu.registers.pc, 0x8011);
 }
 
 #[test]
-fn determine_input_zero_page() {
-    let cpu = cpu(bus());
-    let input = cpu.determine_input(
-        InstructionMode::ZeroPage,
-        &[INPUT_OPCODE, INPUT_ADDRESS_ZP as u8],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS_ZP));
+fn addressing_zero_page() {
+    let mut cpu = cpu(bus());
+    cpu.bus.write(INPUT_ADDRESS_ZP, INPUT_BYTE);
+
+    process_instruction(&mut cpu, &[0xA5, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
 }
 
 #[test]
-fn determine_input_zero_page_x() {
+fn addressing_zero_page_x() {
     let mut cpu = cpu(bus());
     cpu.registers.x = OFFSET_REGISTER_X;
+    cpu.bus.write(INPUT_ADDRESS_ZP + OFFSET_REGISTER_X as u16, INPUT_BYTE);
 
-    let input = cpu.determine_input(
-        InstructionMode::ZeroPageX,
-        &[INPUT_OPCODE, INPUT_ADDRESS_ZP as u8],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS_ZP + OFFSET_REGISTER_X as u16));
+    process_instruction(&mut cpu, &[0xB5, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
 }
 
 #[test]
-fn determine_input_zero_page_y() {
+fn addressing_zero_page_y() {
     let mut cpu = cpu(bus());
     cpu.registers.y = OFFSET_REGISTER_Y;
+    cpu.bus.write(INPUT_ADDRESS_ZP + OFFSET_REGISTER_Y as u16, INPUT_BYTE);
 
-    let input = cpu.determine_input(
-        InstructionMode::ZeroPageY,
-        &[INPUT_OPCODE, INPUT_ADDRESS_ZP as u8],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS_ZP + OFFSET_REGISTER_Y as u16));
+    // LDX
+    process_instruction(&mut cpu, &[0xB6, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.registers.x, INPUT_BYTE);
 }
 
 // TODO: constants
 #[test]
-fn determine_input_relative_negative() {
-    let cpu = cpu(bus());
-    let input = cpu.determine_input(
-        InstructionMode::Relative,
-        &[INPUT_OPCODE, 0xF0],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(0x7FF0));
+fn addressing_relative_negative() {
+    let mut cpu = cpu(bus());
+
+    // BNE, taken
+    process_instruction(&mut cpu, &[0xD0, 0xF0]);
+    assert_eq!(cpu.registers.pc, 0x7FF2);
 }
 
 #[test]
-fn determine_input_immediate() {
-    let cpu = cpu(bus());
-    let input = cpu.determine_input(
-        InstructionMode::Immediate,
-        &[INPUT_OPCODE, INPUT_BYTE],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::Byte(INPUT_BYTE));
+fn addressing_immediate() {
+    let mut cpu = cpu(bus());
+
+    process_instruction(&mut cpu, &[0xA9, INPUT_BYTE]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
 }
 
 #[test]
-fn determine_input_absolute() {
-    let cpu = cpu(bus());
-    let input = cpu.determine_input(
-        InstructionMode::Absolute,
-        &[INPUT_OPCODE, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS));
+fn addressing_absolute() {
+    let mut cpu = cpu(bus());
+    cpu.bus.write(INPUT_ADDRESS, INPUT_BYTE);
+
+    process_instruction(&mut cpu, &[0xAD, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
 }
 
 #[test]
-fn determine_input_absolute_x() {
+fn addressing_absolute_x() {
     let mut cpu = cpu(bus());
     cpu.registers.x = OFFSET_REGISTER_X;
+    cpu.bus.write(INPUT_ADDRESS + OFFSET_REGISTER_X as u16, INPUT_BYTE);
 
-    let input = cpu.determine_input(
-        InstructionMode::AbsoluteX,
-        &[INPUT_OPCODE, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS + OFFSET_REGISTER_X as u16));
+    process_instruction(&mut cpu, &[0xBD, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
 }
 
 #[test]
-fn determine_input_absolute_y() {
+fn addressing_absolute_y() {
     let mut cpu = cpu(bus());
     cpu.registers.y = OFFSET_REGISTER_Y;
+    cpu.bus.write(INPUT_ADDRESS + OFFSET_REGISTER_Y as u16, INPUT_BYTE);
 
-    let input = cpu.determine_input(
-        InstructionMode::AbsoluteY,
-        &[INPUT_OPCODE, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS + OFFSET_REGISTER_Y as u16));
+    process_instruction(&mut cpu, &[0xB9, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
 }
 
 #[test]
-fn determine_input_indirect() {
+fn addressing_indirect() {
     let mut bus = bus();
     bus.write_u16(INPUT_ADDRESS_INDIRECT, INPUT_ADDRESS).unwrap();
 
-    let cpu = cpu(bus);
-    let input = cpu.determine_input(
-        InstructionMode::Indirect,
-        &[INPUT_OPCODE, INPUT_ADDRESS_INDIRECT_LOW, INPUT_ADDRESS_INDIRECT_HIGH],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS));
+    // JMP
+    let mut cpu = cpu(bus);
+    process_instruction(&mut cpu, &[0x6C, INPUT_ADDRESS_INDIRECT_LOW, INPUT_ADDRESS_INDIRECT_HIGH]);
+    assert_eq!(cpu.registers.pc, INPUT_ADDRESS);
 }
 
 #[test]
-fn determine_input_indirect_x() {
+fn addressing_indirect_x() {
     let mut bus = bus();
     bus.write_u16(INPUT_ADDRESS_ZP + OFFSET_REGISTER_X as u16, INPUT_ADDRESS).unwrap();
+    bus.write(INPUT_ADDRESS, INPUT_BYTE);
 
     let mut cpu = cpu(bus);
     cpu.registers.x = OFFSET_REGISTER_X;
 
-    let input = cpu.determine_input(
-        InstructionMode::IndirectX,
-        &[INPUT_OPCODE, INPUT_ADDRESS_ZP as u8],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS));
+    process_instruction(&mut cpu, &[0xA1, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
 }
 
 #[test]
-fn determine_input_indirect_y() {
+fn addressing_indirect_y() {
     let mut bus = bus();
     bus.write_u16(INPUT_ADDRESS_ZP, INPUT_ADDRESS).unwrap();
+    bus.write(INPUT_ADDRESS + OFFSET_REGISTER_Y as u16, INPUT_BYTE);
 
     let mut cpu = cpu(bus);
     cpu.registers.y = OFFSET_REGISTER_Y;
 
-    let input = cpu.determine_input(
-        InstructionMode::IndirectY,
-        &[INPUT_OPCODE, INPUT_ADDRESS_ZP as u8],
-    ).unwrap();
-    assert_eq!(input, InstructionInput::from_address(INPUT_ADDRESS + OFFSET_REGISTER_Y as u16));
+    process_instruction(&mut cpu, &[0xB1, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
+}
+
+#[test]
+fn addressing_indirect_x_wrap() {
+    let mut bus = bus();
+    bus.write(0x00FF, INPUT_ADDRESS_LOW);
+    bus.write(0x0000, INPUT_ADDRESS_HIGH);
+    bus.write(INPUT_ADDRESS, INPUT_BYTE);
+
+    // both the indexed pointer and its high byte stay in the zero page
+    let mut cpu = cpu(bus);
+    cpu.registers.x = 0x10;
+    process_instruction(&mut cpu, &[0xA1, 0xEF]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
+}
+
+#[test]
+fn addressing_indirect_y_wrap() {
+    let mut bus = bus();
+    bus.write(0x00FF, INPUT_ADDRESS_LOW);
+    bus.write(0x0000, INPUT_ADDRESS_HIGH);
+    bus.write(0x0100, 0xFF);
+    bus.write(INPUT_ADDRESS + OFFSET_REGISTER_Y as u16, INPUT_BYTE);
+
+    let mut cpu = cpu(bus);
+    cpu.registers.y = OFFSET_REGISTER_Y;
+    process_instruction(&mut cpu, &[0xB1, 0xFF]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
+}
+
+#[test]
+fn addressing_indirect_page_wrap() {
+    let mut bus = bus();
+    bus.write(0x90FF, 0x34);
+    bus.write(0x9000, 0x12);
+    bus.write(0x9100, 0x56);
+
+    // JMP ($90FF), taking the high byte from the start of the same page
+    let mut cpu = cpu(bus);
+    process_instruction(&mut cpu, &[0x6C, 0xFF, 0x90]);
+    assert_eq!(cpu.registers.pc, 0x1234);
 }
 
 #[test]
@@ -408,18 +542,17 @@ fn process_brk_implied() {
     bus.write_u16(ADDRESS_VECTOR_IRQ, ADDRESS_IRQ).unwrap();
 
     let mut cpu = cpu(bus);
-    cpu.registers.p = StatusFlags::INTERRUPT_DISABLE | StatusFlags::ZERO;
-
-    process_instruction(&mut cpu, &[0x00]);
-    assert_eq!(cpu.registers.pc, 0x8001);
-    cpu.registers.p.remove(StatusFlags::INTERRUPT_DISABLE);
+    let flags = StatusFlags::INTERRUPT_DISABLE | StatusFlags::ZERO;
+    cpu.registers.p = flags;
 
+    // taken regardless of the I flag, with the pushed address skipping the padding byte
     let pc_old = cpu.registers.pc;
     process_instruction(&mut cpu, &[0x00]);
-    assert_eq!(cpu.stack_pull(), StatusFlags::ZERO.bits());
-    assert_eq!(cpu.stack_pull_u16(), pc_old);
+    let pushed = flags | StatusFlags::BREAK_LEFT | StatusFlags::BREAK_RIGHT;
+    assert_eq!(cpu.stack_pull(), pushed.bits());
+    assert_eq!(cpu.stack_pull_u16(), pc_old + 2);
     assert_eq!(cpu.registers.pc, ADDRESS_IRQ);
-    assert!(cpu.registers.p.contains(StatusFlags::BREAK_LEFT | StatusFlags::BREAK_RIGHT));
+    assert_eq!(cpu.registers.p, flags);
 }
 
 #[test]
@@ -796,7 +929,8 @@ fn process_php_implied() {
     cpu.registers.p = flags;
 
     process_instruction(&mut cpu, &[0x08]);
-    assert_eq!(cpu.stack_pull(), flags.bits());
+    let pushed = flags | StatusFlags::BREAK_LEFT | StatusFlags::BREAK_RIGHT;
+    assert_eq!(cpu.stack_pull(), pushed.bits());
 }
 
 #[test]
@@ -875,9 +1009,10 @@ fn process_rti_implied() {
     process_instruction(&mut cpu, &[0x00]);
     cpu.registers.p = StatusFlags::empty();
 
+    // back past BRK's padding byte
     process_instruction(&mut cpu, &[0x40]);
     assert_eq!(cpu.registers.p, flags);
-    assert_eq!(cpu.registers.pc, pc_old);
+    assert_eq!(cpu.registers.pc, pc_old + 2);
 }
 
 #[test]
@@ -892,8 +1027,58 @@ fn process_rts_implied() {
     process_instruction(&mut cpu, &[0xEA]);
     process_instruction(&mut cpu, &[0xEA]);
 
+    // back to the instruction after JSR
     process_instruction(&mut cpu, &[0x60]);
-    assert_eq!(cpu.registers.pc, pc_old + 2);
+    assert_eq!(cpu.registers.pc, pc_old + 3);
+}
+
+/// Reference for ADC and SBC going by the arithmetic value of the operands, as `(result, carry,
+/// overflow)`: carry when the unsigned result leaves 0..=255, or for SBC when it doesn't borrow,
+/// overflow when the signed result leaves -128..=127.
+fn arithmetic_reference(a: u8, input: u8, carry: bool, subtract: bool) -> (u8, bool, bool) {
+    let carry = carry as i16;
+
+    let (unsigned, signed) = if subtract {
+        (a as i16 - input as i16 - (1 - carry), a as i8 as i16 - input as i8 as i16 - (1 - carry))
+    } else {
+        (a as i16 + input as i16 + carry, a as i8 as i16 + input as i8 as i16 + carry)
+    };
+
+    let carry = if subtract { unsigned >= 0 } else { unsigned > 0xFF };
+    (unsigned as u8, carry, !(-128..=127).contains(&signed))
+}
+
+#[test]
+fn arithmetic_exhaustive() {
+    let mut cpu = cpu(bus());
+
+    for &subtract in &[false, true] {
+        for a in 0..=0xFF {
+            for input in 0..=0xFF {
+                for &carry in &[false, true] {
+                    cpu.registers.a = a;
+                    cpu.registers.p = StatusFlags::empty();
+                    cpu.registers.p.set(StatusFlags::CARRY, carry);
+
+                    if subtract {
+                        cpu.run_sbc(input);
+                    } else {
+                        cpu.run_adc(input);
+                    }
+
+                    let (result, carry, overflow) = arithmetic_reference(a, input, carry, subtract);
+                    let mut p = StatusFlags::empty();
+                    p.set(StatusFlags::CARRY, carry);
+                    p.set(StatusFlags::OVERFLOW, overflow);
+                    p.set(StatusFlags::ZERO, result == 0);
+                    p.set(StatusFlags::NEGATIVE, result.is_bit_set(7));
+
+                    assert_eq!(cpu.registers.a, result, "{:02X} {:02X} {:?}", a, input, subtract);
+                    assert_eq!(cpu.registers.p, p, "{:02X} {:02X} {:?}", a, input, subtract);
+                }
+            }
+        }
+    }
 }
 
 #[test]
@@ -904,7 +1089,7 @@ fn process_sbc_immediate() {
     cpu.registers.p.insert(StatusFlags::CARRY);
     process_instruction(&mut cpu, &[0xE9, 0x7F]);
     assert_eq!(cpu.registers.a, 0x80);
-    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE | StatusFlags::CARRY);
 
     cpu.registers.p.insert(StatusFlags::CARRY);
     process_instruction(&mut cpu, &[0xE9, 0x01]);
@@ -1073,3 +1258,1066 @@ fn process_tya_implied() {
     assert_eq!(cpu.registers.a, 0x80);
     assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE);
 }
+
+#[test]
+fn irq_serviced() {
+    let (bus, irq) = bus_irq();
+    let mut cpu = cpu(bus);
+    cpu.registers.p = StatusFlags::CARRY;
+
+    // the reset sequence doesn't poll, so the first instruction always runs
+    irq.set(true);
+    process_instruction(&mut cpu, &[0xEA]);
+    let cycles = cpu.bus.clock().cycles();
+
+    for _ in 0..7 {
+        assert_eq!(cpu.registers.pc, ADDRESS_PRG + 1);
+        cpu.tick().unwrap();
+    }
+
+    assert_eq!(cpu.registers.pc, ADDRESS_IRQ);
+    assert_eq!(cpu.registers.p, StatusFlags::INTERRUPT_DISABLE | StatusFlags::CARRY);
+    assert_eq!(cpu.stack_pull(), (StatusFlags::BREAK_LEFT | StatusFlags::CARRY).bits());
+    assert_eq!(cpu.stack_pull_u16(), ADDRESS_PRG + 1);
+    assert_eq!(cpu.bus.clock().cycles(), cycles + 7);
+}
+
+#[test]
+fn irq_masked() {
+    let (bus, irq) = bus_irq();
+    let mut cpu = cpu(bus);
+    cpu.registers.p.insert(StatusFlags::INTERRUPT_DISABLE);
+
+    irq.set(true);
+    process_instruction(&mut cpu, &[0xEA]);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 1);
+    assert_eq!(cpu.registers.s, 0xFF);
+}
+
+#[test]
+fn nmi_serviced_at_vblank() {
+    let mut cpu = cpu_nmi();
+    cpu.registers.p = StatusFlags::INTERRUPT_DISABLE | StatusFlags::CARRY;
+    cpu.bus.write(0x2000, 0x80);
+
+    while cpu.registers.pc != ADDRESS_NMI {
+        assert!(cpu.bus.ppu().scanline() < 242);
+        cpu.step().unwrap();
+    }
+
+    assert_eq!(cpu.bus.ppu().scanline(), 241);
+    let s = cpu.registers.s;
+
+    // the line stays asserted, but only its edge counts
+    for _ in 0..1000 {
+        cpu.step().unwrap();
+    }
+
+    assert_eq!(cpu.registers.s, s);
+    let pushed = StatusFlags::INTERRUPT_DISABLE | StatusFlags::CARRY | StatusFlags::BREAK_LEFT;
+    assert_eq!(cpu.stack_pull(), pushed.bits());
+    assert_eq!(cpu.stack_pull_u16(), ADDRESS_PRG);
+}
+
+#[test]
+fn nmi_enabled_during_vblank() {
+    let mut cpu = cpu_nmi();
+
+    while cpu.bus.ppu().scanline() != 245 {
+        cpu.step().unwrap();
+    }
+
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG);
+
+    // taken after the next instruction
+    cpu.bus.write(0x2000, 0x80);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_NMI);
+
+    // enabling it again while enabled is no new edge
+    cpu.registers.pc = ADDRESS_PRG;
+    cpu.bus.write(0x2000, 0x80);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG);
+
+    cpu.bus.write(0x2000, 0x00);
+    cpu.step().unwrap();
+    cpu.bus.write(0x2000, 0x80);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_NMI);
+}
+
+#[test]
+fn nmi_hijacks_brk() {
+    let mut bus = bus();
+    bus.write_u16(ADDRESS_VECTOR_NMI, ADDRESS_NMI).unwrap();
+
+    let mut cpu = cpu(bus);
+    cpu.bus.write(ADDRESS_PRG, 0x00);
+
+    // raised while the return address is pushed, before the vector is picked
+    for _ in 0..3 {
+        cpu.tick().unwrap();
+    }
+
+    cpu.nmi_pending = true;
+
+    for _ in 0..4 {
+        cpu.tick().unwrap();
+    }
+
+    assert!(cpu.sequence.is_none());
+    assert!(!cpu.nmi_pending);
+    assert_eq!(cpu.registers.pc, ADDRESS_NMI);
+    assert_eq!(cpu.stack_pull(), (StatusFlags::BREAK_LEFT | StatusFlags::BREAK_RIGHT).bits());
+    assert_eq!(cpu.stack_pull_u16(), ADDRESS_PRG + 2);
+}
+
+#[test]
+fn cli_delayed() {
+    let (bus, irq) = bus_irq();
+    let mut cpu = cpu(bus);
+    cpu.registers.p.insert(StatusFlags::INTERRUPT_DISABLE);
+    irq.set(true);
+
+    // the instruction after CLI still runs before the interrupt
+    process_instruction(&mut cpu, &[0x58]);
+    process_instruction(&mut cpu, &[0xEA]);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 2);
+
+    for _ in 0..7 {
+        cpu.tick().unwrap();
+    }
+
+    assert_eq!(cpu.registers.pc, ADDRESS_IRQ);
+}
+
+#[test]
+fn sei_delayed() {
+    let (bus, irq) = bus_irq();
+    let mut cpu = cpu(bus);
+    irq.set(true);
+
+    // polled before I is set, so the interrupt is taken right after SEI
+    process_instruction(&mut cpu, &[0x78]);
+    for _ in 0..7 {
+        cpu.tick().unwrap();
+    }
+
+    assert_eq!(cpu.registers.pc, ADDRESS_IRQ);
+
+    let pushed = StatusFlags::INTERRUPT_DISABLE | StatusFlags::BREAK_LEFT;
+    assert_eq!(cpu.stack_pull(), pushed.bits());
+    assert_eq!(cpu.stack_pull_u16(), ADDRESS_PRG + 1);
+}
+
+#[test]
+fn plp_delayed() {
+    let (bus, irq) = bus_irq();
+    let mut cpu = cpu(bus);
+    cpu.registers.p.insert(StatusFlags::INTERRUPT_DISABLE);
+    cpu.stack_push(0x00);
+    irq.set(true);
+
+    process_instruction(&mut cpu, &[0x28]);
+    process_instruction(&mut cpu, &[0xEA]);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 2);
+
+    for _ in 0..7 {
+        cpu.tick().unwrap();
+    }
+
+    assert_eq!(cpu.registers.pc, ADDRESS_IRQ);
+}
+
+#[test]
+fn reset_sequence() {
+    let mut cpu = Cpu::new(bus()).unwrap();
+    cpu.bus.write_n(0x0100, &[0xAA; 0x100]).unwrap();
+
+    for _ in 0..7 {
+        cpu.tick().unwrap();
+    }
+
+    // S goes down as if PC and P were pushed, but nothing is written
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG);
+    assert_eq!(cpu.registers.s, 0xFD);
+    assert!(cpu.registers.p.contains(StatusFlags::INTERRUPT_DISABLE));
+    assert_eq!(cpu.bus.read_n(0x0100, 0x100).unwrap(), [0xAA; 0x100].to_vec());
+
+    // also recovers a jammed CPU
+    cpu.bus.write(ADDRESS_PRG, 0x02);
+    assert!(cpu.step().is_err());
+    assert!(cpu.tick().is_err());
+
+    cpu.reset();
+
+    for _ in 0..7 {
+        cpu.tick().unwrap();
+    }
+
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG);
+    assert_eq!(cpu.registers.s, 0xFA);
+    assert_eq!(cpu.bus.read_n(0x0100, 0x100).unwrap(), [0xAA; 0x100].to_vec());
+}
+
+#[test]
+fn clock_speed() {
+    let clock = clock::Clock::new(clock::ClockMode::Pal);
+    assert_eq!(clock.speed(), 1_662_607);
+    assert_eq!(clock.master_cycles(), 0);
+}
+
+/// Base cycles of every opcode, zero for the KIL ones, which never finish.
+#[rustfmt::skip]
+const CYCLES_REFERENCE: [u8; 256] = [
+//  0  1  2  3  4  5  6  7  8  9  A  B  C  D  E  F
+    7, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // 0
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 1
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // 2
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 3
+    6, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // 4
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 5
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // 6
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // 7
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // 8
+    2, 6, 0, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // 9
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // A
+    2, 5, 0, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // B
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // C
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // D
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // E
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // F
+];
+
+/// Opcodes taking a cycle more when indexing crosses a page.
+const PAGE_PENALTY_REFERENCE: [u8; 32] = [
+    0x11, 0x19, 0x1D, 0x31, 0x39, 0x3D, 0x51, 0x59, 0x5D, 0x71, 0x79, 0x7D,
+    0xB1, 0xB9, 0xBC, 0xBD, 0xBE, 0xD1, 0xD9, 0xDD, 0xF1, 0xF9, 0xFD,
+    0x1C, 0x3C, 0x5C, 0x7C, 0xB3, 0xBB, 0xBF, 0xDC, 0xFC,
+];
+
+/// Runs a single instruction and returns the number of cycles it took.
+fn instruction_cycles(cpu: &mut Cpu, bytes: &[u8]) -> u64 {
+    let cycles = cpu.bus.clock().cycles();
+    process_instruction(cpu, bytes);
+    cpu.bus.clock().cycles() - cycles
+}
+
+#[test]
+fn cycles_base() {
+    for (opcode, &cycles) in CYCLES_REFERENCE.iter().enumerate() {
+        if cycles == 0 {
+            continue;
+        }
+
+        let instruction = Instruction::from_opcode(opcode as u8, CpuVariant::Ricoh2A03);
+        assert_eq!(instruction.cycles_base(), cycles, "opcode ${:02X}", opcode);
+
+        let page_penalty = PAGE_PENALTY_REFERENCE.contains(&(opcode as u8));
+        assert_eq!(instruction.page_penalty(), page_penalty, "opcode ${:02X}", opcode);
+    }
+}
+
+#[test]
+fn opcode_table() {
+    for &(variant, official) in &[(CpuVariant::Nmos6502, 151), (CpuVariant::Cmos65C02, 178)] {
+        let table = Instruction::table_for(variant);
+        assert_eq!(table.iter().filter(|instruction| instruction.official()).count(), official);
+
+        for instruction in table.iter() {
+            assert_eq!(instruction.len_bytes(), instruction.mode().len_bytes());
+            assert_eq!(instruction.mnemonic(), instruction.operation().mnemonic());
+        }
+    }
+}
+
+#[test]
+fn opcode_table_metadata() {
+    let nmos = Instruction::table_for(CpuVariant::Ricoh2A03);
+    let cmos = Instruction::table_for(CpuVariant::Cmos65C02);
+
+    let lda = nmos[0xAD];
+    assert_eq!((lda.mnemonic(), lda.len_bytes(), lda.cycles_base()), ("LDA", 3, 4));
+    assert_eq!(lda.flags_read(), StatusFlags::empty());
+    assert_eq!(lda.flags_written(), StatusFlags::NEGATIVE | StatusFlags::ZERO);
+    assert!(lda.official());
+
+    let adc = nmos[0x7D];
+    assert!(adc.page_penalty());
+    assert_eq!(adc.flags_read(), StatusFlags::DECIMAL | StatusFlags::CARRY);
+    assert_eq!(adc.flags_written(), StatusFlags::NEGATIVE | StatusFlags::OVERFLOW | StatusFlags::ZERO | StatusFlags::CARRY);
+
+    // duplicates of documented instructions aren't documented themselves
+    assert!(nmos[0xE9].official() && !nmos[0xEB].official());
+    assert!(nmos[0xEA].official() && !nmos[0x1A].official());
+    assert_eq!(cmos[0x1A].mnemonic(), "INC");
+    assert!(cmos[0x1A].official());
+
+    assert_eq!(cmos[0x89].flags_written(), StatusFlags::ZERO);
+    assert_eq!(cmos[0x00].flags_written(), StatusFlags::DECIMAL | StatusFlags::INTERRUPT_DISABLE);
+}
+
+#[test]
+fn cycles_within_timing() {
+    for &variant in &[CpuVariant::Ricoh2A03, CpuVariant::Nmos6502, CpuVariant::Cmos65C02] {
+        for (opcode, instruction) in Instruction::table_for(variant).iter().enumerate() {
+            if instruction.operation() == InstructionOperation::Kil {
+                continue;
+            }
+
+            // base cycles, plus one for a penalized page crossing or two for a taken branch
+            let cycles_extra = match instruction.mode() {
+                InstructionMode::Relative => 2,
+                _ => instruction.page_penalty() as u64,
+            };
+
+            let mut cpu = cpu_variant(bus(), variant);
+            cpu.registers.x = 0x20;
+            cpu.registers.y = 0x20;
+            let cycles = instruction_cycles(&mut cpu, &[opcode as u8, 0xF0, 0x02]);
+            let cycles_base = instruction.cycles_base() as u64;
+            assert!(
+                (cycles_base..=cycles_base + cycles_extra).contains(&cycles),
+                "{:?} opcode ${:02X} took {} cycles", variant, opcode, cycles,
+            );
+        }
+    }
+}
+
+#[test]
+fn cycles_page_crossed() {
+    let mut cpu = cpu(bus());
+    cpu.registers.x = 0x20;
+
+    // LDA $02C0,X
+    assert_eq!(instruction_cycles(&mut cpu, &[0xBD, 0xC0, 0x02]), 4);
+    // LDA $02F0,X
+    assert_eq!(instruction_cycles(&mut cpu, &[0xBD, 0xF0, 0x02]), 5);
+    // STA $02C0,X and STA $02F0,X
+    assert_eq!(instruction_cycles(&mut cpu, &[0x9D, 0xC0, 0x02]), 5);
+    assert_eq!(instruction_cycles(&mut cpu, &[0x9D, 0xF0, 0x02]), 5);
+    // INC $02F0,X
+    assert_eq!(instruction_cycles(&mut cpu, &[0xFE, 0xF0, 0x02]), 7);
+
+    // LDA ($40),Y
+    cpu.bus.write_u16(INPUT_ADDRESS_ZP, 0x02F0).unwrap();
+    cpu.registers.y = 0x08;
+    assert_eq!(instruction_cycles(&mut cpu, &[0xB1, INPUT_ADDRESS_ZP as u8]), 5);
+    cpu.registers.y = 0x10;
+    assert_eq!(instruction_cycles(&mut cpu, &[0xB1, INPUT_ADDRESS_ZP as u8]), 6);
+}
+
+#[test]
+fn cycles_branch() {
+    let mut cpu = cpu(bus());
+
+    // BNE not taken
+    cpu.registers.p = StatusFlags::ZERO;
+    assert_eq!(instruction_cycles(&mut cpu, &[0xD0, 0x10]), 2);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 2);
+
+    // BNE taken, forwards and backwards
+    cpu.registers.p = StatusFlags::empty();
+    assert_eq!(instruction_cycles(&mut cpu, &[0xD0, 0x10]), 3);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 0x14);
+    assert_eq!(instruction_cycles(&mut cpu, &[0xD0, 0xF0]), 3);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 0x06);
+
+    // BNE taken across a page, forwards and backwards
+    cpu.registers.pc = ADDRESS_PRG + 0xF0;
+    assert_eq!(instruction_cycles(&mut cpu, &[0xD0, 0x20]), 4);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 0x112);
+    assert_eq!(instruction_cycles(&mut cpu, &[0xD0, 0xE0]), 4);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 0xF4);
+
+    // taken to the next page boundary exactly still stays on the same page
+    cpu.registers.pc = ADDRESS_PRG + 0x80;
+    assert_eq!(instruction_cycles(&mut cpu, &[0xD0, 0x7D]), 3);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 0xFF);
+}
+
+#[test]
+fn tick_single_cycle() {
+    let (bus, accesses) = bus_accesses();
+    let mut cpu = cpu(bus);
+    cpu.bus.write(0x9000, INPUT_BYTE);
+    cpu.bus.write_n(ADDRESS_PRG, &[0xAD, 0x00, 0x90]).unwrap();
+    accesses.borrow_mut().clear();
+    let cycles = cpu.bus.clock().cycles();
+
+    // LDA $9000
+    for cycle in 1..=4 {
+        cpu.tick().unwrap();
+        assert_eq!(cpu.bus.clock().cycles(), cycles + cycle);
+        assert_eq!(accesses.borrow().len(), cycle as usize);
+    }
+
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
+    assert_eq!(accesses.replace(vec![]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+        Access::Read(ADDRESS_PRG + 2),
+        Access::Read(0x9000),
+    ]);
+}
+
+#[test]
+fn dummy_read_indexed() {
+    let (bus, accesses) = bus_accesses();
+    let mut cpu = cpu(bus);
+
+    // LDA $80F0,X, reading from the address before its high byte is fixed when crossing a page
+    cpu.registers.x = 0x05;
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0xBD, 0xF0, 0x80]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+        Access::Read(ADDRESS_PRG + 2),
+        Access::Read(0x80F5),
+    ]);
+
+    cpu.registers.pc = ADDRESS_PRG;
+    cpu.registers.x = 0x20;
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0xBD, 0xF0, 0x80]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+        Access::Read(ADDRESS_PRG + 2),
+        Access::Read(0x8010),
+        Access::Read(0x8110),
+    ]);
+
+    // STA $80F0,X, which always takes the dummy read
+    cpu.registers.pc = ADDRESS_PRG;
+    cpu.registers.x = 0x05;
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0x9D, 0xF0, 0x80]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+        Access::Read(ADDRESS_PRG + 2),
+        Access::Read(0x80F5),
+        Access::Write(0x80F5, cpu.registers.a),
+    ]);
+}
+
+#[test]
+fn dummy_write_read_modify_write() {
+    let (bus, accesses) = bus_accesses();
+    let mut cpu = cpu(bus);
+    cpu.bus.write(0x9000, 0x41);
+
+    // INC $9000, writing the value back unmodified first
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0xEE, 0x00, 0x90]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+        Access::Read(ADDRESS_PRG + 2),
+        Access::Read(0x9000),
+        Access::Write(0x9000, 0x41),
+        Access::Write(0x9000, 0x42),
+    ]);
+}
+
+#[test]
+fn dummy_read_branch() {
+    let (bus, accesses) = bus_accesses();
+    let mut cpu = cpu(bus);
+
+    // BNE, reading the next opcode while adding the offset, and from the wrong page after
+    cpu.registers.pc = ADDRESS_PRG + 0xF0;
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0xD0, 0x04]), [
+        Access::Read(ADDRESS_PRG + 0xF0),
+        Access::Read(ADDRESS_PRG + 0xF1),
+        Access::Read(ADDRESS_PRG + 0xF2),
+    ]);
+
+    cpu.registers.pc = ADDRESS_PRG + 0xF0;
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0xD0, 0x20]), [
+        Access::Read(ADDRESS_PRG + 0xF0),
+        Access::Read(ADDRESS_PRG + 0xF1),
+        Access::Read(ADDRESS_PRG + 0xF2),
+        Access::Read(ADDRESS_PRG + 0x12),
+    ]);
+}
+
+#[test]
+fn dummy_read_rts() {
+    let (bus, accesses) = bus_accesses();
+    let mut cpu = cpu(bus);
+    cpu.stack_push_u16(0x9000);
+
+    // the stack reads go to internal RAM, leaving the read of the return address
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0x60]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+        Access::Read(0x9000),
+    ]);
+}
+
+#[test]
+fn process_lax() {
+    let mut cpu = cpu(bus());
+    cpu.bus.write(INPUT_ADDRESS, 0x80);
+
+    process_instruction(&mut cpu, &[0xAF, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH]);
+    assert_eq!(cpu.registers.a, 0x80);
+    assert_eq!(cpu.registers.x, 0x80);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE);
+
+    // LXA, with A ORed with a chip-dependent constant first
+    process_instruction(&mut cpu, &[0xAB, 0x00]);
+    assert_eq!(cpu.registers.a, 0x00);
+    assert_eq!(cpu.registers.x, 0x00);
+    assert_eq!(cpu.registers.p, StatusFlags::ZERO);
+}
+
+#[test]
+fn process_sax() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0xF0;
+    cpu.registers.x = 0x3C;
+
+    process_instruction(&mut cpu, &[0x87, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS_ZP), 0x30);
+    assert_eq!(cpu.registers.p, StatusFlags::empty());
+}
+
+#[test]
+fn process_dcp() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x10;
+    cpu.bus.write(INPUT_ADDRESS_ZP, 0x11);
+
+    process_instruction(&mut cpu, &[0xC7, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS_ZP), 0x10);
+    assert_eq!(cpu.registers.a, 0x10);
+    assert_eq!(cpu.registers.p, StatusFlags::ZERO | StatusFlags::CARRY);
+}
+
+#[test]
+fn process_isc() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x20;
+    cpu.registers.p = StatusFlags::CARRY;
+    cpu.bus.write(INPUT_ADDRESS_ZP, 0x0F);
+
+    process_instruction(&mut cpu, &[0xE7, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS_ZP), 0x10);
+    assert_eq!(cpu.registers.a, 0x10);
+    assert!(cpu.registers.p.contains(StatusFlags::CARRY));
+}
+
+#[test]
+fn process_slo() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x04;
+    cpu.bus.write(INPUT_ADDRESS_ZP, 0x81);
+
+    process_instruction(&mut cpu, &[0x07, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS_ZP), 0x02);
+    assert_eq!(cpu.registers.a, 0x06);
+    assert_eq!(cpu.registers.p, StatusFlags::CARRY);
+}
+
+#[test]
+fn process_rla() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x0F;
+    cpu.registers.p = StatusFlags::CARRY;
+    cpu.bus.write(INPUT_ADDRESS_ZP, 0x81);
+
+    process_instruction(&mut cpu, &[0x27, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS_ZP), 0x03);
+    assert_eq!(cpu.registers.a, 0x03);
+    assert_eq!(cpu.registers.p, StatusFlags::CARRY);
+}
+
+#[test]
+fn process_sre() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x01;
+    cpu.bus.write(INPUT_ADDRESS_ZP, 0x03);
+
+    process_instruction(&mut cpu, &[0x47, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS_ZP), 0x01);
+    assert_eq!(cpu.registers.a, 0x00);
+    assert_eq!(cpu.registers.p, StatusFlags::ZERO | StatusFlags::CARRY);
+}
+
+#[test]
+fn process_rra() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x90;
+    cpu.registers.p = StatusFlags::CARRY;
+    cpu.bus.write(INPUT_ADDRESS_ZP, 0x02);
+
+    // the carry rotated out is added
+    process_instruction(&mut cpu, &[0x67, INPUT_ADDRESS_ZP as u8]);
+    assert_eq!(cpu.bus.read(INPUT_ADDRESS_ZP), 0x81);
+    assert_eq!(cpu.registers.a, 0x11);
+    assert_eq!(cpu.registers.p, StatusFlags::OVERFLOW | StatusFlags::CARRY);
+}
+
+#[test]
+fn process_anc() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0xF0;
+
+    process_instruction(&mut cpu, &[0x0B, 0x80]);
+    assert_eq!(cpu.registers.a, 0x80);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE | StatusFlags::CARRY);
+
+    process_instruction(&mut cpu, &[0x2B, 0x7F]);
+    assert_eq!(cpu.registers.a, 0x00);
+    assert_eq!(cpu.registers.p, StatusFlags::ZERO);
+}
+
+#[test]
+fn process_alr() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0xFF;
+
+    process_instruction(&mut cpu, &[0x4B, 0x03]);
+    assert_eq!(cpu.registers.a, 0x01);
+    assert_eq!(cpu.registers.p, StatusFlags::CARRY);
+}
+
+#[test]
+fn process_arr() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0xFF;
+    cpu.registers.p = StatusFlags::CARRY;
+
+    // C from bit 6, V from bit 6 XOR bit 5
+    process_instruction(&mut cpu, &[0x6B, 0xC0]);
+    assert_eq!(cpu.registers.a, 0xE0);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE | StatusFlags::CARRY);
+
+    cpu.registers.a = 0xFF;
+    process_instruction(&mut cpu, &[0x6B, 0x40]);
+    assert_eq!(cpu.registers.a, 0xA0);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE | StatusFlags::OVERFLOW);
+}
+
+#[test]
+fn process_axs() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0xF0;
+    cpu.registers.x = 0x3C;
+
+    process_instruction(&mut cpu, &[0xCB, 0x10]);
+    assert_eq!(cpu.registers.x, 0x20);
+    assert_eq!(cpu.registers.a, 0xF0);
+    assert_eq!(cpu.registers.p, StatusFlags::CARRY);
+
+    process_instruction(&mut cpu, &[0xCB, 0x21]);
+    assert_eq!(cpu.registers.x, 0xFF);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE);
+}
+
+#[test]
+fn process_las() {
+    let mut cpu = cpu(bus());
+    cpu.registers.s = 0xF0;
+    cpu.bus.write(INPUT_ADDRESS, 0x3C);
+
+    process_instruction(&mut cpu, &[0xBB, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH]);
+    assert_eq!(cpu.registers.a, 0x30);
+    assert_eq!(cpu.registers.x, 0x30);
+    assert_eq!(cpu.registers.s, 0x30);
+}
+
+#[test]
+fn process_xaa() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x0F;
+    cpu.registers.x = 0xF3;
+
+    process_instruction(&mut cpu, &[0x8B, 0x3F]);
+    assert_eq!(cpu.registers.a, 0x33);
+    assert_eq!(cpu.registers.x, 0xF3);
+}
+
+#[test]
+fn process_sbc_unofficial() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0x20;
+    cpu.registers.p = StatusFlags::CARRY;
+
+    process_instruction(&mut cpu, &[0xEB, 0x10]);
+    assert_eq!(cpu.registers.a, 0x10);
+}
+
+#[test]
+fn process_nop_multi_byte() {
+    let mut cpu = cpu(bus());
+
+    for bytes in [&[0x1A][..], &[0x80, 0xFF], &[0x04, 0xFF], &[0x14, 0xFF], &[0x0C, 0xFF, 0xFF], &[0x1C, 0xFF, 0xFF]].iter() {
+        let pc = cpu.registers.pc;
+        process_instruction(&mut cpu, bytes);
+        assert_eq!(cpu.registers.pc, pc + bytes.len() as u16);
+        assert_eq!(cpu.registers.a, 0);
+        assert_eq!(cpu.registers.p, StatusFlags::empty());
+    }
+}
+
+#[test]
+fn process_sh() {
+    let mut cpu = cpu(bus());
+    cpu.registers.a = 0xFF;
+    cpu.registers.x = 0xF7;
+    cpu.registers.y = 0x10;
+
+    // SHX $9000,Y, storing X ANDed with the high byte of the base address plus one
+    process_instruction(&mut cpu, &[0x9E, 0x00, 0x90]);
+    assert_eq!(cpu.bus.read(0x9010), 0x91);
+
+    // SHY $9000,X
+    process_instruction(&mut cpu, &[0x9C, 0x00, 0x90]);
+    assert_eq!(cpu.bus.read(0x90F7), 0x10);
+
+    // SHA $9000,Y
+    cpu.registers.a = 0x0F;
+    process_instruction(&mut cpu, &[0x9F, 0x00, 0x90]);
+    assert_eq!(cpu.bus.read(0x9010), 0x01);
+
+    // TAS $9000,Y, setting S to A AND X
+    process_instruction(&mut cpu, &[0x9B, 0x00, 0x90]);
+    assert_eq!(cpu.registers.s, 0x07);
+    assert_eq!(cpu.bus.read(0x9010), 0x01);
+
+    // crossing a page puts the value into the high byte of the address
+    cpu.registers.x = 0x0F;
+    cpu.registers.y = 0x20;
+    process_instruction(&mut cpu, &[0x9E, 0xF0, 0x90]);
+    assert_eq!(cpu.bus.read(0x9110), 0);
+    assert_eq!(cpu.bus.read(0x0110), 0x01);
+}
+
+#[test]
+fn kil_jams() {
+    let (mut bus, irq) = bus_irq();
+    bus.write(ADDRESS_PRG, 0x02);
+    let mut cpu = cpu(bus);
+
+    let jammed = CpuError::Jammed { address: ADDRESS_PRG };
+    assert_eq!(cpu.step(), Err(jammed));
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 1);
+
+    // only the rest of the system runs on, interrupts included
+    irq.set(true);
+    let cycles = cpu.bus.clock().cycles();
+    assert_eq!(cpu.tick(), Err(jammed));
+    assert_eq!(cpu.step(), Err(jammed));
+    assert_eq!(cpu.bus.clock().cycles(), cycles + 2);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 1);
+    assert_eq!(cpu.registers.s, 0xFF);
+}
+
+#[test]
+fn jsr_rts_round_trip() {
+    let mut cpu = cpu(bus());
+    cpu.bus.write_n(ADDRESS_PRG, &[0x20, INPUT_ADDRESS_LOW, INPUT_ADDRESS_HIGH, 0xE8]).unwrap();
+    cpu.bus.write(INPUT_ADDRESS, 0x60);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, INPUT_ADDRESS);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 3);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.x, 1);
+    assert_eq!(cpu.registers.s, 0xFF);
+}
+
+#[test]
+fn brk_rti_round_trip() {
+    let mut cpu = cpu(bus());
+    cpu.registers.p = StatusFlags::CARRY;
+    cpu.bus.write_n(ADDRESS_PRG, &[0x00, 0xFF, 0xE8]).unwrap();
+    cpu.bus.write(ADDRESS_IRQ, 0x40);
+
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_IRQ);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 2);
+    assert_eq!(cpu.registers.p, StatusFlags::CARRY);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.x, 1);
+    assert_eq!(cpu.registers.s, 0xFF);
+}
+
+#[test]
+fn branch_relative_to_next_instruction() {
+    let mut cpu = cpu(bus());
+
+    // BNE $00
+    process_instruction(&mut cpu, &[0xD0, 0x00]);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 2);
+
+    // BNE $FE, onto itself
+    process_instruction(&mut cpu, &[0xD0, 0xFE]);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 2);
+}
+
+#[test]
+fn jmp_low_address() {
+    let mut cpu = cpu(bus());
+
+    process_instruction(&mut cpu, &[0x4C, 0x01, 0x00]);
+    assert_eq!(cpu.registers.pc, 0x0001);
+}
+
+/// Decimal ADC and SBC on every pair of valid BCD operands.
+#[test]
+fn decimal_mode() {
+    for &variant in &[CpuVariant::Nmos6502, CpuVariant::Cmos65C02] {
+        let mut cpu = cpu_variant(bus(), variant);
+
+        for a in 0..100 {
+            for input in 0..100 {
+                for &carry in &[false, true] {
+                    let bcd = |value: u32| (((value / 10) << 4) | (value % 10)) as u8;
+
+                    cpu.registers.a = bcd(a);
+                    cpu.registers.p = StatusFlags::DECIMAL;
+                    cpu.registers.p.set(StatusFlags::CARRY, carry);
+                    cpu.run_adc(bcd(input));
+
+                    let sum = a + input + carry as u32;
+                    assert_eq!(cpu.registers.a, bcd(sum % 100), "{:?} {} + {}", variant, a, input);
+                    assert_eq!(cpu.registers.p.contains(StatusFlags::CARRY), sum > 99);
+
+                    cpu.registers.a = bcd(a);
+                    cpu.registers.p = StatusFlags::DECIMAL;
+                    cpu.registers.p.set(StatusFlags::CARRY, carry);
+                    cpu.run_sbc(bcd(input));
+
+                    let difference = 100 + a - input - !carry as u32;
+                    assert_eq!(cpu.registers.a, bcd(difference % 100), "{:?} {} - {}", variant, a, input);
+                    assert_eq!(cpu.registers.p.contains(StatusFlags::CARRY), difference >= 100);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn decimal_mode_flags() {
+    // 99 + 1: NMOS takes Z from the binary sum and N from the unadjusted one
+    let mut cpu = cpu_variant(bus(), CpuVariant::Nmos6502);
+    cpu.registers.a = 0x99;
+    cpu.registers.p = StatusFlags::DECIMAL;
+    process_instruction(&mut cpu, &[0x69, 0x01]);
+    assert_eq!(cpu.registers.a, 0x00);
+    assert_eq!(cpu.registers.p, StatusFlags::DECIMAL | StatusFlags::NEGATIVE | StatusFlags::CARRY);
+
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+    cpu.registers.a = 0x99;
+    cpu.registers.p = StatusFlags::DECIMAL;
+    process_instruction(&mut cpu, &[0x69, 0x01]);
+    assert_eq!(cpu.registers.a, 0x00);
+    assert_eq!(cpu.registers.p, StatusFlags::DECIMAL | StatusFlags::ZERO | StatusFlags::CARRY);
+
+    // the 2A03 has no decimal mode
+    let mut cpu = cpu_variant(bus(), CpuVariant::Ricoh2A03);
+    cpu.registers.a = 0x09;
+    cpu.registers.p = StatusFlags::DECIMAL;
+    process_instruction(&mut cpu, &[0x69, 0x01]);
+    assert_eq!(cpu.registers.a, 0x0A);
+}
+
+#[test]
+fn cmos_interrupt_clears_decimal() {
+    let mut bus = bus();
+    bus.write_u16(ADDRESS_VECTOR_IRQ, ADDRESS_IRQ).unwrap();
+
+    let mut cpu = cpu_variant(bus, CpuVariant::Cmos65C02);
+    cpu.registers.p = StatusFlags::DECIMAL;
+    process_instruction(&mut cpu, &[0x00]);
+    assert_eq!(cpu.registers.pc, ADDRESS_IRQ);
+    assert_eq!(cpu.registers.p, StatusFlags::INTERRUPT_DISABLE);
+}
+
+#[test]
+fn cmos_bra() {
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+    cpu.registers.p = StatusFlags::all();
+
+    assert_eq!(instruction_cycles(&mut cpu, &[0x80, 0x10]), 3);
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 0x12);
+}
+
+#[test]
+fn cmos_push_pull_index() {
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+    cpu.registers.x = 0x80;
+    cpu.registers.y = 0x00;
+
+    // PHX, PHY, PLX, PLY swap X and Y
+    process_instruction(&mut cpu, &[0xDA]);
+    process_instruction(&mut cpu, &[0x5A]);
+    process_instruction(&mut cpu, &[0xFA]);
+    assert_eq!(cpu.registers.x, 0x00);
+    assert_eq!(cpu.registers.p, StatusFlags::ZERO);
+
+    process_instruction(&mut cpu, &[0x7A]);
+    assert_eq!(cpu.registers.y, 0x80);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE);
+    assert_eq!(cpu.registers.s, 0xFF);
+}
+
+#[test]
+fn cmos_stz() {
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+    cpu.registers.a = 0xFF;
+    cpu.registers.x = 0x01;
+    cpu.bus.write_n(0x0040, &[0xFF; 2]).unwrap();
+    cpu.bus.write_n(0x9000, &[0xFF; 2]).unwrap();
+
+    process_instruction(&mut cpu, &[0x64, 0x40]);
+    process_instruction(&mut cpu, &[0x74, 0x40]);
+    process_instruction(&mut cpu, &[0x9C, 0x00, 0x90]);
+    assert_eq!(instruction_cycles(&mut cpu, &[0x9E, 0x00, 0x90]), 5);
+    assert_eq!(cpu.bus.read_n(0x0040, 2).unwrap(), [0x00; 2]);
+    assert_eq!(cpu.bus.read_n(0x9000, 2).unwrap(), [0x00; 2]);
+}
+
+#[test]
+fn cmos_trb_tsb() {
+    let (bus, accesses) = bus_accesses();
+    let mut cpu = cpu_variant(bus, CpuVariant::Cmos65C02);
+    cpu.registers.a = 0x0F;
+    cpu.bus.write(0x9000, 0x3C);
+
+    // TSB $9000, reading the operand twice instead of writing it back unmodified
+    assert_eq!(instruction_accesses(&mut cpu, &accesses, &[0x0C, 0x00, 0x90]), [
+        Access::Read(ADDRESS_PRG),
+        Access::Read(ADDRESS_PRG + 1),
+        Access::Read(ADDRESS_PRG + 2),
+        Access::Read(0x9000),
+        Access::Read(0x9000),
+        Access::Write(0x9000, 0x3F),
+    ]);
+    assert_eq!(cpu.registers.p, StatusFlags::empty());
+
+    // TRB $40, Z telling whether any of the bits were set
+    cpu.bus.write(0x0040, 0xF0);
+    process_instruction(&mut cpu, &[0x14, 0x40]);
+    assert_eq!(cpu.bus.read(0x0040), 0xF0);
+    assert_eq!(cpu.registers.p, StatusFlags::ZERO);
+}
+
+#[test]
+fn cmos_zero_page_indirect() {
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+    cpu.registers.y = 0xFF;
+    cpu.bus.write_u16(0x0040, 0x9000).unwrap();
+
+    // STA ($40), LDA ($40), not indexed by Y
+    cpu.registers.a = INPUT_BYTE;
+    assert_eq!(instruction_cycles(&mut cpu, &[0x92, 0x40]), 5);
+    assert_eq!(cpu.bus.read(0x9000), INPUT_BYTE);
+
+    cpu.registers.a = 0;
+    process_instruction(&mut cpu, &[0xB2, 0x40]);
+    assert_eq!(cpu.registers.a, INPUT_BYTE);
+}
+
+#[test]
+fn cmos_jmp_indirect() {
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+    cpu.bus.write(0x90FF, 0x34);
+    cpu.bus.write(0x9100, 0x12);
+
+    // JMP ($90FF), reading the high byte from the next page
+    assert_eq!(instruction_cycles(&mut cpu, &[0x6C, 0xFF, 0x90]), 6);
+    assert_eq!(cpu.registers.pc, 0x1234);
+
+    // JMP ($90FF,X)
+    cpu.registers.x = 0x01;
+    cpu.bus.write_u16(0x9100, 0x4321).unwrap();
+    assert_eq!(instruction_cycles(&mut cpu, &[0x7C, 0xFF, 0x90]), 6);
+    assert_eq!(cpu.registers.pc, 0x4321);
+}
+
+#[test]
+fn cmos_accumulator_and_bit() {
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+
+    // INC A, DEC A
+    process_instruction(&mut cpu, &[0x1A]);
+    assert_eq!(cpu.registers.a, 0x01);
+    process_instruction(&mut cpu, &[0x3A]);
+    process_instruction(&mut cpu, &[0x3A]);
+    assert_eq!(cpu.registers.a, 0xFF);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE);
+
+    // BIT #$C0 only sets Z
+    cpu.registers.a = 0x3F;
+    process_instruction(&mut cpu, &[0x89, 0xC0]);
+    assert_eq!(cpu.registers.p, StatusFlags::NEGATIVE | StatusFlags::ZERO);
+}
+
+#[test]
+fn cmos_nop() {
+    let mut cpu = cpu_variant(bus(), CpuVariant::Cmos65C02);
+
+    // the NMOS KIL and undocumented opcodes are NOPs, single cycle ones in columns 3, 7, B and F
+    for &(bytes, cycles) in [(&[0x02, 0xFF][..], 2), (&[0x03][..], 1), (&[0xFB][..], 1), (&[0xDC, 0xFF, 0xFF][..], 4)].iter() {
+        let pc = cpu.registers.pc;
+        assert_eq!(instruction_cycles(&mut cpu, bytes), cycles);
+        assert_eq!(cpu.registers.pc, pc + bytes.len() as u16);
+        assert_eq!(cpu.registers.a, 0);
+        assert_eq!(cpu.registers.p, StatusFlags::empty());
+    }
+}
+
+#[test]
+fn illegal_opcode_halt() {
+    let mut cpu = cpu(bus());
+    cpu.set_illegal_opcode_policy(IllegalOpcodePolicy::Halt);
+    cpu.bus.write(ADDRESS_PRG, 0x02);
+
+    // halted at the opcode, with nothing running on
+    let invalid = CpuError::InvalidOpcode { opcode: 0x02, address: ADDRESS_PRG };
+    assert_eq!(cpu.step(), Err(invalid));
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG);
+
+    let cycles = cpu.bus.clock().cycles();
+    assert_eq!(cpu.tick(), Err(invalid));
+    assert_eq!(cpu.bus.clock().cycles(), cycles);
+
+    // documented opcodes still run, and a reset resumes
+    cpu.reset();
+    cpu.bus.write(ADDRESS_PRG, 0xEA);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, ADDRESS_PRG + 1);
+}
+
+#[test]
+fn illegal_opcode_nop() {
+    let mut cpu = cpu(bus());
+    cpu.set_illegal_opcode_policy(IllegalOpcodePolicy::Nop);
+    cpu.registers.x = 0x01;
+    cpu.bus.write(0x0040, 0x42);
+
+    // KIL, LAX ($40,X) and SLO $9000,Y skip their operands without any effect
+    for &(bytes, cycles) in [(&[0x02][..], 2), (&[0xA3, 0x3F][..], 6), (&[0x1B, 0x00, 0x90][..], 4)].iter() {
+        let pc = cpu.registers.pc;
+        assert_eq!(instruction_cycles(&mut cpu, bytes), cycles);
+        assert_eq!(cpu.registers.pc, pc + bytes.len() as u16);
+        assert_eq!(cpu.registers.a, 0);
+        assert_eq!(cpu.registers.p, StatusFlags::empty());
+    }
+}
+
+#[test]
+fn instruction_next_to_vectors() {
+    let mut cpu = cpu(bus());
+    cpu.registers.pc = ADDRESS_VECTOR_NMI - 3;
+
+    // JMP $8000 ending right below the vectors
+    process_instruction(&mut cpu, &[0x4C, 0x00, 0x80]);
+    assert_eq!(cpu.registers.pc, 0x8000);
+}
@@ -0,0 +1,21 @@
+/// The members of the 6502 family the core can run as.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CpuVariant {
+    /// The NES CPU, an NMOS 6502 with its decimal mode cut out: SED and CLD still set and clear
+    /// D, but ADC and SBC ignore it.
+    Ricoh2A03,
+    /// The original NMOS 6502, with BCD arithmetic in decimal mode.
+    Nmos6502,
+    /// The CMOS 65C02 as first released, without the Rockwell bit instructions or WAI and STP.
+    /// Its decimal mode sets N and Z by the result, and interrupts clear D.
+    Cmos65C02,
+}
+
+impl CpuVariant {
+    pub fn has_decimal_mode(self) -> bool {
+        match self {
+            CpuVariant::Ricoh2A03 => false,
+            CpuVariant::Nmos6502 | CpuVariant::Cmos65C02 => true,
+        }
+    }
+}
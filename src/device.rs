@@ -0,0 +1,41 @@
+/// A component that can be attached to a bus and respond to reads and writes.
+///
+/// Reads return `None` when the device does not drive the data bus for the given address,
+/// in which case the bus keeps the last value it saw (open bus).
+pub trait Device {
+    fn read(&mut self, address: u16) -> Option<u8>;
+    fn write(&mut self, address: u16, value: u8);
+}
+
+/// RAM mirrored across the whole address range it is attached to.
+pub struct Ram {
+    bytes: Vec<u8>,
+}
+
+impl Ram {
+    pub fn new(len: usize) -> Self {
+        Self { bytes: vec![0; len] }
+    }
+}
+
+impl Device for Ram {
+    fn read(&mut self, address: u16) -> Option<u8> {
+        Some(self.bytes[address as usize % self.bytes.len()])
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        let len = self.bytes.len();
+        self.bytes[address as usize % len] = value;
+    }
+}
+
+/// Placeholder for address ranges that have nothing connected.
+pub struct OpenBus;
+
+impl Device for OpenBus {
+    fn read(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    fn write(&mut self, _address: u16, _value: u8) {}
+}
@@ -5,19 +5,30 @@ extern crate bitflags;
 #[macro_use]
 extern crate getset;
 
-mod types;
-mod bus;
-mod cpu;
-mod ui;
+pub mod types;
+pub mod apu;
+pub mod bus;
+pub mod cartridge;
+pub mod cpu;
+pub mod device;
+pub mod mapper;
+pub mod ppu;
+pub mod save;
+pub mod ui;
 
 use types::Result;
 use bus::Bus;
+use cartridge::Cartridge;
 use cpu::Cpu;
+use save::SaveFile;
 use ui::RuntimeUi;
 use tui::backend::CrosstermBackend;
 use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-pub fn run() -> Result {
+pub fn run(rom: impl AsRef<Path>) -> Result {
     let mut _ui = {
         let stdout = io::stdout();
         let backend = CrosstermBackend::new(stdout);
@@ -25,9 +36,48 @@ pub fn run() -> Result {
     };
     // ui.connect()?;
 
-    let bus = Bus::new();
+    let cartridge = Cartridge::load(&rom)?;
+    let mut save = if cartridge.header().battery() {
+        Some(SaveFile::for_rom(&rom))
+    } else {
+        None
+    };
+
+    let mut bus = Bus::with_cartridge(cartridge)?;
+
+    if let Some(data) = save.as_mut().map(SaveFile::load).transpose()?.flatten() {
+        bus.load_save_data(&data);
+    }
+
+    let quit = Arc::new(AtomicBool::new(false));
+    {
+        let quit = quit.clone();
+        ctrlc::set_handler(move || quit.store(true, Ordering::Relaxed))?;
+    }
+
     let mut cpu = Cpu::new(bus)?;
-    cpu.start()?;
+    let result = run_cpu(&mut cpu, save.as_mut(), &quit);
+
+    // write back on exit, whether quit or stopped by an error
+    let stored = match &mut save {
+        Some(save) => save.store(cpu.bus().save_data()),
+        None => Ok(()),
+    };
+
+    match (result, stored) {
+        (Err(error), Err(error_store)) => Err(anyhow!("{}, and {}", error, error_store)),
+        (result, stored) => result.and(stored),
+    }
+}
+
+fn run_cpu(cpu: &mut Cpu, mut save: Option<&mut SaveFile>, quit: &AtomicBool) -> Result {
+    while !quit.load(Ordering::Relaxed) {
+        cpu.step()?;
+
+        if let Some(save) = &mut save {
+            save.store_periodically(cpu.bus().save_data());
+        }
+    }
 
     Ok(())
 }
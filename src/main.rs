@@ -1,6 +1,8 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use nes::run;
+use std::env;
 
 fn main() -> Result<()> {
-    run()
+    let rom = env::args().nth(1).ok_or_else(|| anyhow!("usage: nes <rom>"))?;
+    run(rom)
 }
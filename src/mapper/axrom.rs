@@ -0,0 +1,63 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+use crate::types::BitRead;
+
+const PRG_BANK_LEN: usize = 0x8000;
+const CHR_BANK_LEN: usize = 0x2000;
+
+/// Mapper 7: a switchable 32 KiB PRG bank and a register-selected single-screen nametable.
+pub struct Axrom {
+    prg_rom: Memory,
+    chr: Memory,
+    bus_conflicts: bool,
+    prg_bank: usize,
+    mirroring: Mirroring,
+}
+
+impl Axrom {
+    pub fn new(cartridge: Cartridge, bus_conflicts: bool) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            chr: super::chr_memory(chr_rom, &header),
+            bus_conflicts,
+            prg_bank: 0,
+            mirroring: Mirroring::SingleScreenLower,
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank, PRG_BANK_LEN, address as usize),
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let value = super::resolve_bus_conflict(self, self.bus_conflicts, address, value);
+            self.prg_bank = (value & 0b0000_0111) as usize;
+            self.mirroring = if value.is_bit_set(4) {
+                Mirroring::SingleScreenUpper
+            } else {
+                Mirroring::SingleScreenLower
+            };
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr.read(0, CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(0, CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
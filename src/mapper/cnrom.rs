@@ -0,0 +1,57 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_LEN: usize = 0x8000;
+const CHR_BANK_LEN: usize = 0x2000;
+
+/// Mapper 3: fixed PRG ROM as on NROM and a switchable 8 KiB CHR bank.
+pub struct Cnrom {
+    prg_rom: Memory,
+    chr: Memory,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    chr_bank: usize,
+}
+
+impl Cnrom {
+    pub fn new(cartridge: Cartridge, bus_conflicts: bool) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            chr: super::chr_memory(chr_rom, &header),
+            mirroring: header.mirroring(),
+            bus_conflicts,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Cnrom {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x8000..=0xFFFF => self.prg_rom.read(0, PRG_BANK_LEN, address as usize),
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let value = super::resolve_bus_conflict(self, self.bus_conflicts, address, value);
+            self.chr_bank = value as usize;
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_bank, CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(self.chr_bank, CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,58 @@
+/// ROM or RAM on a cartridge board, addressed in banks that wrap around the available size.
+pub struct Memory {
+    bytes: Vec<u8>,
+    writable: bool,
+}
+
+impl Memory {
+    pub fn rom(bytes: Vec<u8>) -> Self {
+        Self { bytes, writable: false }
+    }
+
+    pub fn ram(len: usize) -> Self {
+        Self { bytes: vec![0; len], writable: true }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+
+    pub fn bank_count(&self, bank_len: usize) -> usize {
+        (self.bytes.len() / bank_len).max(1)
+    }
+
+    pub fn read(&self, bank: usize, bank_len: usize, offset: usize) -> Option<u8> {
+        self.resolve(bank, bank_len, offset).map(|index| self.bytes[index])
+    }
+
+    pub fn write(&mut self, bank: usize, bank_len: usize, offset: usize, value: u8) {
+        if !self.writable {
+            return;
+        }
+
+        if let Some(index) = self.resolve(bank, bank_len, offset) {
+            self.bytes[index] = value;
+        }
+    }
+
+    fn resolve(&self, bank: usize, bank_len: usize, offset: usize) -> Option<usize> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        let bank = bank % self.bank_count(bank_len);
+        Some((bank * bank_len + offset % bank_len) % self.bytes.len())
+    }
+}
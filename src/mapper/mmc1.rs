@@ -0,0 +1,190 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+use crate::types::BitRead;
+
+const PRG_BANK_LEN: usize = 0x4000;
+const PRG_RAM_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x1000;
+const PRG_OUTER_BANK_LEN: usize = 0x40000;
+
+/// Mapper 1: the MMC1, configured through a 5-bit serial shift register.
+///
+/// Boards with 8 KiB of CHR (SNROM, SOROM, SUROM, SXROM) repurpose the upper bits of the
+/// CHR bank registers to disable PRG RAM, bank PRG RAM and select a 256 KiB PRG ROM half.
+pub struct Mmc1 {
+    prg_rom: Memory,
+    prg_ram: Memory,
+    chr: Memory,
+    shift: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    cycle: u64,
+    cycle_last_write: Option<u64>,
+}
+
+impl Mmc1 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            prg_ram: Memory::ram(header.prg_ram_len() + header.prg_nvram_len()),
+            chr: super::chr_memory(chr_rom, &header),
+            shift: 0,
+            shift_count: 0,
+            control: 0b0_11_00,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            cycle: 0,
+            cycle_last_write: None,
+        }
+    }
+
+    fn write_shift(&mut self, address: u16, value: u8) {
+        if value.is_bit_set(7) {
+            self.shift = 0;
+            self.shift_count = 0;
+            self.control |= 0b0_11_00;
+            return;
+        }
+
+        self.shift |= (value & 1) << self.shift_count;
+        self.shift_count += 1;
+
+        if self.shift_count == 5 {
+            match address {
+                0x8000..=0x9FFF => self.control = self.shift,
+                0xA000..=0xBFFF => self.chr_bank_0 = self.shift,
+                0xC000..=0xDFFF => self.chr_bank_1 = self.shift,
+                _ => self.prg_bank = self.shift,
+            }
+
+            self.shift = 0;
+            self.shift_count = 0;
+        }
+    }
+
+    /// Boards with 8 KiB of CHR use the CHR bank register bits for other purposes.
+    fn is_chr_small(&self) -> bool {
+        self.chr.len() <= 0x2000
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        let disabled_snrom = self.is_chr_small()
+            && self.prg_rom.len() <= PRG_OUTER_BANK_LEN
+            && self.chr_bank_0.is_bit_set(4);
+
+        self.prg_bank.is_bit_clear(4) && !disabled_snrom
+    }
+
+    fn prg_ram_bank(&self) -> usize {
+        if !self.is_chr_small() {
+            return 0;
+        }
+
+        match self.prg_ram.bank_count(PRG_RAM_BANK_LEN) {
+            // SOROM
+            2 => ((self.chr_bank_0 >> 3) & 0b01) as usize,
+            // SXROM
+            4 => ((self.chr_bank_0 >> 2) & 0b11) as usize,
+            _ => 0,
+        }
+    }
+
+    fn prg_bank_outer(&self) -> usize {
+        if self.prg_rom.len() > PRG_OUTER_BANK_LEN {
+            (self.chr_bank_0 & 0b1_0000) as usize
+        } else {
+            0
+        }
+    }
+
+    fn prg_bank(&self, address: u16) -> usize {
+        let bank = (self.prg_bank & 0b0_1111) as usize;
+        let upper = address >= 0xC000;
+
+        let bank = match (self.control >> 2) & 0b11 {
+            0 | 1 => (bank & !1) | upper as usize,
+            2 => if upper { bank } else { 0 },
+            _ => if upper { 0b1111 } else { bank },
+        };
+
+        self.prg_bank_outer() | bank
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        let upper = address >= 0x1000;
+
+        if self.control.is_bit_set(4) {
+            (if upper { self.chr_bank_1 } else { self.chr_bank_0 }) as usize
+        } else {
+            (self.chr_bank_0 & !1) as usize | upper as usize
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.prg_ram.read(self.prg_ram_bank(), PRG_RAM_BANK_LEN, address as usize)
+            },
+            0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank(address), PRG_BANK_LEN, address as usize),
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.prg_ram.write(self.prg_ram_bank(), PRG_RAM_BANK_LEN, address as usize, value);
+            },
+            0x8000..=0xFFFF => {
+                // the serial port ignores writes on consecutive cycles, such as the double
+                // write of read-modify-write instructions
+                let consecutive = self.cycle_last_write
+                    .is_some_and(|cycle| self.cycle.wrapping_sub(cycle) <= 1);
+                self.cycle_last_write = Some(self.cycle);
+
+                if !consecutive {
+                    self.write_shift(address, value);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_bank(address), CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(self.chr_bank(address), CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.bytes()
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        self.prg_ram.bytes_mut()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.cycle = self.cycle.wrapping_add(1);
+    }
+}
@@ -0,0 +1,132 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x1000;
+
+/// Mapper 9: the MMC2, an 8 KiB switchable PRG bank and two latch-switched 4 KiB CHR banks.
+pub struct Mmc2 {
+    prg_rom: Memory,
+    chr: Memory,
+    prg_bank: u8,
+    latches: ChrLatches,
+}
+
+impl Mmc2 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            chr: super::chr_memory(chr_rom, &header),
+            prg_bank: 0,
+            latches: ChrLatches::new(header.mirroring(), false),
+        }
+    }
+}
+
+impl Mapper for Mmc2 {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        let bank_count = self.prg_rom.bank_count(PRG_BANK_LEN);
+
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_bank as usize,
+            0xA000..=0xFFFF => bank_count.saturating_sub(4) + ((address - 0x8000) >> 13) as usize,
+            _ => return None,
+        };
+
+        self.prg_rom.read(bank, PRG_BANK_LEN, address as usize)
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        match address {
+            0xA000..=0xAFFF => self.prg_bank = value & 0x0F,
+            0xB000..=0xFFFF => self.latches.write(address, value),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        let value = self.chr.read(self.latches.bank(address), CHR_BANK_LEN, address as usize).unwrap_or(0);
+        self.latches.observe(address);
+        value
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(self.latches.bank(address), CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.latches.mirroring()
+    }
+}
+
+/// The CHR latch pair shared by the MMC2 and MMC4.
+///
+/// Each pattern table half has two bank registers, one used after the PPU fetched tile $FD
+/// and one after tile $FE. The latch flips once the fetch completes, so the tile that
+/// triggers the switch is still read from the old bank.
+pub(super) struct ChrLatches {
+    /// Bank registers indexed by pattern table half, then by latch state ($FD, $FE).
+    banks: [[u8; 2]; 2],
+    /// Latch state per pattern table half, `false` for $FD and `true` for $FE.
+    latches: [bool; 2],
+    /// The MMC4 reacts to the whole 8-byte tile row in the lower half too.
+    ranged_lower: bool,
+    mirroring: Mirroring,
+}
+
+impl ChrLatches {
+    pub fn new(mirroring: Mirroring, ranged_lower: bool) -> Self {
+        Self {
+            banks: [[0; 2]; 2],
+            latches: [true; 2],
+            ranged_lower,
+            mirroring,
+        }
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    /// Handles writes to the bank and mirroring registers at $B000-$FFFF.
+    pub fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0xB000..=0xBFFF => self.banks[0][0] = value & 0x1F,
+            0xC000..=0xCFFF => self.banks[0][1] = value & 0x1F,
+            0xD000..=0xDFFF => self.banks[1][0] = value & 0x1F,
+            0xE000..=0xEFFF => self.banks[1][1] = value & 0x1F,
+            0xF000..=0xFFFF => {
+                self.mirroring = if value & 1 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            },
+            _ => {},
+        }
+    }
+
+    pub fn bank(&self, address: u16) -> usize {
+        let half = (address >> 12) as usize & 1;
+        self.banks[half][self.latches[half] as usize] as usize
+    }
+
+    pub fn observe(&mut self, address: u16) {
+        let half = (address >> 12) as usize & 1;
+        let ranged = half == 1 || self.ranged_lower;
+
+        let (tile, row) = ((address >> 4) & 0xFF, address & 0x000F);
+        let matched = if ranged { row >= 8 } else { row == 8 };
+
+        if matched {
+            match tile {
+                0xFD => self.latches[half] = false,
+                0xFE => self.latches[half] = true,
+                _ => {},
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+use crate::types::BitRead;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const PRG_RAM_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+/// Number of CPU cycles PPU A12 has to stay low before a rising edge clocks the IRQ counter.
+const A12_LOW_CYCLES_MIN: u64 = 3;
+
+/// Mapper 4: the MMC3, with eight bank registers and a scanline counter clocked by PPU A12.
+pub struct Mmc3 {
+    prg_rom: Memory,
+    prg_ram: Memory,
+    chr: Memory,
+    revision: Mmc3Revision,
+    mirroring_fixed: Option<Mirroring>,
+    mirroring: Mirroring,
+    bank_select: u8,
+    banks: [u8; 8],
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12: bool,
+    cycle: u64,
+    cycle_a12_low: u64,
+}
+
+/// IRQ behaviour of the different MMC3 chips, selected by NES 2.0 submapper.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Mmc3Revision {
+    /// Sharp MMC3B/C: an IRQ is raised whenever the counter is 0 after being clocked.
+    B,
+    /// NEC MMC3A: an IRQ is raised only when the counter becomes 0 by decrementing, or by
+    /// a reload requested through $C001.
+    A,
+}
+
+impl Mmc3 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        let revision = match header.submapper() {
+            4 => Mmc3Revision::A,
+            _ => Mmc3Revision::B,
+        };
+
+        let mirroring_fixed = match header.mirroring() {
+            Mirroring::FourScreen => Some(Mirroring::FourScreen),
+            _ => None,
+        };
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            prg_ram: Memory::ram(header.prg_ram_len() + header.prg_nvram_len()),
+            chr: super::chr_memory(chr_rom, &header),
+            revision,
+            mirroring_fixed,
+            mirroring: header.mirroring(),
+            bank_select: 0,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            prg_ram_protect: 0b1000_0000,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12: false,
+            cycle: 0,
+            cycle_a12_low: 0,
+        }
+    }
+
+    pub fn revision(&self) -> Mmc3Revision {
+        self.revision
+    }
+
+    fn prg_bank(&self, address: u16) -> usize {
+        let bank_count = self.prg_rom.bank_count(PRG_BANK_LEN);
+        let bank_second_last = bank_count.saturating_sub(2);
+        let swapped = self.bank_select.is_bit_set(6);
+
+        match ((address >> 13) & 0b11, swapped) {
+            (0, false) | (2, true) => (self.banks[6] & 0b0011_1111) as usize,
+            (0, true) | (2, false) => bank_second_last,
+            (1, _) => (self.banks[7] & 0b0011_1111) as usize,
+            _ => bank_count - 1,
+        }
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        let mut slot = (address >> 10) & 0b111;
+
+        if self.bank_select.is_bit_set(7) {
+            slot ^= 0b100;
+        }
+
+        match slot {
+            0 => (self.banks[0] & !1) as usize,
+            1 => (self.banks[0] | 1) as usize,
+            2 => (self.banks[1] & !1) as usize,
+            3 => (self.banks[1] | 1) as usize,
+            _ => self.banks[slot as usize - 2] as usize,
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_ram_protect.is_bit_set(7)
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_enabled() && self.prg_ram_protect.is_bit_clear(6)
+    }
+
+    /// Watches PPU A12 for rising edges, ignoring the short pulses between sprite fetches.
+    fn observe_a12(&mut self, address: u16) {
+        let a12 = address & 0x1000 != 0;
+
+        if a12 && !self.a12 && self.cycle - self.cycle_a12_low >= A12_LOW_CYCLES_MIN {
+            self.clock_irq_counter();
+        }
+
+        if !a12 && self.a12 {
+            self.cycle_a12_low = self.cycle;
+        }
+
+        self.a12 = a12;
+    }
+
+    fn clock_irq_counter(&mut self) {
+        let counter_old = self.irq_counter;
+        let reload = self.irq_reload;
+
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        self.irq_reload = false;
+
+        let trigger = match self.revision {
+            Mmc3Revision::B => self.irq_counter == 0,
+            Mmc3Revision::A => self.irq_counter == 0 && (counter_old != 0 || reload),
+        };
+
+        if trigger && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.prg_ram.read(0, PRG_RAM_BANK_LEN, address as usize)
+            },
+            0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank(address), PRG_BANK_LEN, address as usize),
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        let even = address & 1 == 0;
+
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_writable() => {
+                self.prg_ram.write(0, PRG_RAM_BANK_LEN, address as usize, value);
+            },
+            0x8000..=0x9FFF if even => self.bank_select = value,
+            0x8000..=0x9FFF => self.banks[(self.bank_select & 0b111) as usize] = value,
+            0xA000..=0xBFFF if even => {
+                self.mirroring = self.mirroring_fixed.unwrap_or(if value.is_bit_set(0) {
+                    Mirroring::Horizontal
+                } else {
+                    Mirroring::Vertical
+                });
+            },
+            0xA000..=0xBFFF => self.prg_ram_protect = value,
+            0xC000..=0xDFFF if even => self.irq_latch = value,
+            0xC000..=0xDFFF => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            0xE000..=0xFFFF if even => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            0xE000..=0xFFFF => self.irq_enabled = true,
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.observe_a12(address);
+        self.chr.read(self.chr_bank(address), CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.observe_a12(address);
+        self.chr.write(self.chr_bank(address), CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.bytes()
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        self.prg_ram.bytes_mut()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.cycle += 1;
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn cycles_until_irq(&self) -> Option<u32> {
+        if self.irq_enabled { Some(0) } else { None }
+    }
+}
@@ -0,0 +1,76 @@
+use super::mmc2::ChrLatches;
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_LEN: usize = 0x4000;
+const PRG_RAM_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x1000;
+
+/// Mapper 10: the MMC4, the MMC2 latch logic with 16 KiB PRG banking and PRG RAM.
+pub struct Mmc4 {
+    prg_rom: Memory,
+    prg_ram: Memory,
+    chr: Memory,
+    prg_bank: u8,
+    latches: ChrLatches,
+}
+
+impl Mmc4 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            prg_ram: Memory::ram(header.prg_ram_len() + header.prg_nvram_len()),
+            chr: super::chr_memory(chr_rom, &header),
+            prg_bank: 0,
+            latches: ChrLatches::new(header.mirroring(), true),
+        }
+    }
+}
+
+impl Mapper for Mmc4 {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.prg_ram.read(0, PRG_RAM_BANK_LEN, address as usize),
+            0x8000..=0xBFFF => self.prg_rom.read(self.prg_bank as usize, PRG_BANK_LEN, address as usize),
+            0xC000..=0xFFFF => {
+                let bank_last = self.prg_rom.bank_count(PRG_BANK_LEN) - 1;
+                self.prg_rom.read(bank_last, PRG_BANK_LEN, address as usize)
+            },
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => self.prg_ram.write(0, PRG_RAM_BANK_LEN, address as usize, value),
+            0xA000..=0xAFFF => self.prg_bank = value & 0x0F,
+            0xB000..=0xFFFF => self.latches.write(address, value),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        let value = self.chr.read(self.latches.bank(address), CHR_BANK_LEN, address as usize).unwrap_or(0);
+        self.latches.observe(address);
+        value
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(self.latches.bank(address), CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.latches.mirroring()
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.bytes()
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        self.prg_ram.bytes_mut()
+    }
+}
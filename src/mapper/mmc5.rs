@@ -0,0 +1,517 @@
+use super::{Mapper, Memory};
+use crate::apu::{self, Pulse, PulseChannel};
+use crate::cartridge::{Cartridge, Mirroring};
+use crate::types::BitRead;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_SPLIT_BANK_LEN: usize = 0x1000;
+const EXRAM_LEN: usize = 0x0400;
+/// Offset of the attribute bytes within a nametable, and within ExRAM in split mode.
+const ATTRIBUTES: u16 = 0x03C0;
+/// Number of CPU cycles without a PPU read after which rendering is considered stopped.
+const PPU_IDLE_CYCLES: u8 = 3;
+/// Nametable fetches of a scanline's visible tiles, counted from the scanline start.
+const FETCHES_BACKGROUND: u8 = 32;
+/// Garbage nametable fetches made alongside the sprite pattern fetches, two per sprite.
+const FETCHES_SPRITE: u8 = 16;
+/// CPU cycles between clocks of the audio frame sequencer, which runs at a fixed 240 Hz.
+const AUDIO_FRAME_CYCLES: u16 = 7457;
+/// Output level of the PCM channel at full scale, comparable to a full-scale DMC.
+const PCM_LEVEL: f32 = 0.5;
+
+/// Mapper 5: the MMC5, with four PRG and CHR banking modes, 1 KiB of ExRAM usable as a
+/// nametable, extended attributes or a vertical split, a scanline IRQ, a multiplier and
+/// three extra sound channels.
+///
+/// The MMC5 has no A12 or scanline input; it works out where the PPU is from the fetches
+/// it sees. Three identical nametable reads in a row happen only at the end of a rendered
+/// scanline (dots 337 and 339, then dot 1 of the next one), and from there on the number
+/// of nametable fetches tells background tiles from sprites.
+pub struct Mmc5 {
+    prg_rom: Memory,
+    prg_ram: Memory,
+    chr: Memory,
+    exram: Vec<u8>,
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametables: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    /// $5113-$5117: the PRG RAM bank at $6000, then the four $8000-$FFFF registers.
+    prg_banks: [u8; 5],
+    /// $5120-$512B with the $5130 upper bits latched at the time of the write.
+    chr_banks: [u16; 12],
+    chr_upper: u8,
+    /// Whether the last CHR bank register written was one of the background set $5128-$512B.
+    chr_background_last: bool,
+    sprite_8x16: bool,
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    multiplicand: u8,
+    multiplier: u8,
+    in_frame: bool,
+    scanline: u8,
+    ppu_idle: u8,
+    nametable_last: Option<u16>,
+    nametable_repeats: u8,
+    fetches: u8,
+    tile: TileSource,
+    pulses: [Pulse; 2],
+    pcm_control: u8,
+    pcm: u8,
+    pcm_irq: bool,
+    audio_cycle: u16,
+    audio_odd: bool,
+}
+
+/// Where the data of the background tile currently being fetched comes from.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum TileSource {
+    Nametable,
+    /// Extended attributes: the ExRAM byte matching the nametable entry.
+    Extended(u8),
+    /// Vertical split: the ExRAM nametable entry and the split's vertical position.
+    Split { index: u16, y: u16 },
+}
+
+impl Mmc5 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            prg_ram: Memory::ram(header.prg_ram_len() + header.prg_nvram_len()),
+            chr: super::chr_memory(chr_rom, &header),
+            exram: vec![0; EXRAM_LEN],
+            prg_mode: 3,
+            chr_mode: 3,
+            prg_ram_protect: [0; 2],
+            exram_mode: 0,
+            nametables: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0xFF; 5],
+            chr_banks: [0; 12],
+            chr_upper: 0,
+            chr_background_last: false,
+            sprite_8x16: false,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            in_frame: false,
+            scanline: 0,
+            ppu_idle: 0,
+            nametable_last: None,
+            nametable_repeats: 0,
+            fetches: 0,
+            tile: TileSource::Nametable,
+            pulses: [Pulse::new(PulseChannel::Mmc5), Pulse::new(PulseChannel::Mmc5)],
+            pcm_control: 0,
+            pcm: 0,
+            pcm_irq: false,
+            audio_cycle: 0,
+            audio_odd: false,
+        }
+    }
+
+    /// 8 KiB bank mapped at `address` in $8000-$FFFF, and whether it is ROM rather than RAM.
+    fn prg_bank(&self, address: u16) -> (usize, bool) {
+        let slot = ((address >> 13) & 0b11) as u8;
+
+        let (register, mask) = match (self.prg_mode, slot) {
+            (0, _) => (4, 0b0111_1100),
+            (1, 0 | 1) | (2, 0 | 1) => (2, 0b0111_1110),
+            (1, _) => (4, 0b0111_1110),
+            (2, 2) => (3, 0b0111_1111),
+            (2, _) => (4, 0b0111_1111),
+            (_, slot) => (1 + slot as usize, 0b0111_1111),
+        };
+
+        let value = self.prg_banks[register];
+        let bank = (value & mask) | (slot & !mask);
+        // $5117 always maps ROM, the others select with bit 7
+        (bank as usize, register == 4 || value.is_bit_set(7))
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0b10, 0b01]
+    }
+
+    /// CHR bank and bank length for `address`, from the sprite set $5120-$5127 or the
+    /// background set $5128-$512B.
+    fn chr_bank(&self, address: u16, background: bool) -> (usize, usize) {
+        let (register, bank_len) = match (self.chr_mode, background) {
+            (0, false) => (7, 0x2000),
+            (0, true) => (11, 0x2000),
+            (1, false) => (3 + (address >> 12) as usize * 4, 0x1000),
+            (1, true) => (11, 0x1000),
+            (2, false) => (1 + (address >> 11) as usize * 2, 0x0800),
+            (2, true) => (9 + ((address >> 11) & 1) as usize * 2, 0x0800),
+            (_, false) => ((address >> 10) as usize, 0x0400),
+            (_, true) => (8 + ((address >> 10) & 0b11) as usize, 0x0400),
+        };
+
+        (self.chr_banks[register] as usize, bank_len)
+    }
+
+    /// Whether the PPU uses the background CHR set for its current fetch: with 8x16 sprites
+    /// backgrounds and sprites each get their own set, otherwise the last written set wins.
+    fn chr_background(&self, sprite: bool) -> bool {
+        if self.in_frame && self.sprite_8x16 {
+            !sprite
+        } else {
+            self.chr_background_last
+        }
+    }
+
+    fn is_sprite_fetch(&self) -> bool {
+        self.in_frame && (FETCHES_BACKGROUND..FETCHES_BACKGROUND + FETCHES_SPRITE).contains(&self.fetches)
+    }
+
+    fn is_background_fetch(&self) -> bool {
+        self.in_frame && !self.is_sprite_fetch()
+    }
+
+    /// Tracks PPU reads for the scanline detection and the rendering state.
+    fn observe_ppu_read(&mut self, address: u16) {
+        self.ppu_idle = PPU_IDLE_CYCLES;
+
+        if self.nametable_last == Some(address) {
+            self.nametable_repeats += 1;
+
+            if self.nametable_repeats == 2 {
+                self.start_scanline();
+            }
+        } else {
+            self.nametable_repeats = 0;
+        }
+
+        self.nametable_last = (0x2000..=0x2FFF).contains(&address).then_some(address);
+    }
+
+    fn start_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline = self.scanline.wrapping_add(1);
+
+            if self.scanline == self.irq_compare {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+        }
+
+        // The fetch that completed the detection is the first of the scanline
+        self.fetches = 0;
+    }
+
+    fn leave_frame(&mut self) {
+        self.in_frame = false;
+        self.nametable_last = None;
+        self.nametable_repeats = 0;
+    }
+
+    /// Chooses the source of the background tile whose nametable entry is fetched next.
+    fn fetch_tile(&mut self, address: u16) -> TileSource {
+        if !self.is_background_fetch() || self.exram_mode > 1 {
+            return TileSource::Nametable;
+        }
+
+        // The first fetches of a scanline are for its third tile, the first two come after the
+        // sprite fetches of the scanline before
+        let (x, scanline) = if self.fetches < FETCHES_BACKGROUND {
+            (self.fetches as u16 + 2, self.scanline as u16)
+        } else {
+            ((self.fetches - FETCHES_BACKGROUND - FETCHES_SPRITE) as u16, self.scanline as u16 + 1)
+        };
+
+        if self.split_control.is_bit_set(7) {
+            let threshold = (self.split_control & 0b1_1111) as u16;
+            let right = self.split_control.is_bit_set(6);
+
+            if right == (x >= threshold) {
+                let y = (self.split_scroll as u16 + scanline) % 240;
+                return TileSource::Split { index: (y / 8) * 32 + x % 32, y };
+            }
+        }
+
+        match self.exram_mode {
+            1 => TileSource::Extended(self.exram[(address & 0x03FF) as usize]),
+            _ => TileSource::Nametable,
+        }
+    }
+
+    fn read_split_attribute(&self, index: u16) -> u8 {
+        let (x, y) = (index % 32, index / 32);
+        let attribute = self.exram[(ATTRIBUTES + (y / 4) * 8 + x / 4) as usize];
+        let shift = ((y & 0b10) << 1) | (x & 0b10);
+        ((attribute >> shift) & 0b11) * 0b0101_0101
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x5000..=0x5007 => self.pulses[(address >> 2) as usize & 1].write(address, value),
+            0x5010 => self.pcm_control = value & 0b1000_0001,
+            0x5011 if self.pcm_control.is_bit_clear(0) => self.play_pcm(value),
+            0x5015 => {
+                self.pulses[0].set_enabled(value.is_bit_set(0));
+                self.pulses[1].set_enabled(value.is_bit_set(1));
+            },
+            0x5100 => self.prg_mode = value & 0b11,
+            0x5101 => self.chr_mode = value & 0b11,
+            0x5102 | 0x5103 => self.prg_ram_protect[(address - 0x5102) as usize] = value & 0b11,
+            0x5104 => self.exram_mode = value & 0b11,
+            0x5105 => self.nametables = value,
+            0x5106 => self.fill_tile = value,
+            0x5107 => self.fill_attribute = (value & 0b11) * 0b0101_0101,
+            0x5113..=0x5117 => self.prg_banks[(address - 0x5113) as usize] = value,
+            0x5120..=0x512B => {
+                self.chr_banks[(address - 0x5120) as usize] = value as u16 | ((self.chr_upper as u16) << 8);
+                self.chr_background_last = address >= 0x5128;
+            },
+            0x5130 => self.chr_upper = value & 0b11,
+            0x5200 => self.split_control = value,
+            0x5201 => self.split_scroll = value,
+            0x5202 => self.split_bank = value,
+            0x5203 => self.irq_compare = value,
+            0x5204 => self.irq_enabled = value.is_bit_set(7),
+            0x5205 => self.multiplicand = value,
+            0x5206 => self.multiplier = value,
+            0x5C00..=0x5FFF => {
+                let index = (address & 0x03FF) as usize;
+
+                match self.exram_mode {
+                    0 | 1 => self.exram[index] = if self.in_frame { value } else { 0 },
+                    2 => self.exram[index] = value,
+                    _ => {},
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn read_register(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x5010 => {
+                let value = (self.pcm_irq as u8) << 7 | (self.pcm_control & 1);
+                self.pcm_irq = false;
+                Some(value)
+            },
+            0x5015 => Some(self.pulses[0].is_active() as u8 | (self.pulses[1].is_active() as u8) << 1),
+            0x5204 => {
+                let value = (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6;
+                self.irq_pending = false;
+                Some(value)
+            },
+            0x5205 => Some((self.multiplicand as u16 * self.multiplier as u16) as u8),
+            0x5206 => Some(((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8),
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => Some(self.exram[(address & 0x03FF) as usize]),
+            _ => None,
+        }
+    }
+
+    /// Outputs a PCM sample; zero is not played but raises the PCM IRQ instead.
+    fn play_pcm(&mut self, value: u8) {
+        if value == 0 {
+            self.pcm_irq = true;
+        } else {
+            self.pcm = value;
+        }
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x5000..=0x5FFF => self.read_register(address),
+            0x6000..=0x7FFF => self.prg_ram.read(self.prg_banks[0] as usize, PRG_BANK_LEN, address as usize),
+            0x8000..=0xFFFF => {
+                // The MMC5 takes fetching the NMI vector as the end of the frame
+                if address == 0xFFFA || address == 0xFFFB {
+                    self.leave_frame();
+                }
+
+                let (bank, rom) = self.prg_bank(address);
+                let value = if rom {
+                    self.prg_rom.read(bank, PRG_BANK_LEN, address as usize)
+                } else {
+                    self.prg_ram.read(bank, PRG_BANK_LEN, address as usize)
+                };
+
+                if address < 0xC000 && self.pcm_control.is_bit_set(0) {
+                    self.play_pcm(value.unwrap_or(0));
+                }
+
+                value
+            },
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        match address {
+            0x5000..=0x5FFF => self.write_register(address, value),
+            0x6000..=0x7FFF if self.prg_ram_writable() => {
+                self.prg_ram.write(self.prg_banks[0] as usize, PRG_BANK_LEN, address as usize, value)
+            },
+            0x8000..=0xFFFF if self.prg_ram_writable() => {
+                let (bank, rom) = self.prg_bank(address);
+
+                if !rom {
+                    self.prg_ram.write(bank, PRG_BANK_LEN, address as usize, value);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.observe_ppu_read(address);
+
+        if self.is_background_fetch() {
+            match self.tile {
+                TileSource::Split { y, .. } => {
+                    let offset = (address as usize & 0x0FF8) | (y as usize & 0b111);
+                    return self.chr.read(self.split_bank as usize, CHR_SPLIT_BANK_LEN, offset).unwrap_or(0);
+                },
+                TileSource::Extended(value) => {
+                    let bank = (value & 0b0011_1111) as usize | (self.chr_upper as usize) << 6;
+                    return self.chr.read(bank, CHR_SPLIT_BANK_LEN, address as usize).unwrap_or(0);
+                },
+                TileSource::Nametable => {},
+            }
+        }
+
+        let (bank, bank_len) = self.chr_bank(address, self.chr_background(self.is_sprite_fetch()));
+        self.chr.read(bank, bank_len, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        let (bank, bank_len) = self.chr_bank(address, self.chr_background_last);
+        self.chr.write(bank, bank_len, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        let mut pages = [0; 4];
+
+        for (nametable, page) in pages.iter_mut().enumerate() {
+            *page = (self.nametables >> (nametable * 2)) & 1;
+        }
+
+        Mirroring::Custom(pages)
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.bytes()
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        self.prg_ram.bytes_mut()
+    }
+
+    fn read_nametable(&mut self, address: u16) -> Option<u8> {
+        let attribute = address & 0x03FF >= ATTRIBUTES;
+
+        if !attribute {
+            self.fetches = self.fetches.saturating_add(1);
+        }
+
+        self.observe_ppu_read(address);
+
+        if !attribute {
+            self.tile = self.fetch_tile(address);
+        }
+
+        if self.is_background_fetch() {
+            match (self.tile, attribute) {
+                (TileSource::Split { index, .. }, false) => return Some(self.exram[index as usize]),
+                (TileSource::Split { index, .. }, true) => return Some(self.read_split_attribute(index)),
+                (TileSource::Extended(value), true) => return Some((value >> 6) * 0b0101_0101),
+                _ => {},
+            }
+        }
+
+        match (self.nametables >> (((address >> 10) & 0b11) * 2)) & 0b11 {
+            2 if self.exram_mode <= 1 => Some(self.exram[(address & 0x03FF) as usize]),
+            2 => Some(0),
+            3 if attribute => Some(self.fill_attribute),
+            3 => Some(self.fill_tile),
+            _ => None,
+        }
+    }
+
+    fn write_nametable(&mut self, address: u16, value: u8) -> bool {
+        match (self.nametables >> (((address >> 10) & 0b11) * 2)) & 0b11 {
+            2 => {
+                if self.exram_mode <= 1 {
+                    self.exram[(address & 0x03FF) as usize] = value;
+                }
+
+                true
+            },
+            3 => true,
+            _ => false,
+        }
+    }
+
+    fn observe_cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x2000..=0x3FFF if address & 0b111 == 0 => self.sprite_8x16 = value.is_bit_set(5),
+            0x2000..=0x3FFF if address & 0b111 == 1 && value & 0b0001_1000 == 0 => self.leave_frame(),
+            _ => {},
+        }
+    }
+
+    fn clock_cpu(&mut self) {
+        if self.ppu_idle > 0 {
+            self.ppu_idle -= 1;
+
+            if self.ppu_idle == 0 {
+                self.leave_frame();
+            }
+        }
+
+        self.audio_odd = !self.audio_odd;
+
+        if self.audio_odd {
+            self.pulses.iter_mut().for_each(Pulse::clock_timer);
+        }
+
+        self.audio_cycle += 1;
+
+        if self.audio_cycle == AUDIO_FRAME_CYCLES {
+            self.audio_cycle = 0;
+
+            for pulse in &mut self.pulses {
+                pulse.clock_quarter_frame();
+                pulse.clock_half_frame();
+            }
+        }
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_enabled && self.irq_pending) || (self.pcm_irq && self.pcm_control.is_bit_set(7))
+    }
+
+    fn cycles_until_irq(&self) -> Option<u32> {
+        // the PCM IRQ only comes from CPU reads
+        if self.irq_enabled { Some(0) } else { None }
+    }
+
+    fn audio_output(&self) -> f32 {
+        let pulse = apu::mix_pulse(self.pulses[0].output() + self.pulses[1].output());
+        pulse + self.pcm as f32 / 255.0 * PCM_LEVEL
+    }
+}
@@ -0,0 +1,128 @@
+mod axrom;
+mod cnrom;
+mod memory;
+mod mmc1;
+mod mmc2;
+mod mmc3;
+mod mmc4;
+mod mmc5;
+mod nrom;
+mod tests;
+mod uxrom;
+mod vrc4;
+mod vrc6;
+
+pub use self::axrom::Axrom;
+pub use self::cnrom::Cnrom;
+pub use self::memory::Memory;
+pub use self::mmc1::Mmc1;
+pub use self::mmc2::Mmc2;
+pub use self::mmc3::{Mmc3, Mmc3Revision};
+pub use self::mmc4::Mmc4;
+pub use self::mmc5::Mmc5;
+pub use self::nrom::Nrom;
+pub use self::uxrom::Uxrom;
+pub use self::vrc4::{Vrc4, VrcChip};
+pub use self::vrc6::Vrc6;
+
+use crate::cartridge::{Cartridge, Header, Mirroring};
+use crate::types::Result;
+
+/// Cartridge board logic, sitting between the PRG/CHR memory and the CPU and PPU buses.
+pub trait Mapper {
+    /// Reads from the CPU bus at $4020-$FFFF, `None` if the board does not drive the bus.
+    fn read_cpu(&mut self, address: u16) -> Option<u8>;
+    /// Writes to the CPU bus at $4020-$FFFF.
+    fn write_cpu(&mut self, address: u16, value: u8);
+    /// Reads from the pattern tables on the PPU bus at $0000-$1FFF.
+    ///
+    /// The PPU goes through here for every pattern fetch, so boards that react to the PPU
+    /// address bus (A12 counters, CHR latches) observe it from this call.
+    fn read_ppu(&mut self, address: u16) -> u8;
+    /// Writes to the pattern tables on the PPU bus at $0000-$1FFF.
+    fn write_ppu(&mut self, address: u16, value: u8);
+    fn mirroring(&self) -> Mirroring;
+    /// All of the board's PRG RAM, as saved to battery backup; empty if there is none.
+    fn prg_ram(&self) -> &[u8] {
+        &[]
+    }
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        &mut []
+    }
+    /// Reads from the nametables on the PPU bus at $2000-$2FFF, `None` to let the PPU serve
+    /// the fetch from CIRAM as arranged by `mirroring`.
+    ///
+    /// Like pattern fetches, every nametable and attribute fetch goes through here.
+    fn read_nametable(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+    /// Writes to the nametables on the PPU bus at $2000-$2FFF, `false` to let the PPU store
+    /// the value in CIRAM.
+    fn write_nametable(&mut self, _address: u16, _value: u8) -> bool {
+        false
+    }
+    /// Called for CPU writes outside $4020-$FFFF, for boards that snoop PPU or APU registers.
+    fn observe_cpu_write(&mut self, _address: u16, _value: u8) {}
+    /// Called once every CPU cycle, for boards that count cycles or time register writes.
+    fn clock_cpu(&mut self) {}
+    /// State of the board's IRQ output, wired to the CPU IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+    /// CPU cycles the board can be left to run behind before its IRQ output may be asserted,
+    /// `None` if it cannot be. Counters clocked by PPU fetches cannot tell ahead and have to
+    /// return 0, which keeps the board in step every cycle.
+    fn cycles_until_irq(&self) -> Option<u32> {
+        None
+    }
+    /// Output of the board's expansion audio, on the same scale as the APU mixer output.
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
+}
+
+pub fn from_cartridge(cartridge: Cartridge) -> Result<Box<dyn Mapper>> {
+    let bus_conflicts = has_bus_conflicts(cartridge.header());
+
+    let mapper: Box<dyn Mapper> = match cartridge.header().mapper() {
+        0 => Box::new(Nrom::new(cartridge)),
+        1 => Box::new(Mmc1::new(cartridge)),
+        2 => Box::new(Uxrom::new(cartridge, bus_conflicts)),
+        3 => Box::new(Cnrom::new(cartridge, bus_conflicts)),
+        4 => Box::new(Mmc3::new(cartridge)),
+        5 => Box::new(Mmc5::new(cartridge)),
+        7 => Box::new(Axrom::new(cartridge, bus_conflicts)),
+        9 => Box::new(Mmc2::new(cartridge)),
+        10 => Box::new(Mmc4::new(cartridge)),
+        21 | 22 | 23 | 25 => Box::new(Vrc4::new(cartridge)),
+        24 | 26 => Box::new(Vrc6::new(cartridge)),
+        mapper => return Err(anyhow!("unsupported mapper `{}`", mapper)),
+    };
+
+    Ok(mapper)
+}
+
+/// CHR ROM if the cartridge has any, otherwise the CHR RAM declared by the header.
+fn chr_memory(chr_rom: Vec<u8>, header: &Header) -> Memory {
+    if chr_rom.is_empty() {
+        Memory::ram(header.chr_ram_len() + header.chr_nvram_len())
+    } else {
+        Memory::rom(chr_rom)
+    }
+}
+
+/// Discrete logic boards use NES 2.0 submapper 2 to declare that writes conflict with the
+/// ROM output, anything else (including unspecified) is treated as conflict-free.
+fn has_bus_conflicts(header: &Header) -> bool {
+    header.submapper() == 2
+}
+
+/// On boards with bus conflicts the ROM drives the data bus during writes too, so the
+/// register only sees the bits that both the CPU and the ROM agree on.
+fn resolve_bus_conflict(mapper: &mut impl Mapper, bus_conflicts: bool, address: u16, value: u8) -> u8 {
+    if bus_conflicts {
+        value & mapper.read_cpu(address).unwrap_or(0xFF)
+    } else {
+        value
+    }
+}
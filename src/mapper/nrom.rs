@@ -0,0 +1,64 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_LEN: usize = 0x8000;
+const PRG_RAM_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x2000;
+
+/// Mapper 0: 16 KiB (mirrored) or 32 KiB of PRG ROM and 8 KiB of CHR without any banking.
+pub struct Nrom {
+    prg_rom: Memory,
+    prg_ram: Memory,
+    chr: Memory,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            prg_ram: Memory::ram(header.prg_ram_len() + header.prg_nvram_len()),
+            chr: super::chr_memory(chr_rom, &header),
+            mirroring: header.mirroring(),
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.prg_ram.read(0, PRG_RAM_BANK_LEN, address as usize),
+            0x8000..=0xFFFF => self.prg_rom.read(0, PRG_BANK_LEN, address as usize),
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        if let 0x6000..=0x7FFF = address {
+            self.prg_ram.write(0, PRG_RAM_BANK_LEN, address as usize, value);
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr.read(0, CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(0, CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.bytes()
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        self.prg_ram.bytes_mut()
+    }
+}
@@ -0,0 +1,1024 @@
+#![cfg(test)]
+
+use super::*;
+
+const HEADER_LEN: usize = 16;
+
+fn cartridge(mapper: u8, prg_rom_banks: u8, chr_rom_banks: u8) -> Cartridge {
+    cartridge_submapper(mapper, 0, prg_rom_banks, chr_rom_banks)
+}
+
+fn cartridge_submapper(mapper: u8, submapper: u8, prg_rom_banks: u8, chr_rom_banks: u8) -> Cartridge {
+    let mut bytes = b"NES\x1A".to_vec();
+    bytes.extend(&[prg_rom_banks, chr_rom_banks, mapper << 4, (mapper & 0xF0) | 0b0000_1000, submapper << 4]);
+    bytes.resize(HEADER_LEN, 0);
+    // 8 KiB of PRG RAM, and of CHR RAM when there is no CHR ROM
+    bytes[10] = 0x07;
+    bytes[11] = if chr_rom_banks == 0 { 0x07 } else { 0x00 };
+
+    // tag every byte of PRG with its 8 KiB bank number and every byte of CHR with its 1 KiB bank number
+    for i in 0..prg_rom_banks as usize * 0x4000 {
+        bytes.push((i / 0x2000) as u8);
+    }
+
+    for i in 0..chr_rom_banks as usize * 0x2000 {
+        bytes.push((i / 0x0400) as u8);
+    }
+
+    Cartridge::from_bytes(&bytes).unwrap()
+}
+
+#[test]
+fn from_cartridge_unsupported() {
+    assert!(from_cartridge(cartridge(0xFF, 1, 1)).is_err());
+}
+
+#[test]
+fn nrom_128() {
+    let mut mapper = from_cartridge(cartridge(0, 1, 1)).unwrap();
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+    assert_eq!(mapper.read_cpu(0xA000), Some(1));
+    assert_eq!(mapper.read_cpu(0xC000), Some(0));
+    assert_eq!(mapper.read_cpu(0xFFFF), Some(1));
+}
+
+#[test]
+fn nrom_256() {
+    let mut mapper = from_cartridge(cartridge(0, 2, 1)).unwrap();
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+    assert_eq!(mapper.read_cpu(0xC000), Some(2));
+    assert_eq!(mapper.read_cpu(0xFFFF), Some(3));
+}
+
+#[test]
+fn nrom_prg_rom_read_only() {
+    let mut mapper = from_cartridge(cartridge(0, 1, 1)).unwrap();
+    mapper.write_cpu(0x8000, 0xFF);
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+}
+
+#[test]
+fn nrom_prg_ram() {
+    let mut mapper = from_cartridge(cartridge(0, 1, 1)).unwrap();
+    mapper.write_cpu(0x6000, 0x12);
+    mapper.write_cpu(0x7FFF, 0x34);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+    assert_eq!(mapper.read_cpu(0x7FFF), Some(0x34));
+    assert_eq!(mapper.read_cpu(0x5000), None);
+}
+
+#[test]
+fn nrom_prg_ram_save_data() {
+    let mut mapper = from_cartridge(cartridge(0, 1, 1)).unwrap();
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.prg_ram().len(), 0x2000);
+    assert_eq!(mapper.prg_ram()[0], 0x12);
+
+    mapper.prg_ram_mut()[1] = 0x34;
+    assert_eq!(mapper.read_cpu(0x6001), Some(0x34));
+}
+
+#[test]
+fn nrom_chr_rom() {
+    let mut mapper = from_cartridge(cartridge(0, 1, 1)).unwrap();
+    mapper.write_ppu(0x0000, 0xFF);
+    assert_eq!(mapper.read_ppu(0x0000), 0);
+    assert_eq!(mapper.read_ppu(0x1FFF), 7);
+}
+
+#[test]
+fn nrom_chr_ram() {
+    let mut mapper = from_cartridge(cartridge(0, 1, 0)).unwrap();
+    mapper.write_ppu(0x1234, 0x56);
+    assert_eq!(mapper.read_ppu(0x1234), 0x56);
+}
+
+#[test]
+fn uxrom_prg_banks() {
+    let mut mapper = from_cartridge(cartridge(2, 8, 0)).unwrap();
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+    assert_eq!(mapper.read_cpu(0xFFFF), Some(15));
+
+    mapper.write_cpu(0x8000, 3);
+    assert_eq!(mapper.read_cpu(0x8000), Some(6));
+    assert_eq!(mapper.read_cpu(0xBFFF), Some(7));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+
+    mapper.write_cpu(0xFFFF, 9);
+    assert_eq!(mapper.read_cpu(0x8000), Some(2));
+}
+
+#[test]
+fn uxrom_chr_ram() {
+    let mut mapper = from_cartridge(cartridge(2, 8, 0)).unwrap();
+    mapper.write_ppu(0x1FFF, 0x12);
+    assert_eq!(mapper.read_ppu(0x1FFF), 0x12);
+}
+
+#[test]
+fn uxrom_bus_conflicts() {
+    let mut mapper = from_cartridge(cartridge_submapper(2, 2, 8, 0)).unwrap();
+
+    // the ROM outputs 14 at $C000, so only bit 1 of 3 survives
+    mapper.write_cpu(0xC000, 3);
+    assert_eq!(mapper.read_cpu(0x8000), Some(4));
+
+    let mut mapper = from_cartridge(cartridge_submapper(2, 1, 8, 0)).unwrap();
+    mapper.write_cpu(0xC000, 3);
+    assert_eq!(mapper.read_cpu(0x8000), Some(6));
+}
+
+#[test]
+fn cnrom_chr_banks() {
+    let mut mapper = from_cartridge(cartridge(3, 2, 4)).unwrap();
+    assert_eq!(mapper.read_ppu(0x0000), 0);
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+    assert_eq!(mapper.read_cpu(0xFFFF), Some(3));
+
+    mapper.write_cpu(0x8000, 2);
+    assert_eq!(mapper.read_ppu(0x0000), 16);
+    assert_eq!(mapper.read_ppu(0x1FFF), 23);
+
+    mapper.write_ppu(0x0000, 0xFF);
+    assert_eq!(mapper.read_ppu(0x0000), 16);
+}
+
+#[test]
+fn cnrom_bus_conflicts() {
+    let mut mapper = from_cartridge(cartridge_submapper(3, 2, 2, 4)).unwrap();
+
+    // the ROM outputs 1 at $A000
+    mapper.write_cpu(0xA000, 3);
+    assert_eq!(mapper.read_ppu(0x0000), 8);
+}
+
+#[test]
+fn axrom_prg_banks() {
+    let mut mapper = from_cartridge(cartridge(7, 16, 0)).unwrap();
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+    assert_eq!(mapper.read_cpu(0xFFFF), Some(3));
+
+    mapper.write_cpu(0x8000, 5);
+    assert_eq!(mapper.read_cpu(0x8000), Some(20));
+    assert_eq!(mapper.read_cpu(0xFFFF), Some(23));
+}
+
+#[test]
+fn axrom_mirroring() {
+    let mut mapper = from_cartridge(cartridge(7, 16, 0)).unwrap();
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+
+    mapper.write_cpu(0x8000, 0b0001_0000);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+
+    mapper.write_cpu(0x8000, 0b0000_0000);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+}
+
+#[test]
+fn axrom_bus_conflicts() {
+    let mut mapper = from_cartridge(cartridge_submapper(7, 2, 16, 0)).unwrap();
+
+    // the ROM outputs 3 at $E000
+    mapper.write_cpu(0xE000, 0b0001_0110);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+    assert_eq!(mapper.read_cpu(0x8000), Some(8));
+}
+
+fn mmc1_write(mapper: &mut Box<dyn Mapper>, address: u16, value: u8) {
+    for i in 0..5 {
+        mapper.write_cpu(address, (value >> i) & 1);
+        mapper.clock_cpu();
+        mapper.clock_cpu();
+    }
+}
+
+#[test]
+fn mmc1_power_on_prg_mode() {
+    let mut mapper = from_cartridge(cartridge(1, 8, 2)).unwrap();
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+    assert_eq!(mapper.read_cpu(0xFFFF), Some(15));
+}
+
+#[test]
+fn mmc1_shift_register() {
+    let mut mapper = from_cartridge(cartridge(1, 8, 2)).unwrap();
+
+    mmc1_write(&mut mapper, 0xE000, 3);
+    assert_eq!(mapper.read_cpu(0x8000), Some(6));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+
+    // incomplete writes have no effect until the fifth bit
+    for _ in 0..4 {
+        mapper.write_cpu(0xE000, 0);
+        mapper.clock_cpu();
+        mapper.clock_cpu();
+        assert_eq!(mapper.read_cpu(0x8000), Some(6));
+    }
+}
+
+#[test]
+fn mmc1_reset() {
+    let mut mapper = from_cartridge(cartridge(1, 8, 2)).unwrap();
+    mmc1_write(&mut mapper, 0x8000, 0b0_10_00);
+    assert_eq!(mapper.read_cpu(0xC000), Some(0));
+
+    mapper.write_cpu(0xE000, 1);
+    mapper.clock_cpu();
+    mapper.clock_cpu();
+    mapper.write_cpu(0x8000, 0x80);
+    mapper.clock_cpu();
+    mapper.clock_cpu();
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+
+    // the partially shifted bit is discarded
+    mmc1_write(&mut mapper, 0xE000, 2);
+    assert_eq!(mapper.read_cpu(0x8000), Some(4));
+}
+
+#[test]
+fn mmc1_consecutive_writes_ignored() {
+    let mut mapper = from_cartridge(cartridge(1, 8, 2)).unwrap();
+
+    mapper.write_cpu(0xE000, 1);
+    mapper.clock_cpu();
+    mapper.write_cpu(0xE000, 0);
+    for _ in 0..4 {
+        mapper.clock_cpu();
+        mapper.clock_cpu();
+        mapper.write_cpu(0xE000, 0);
+    }
+
+    assert_eq!(mapper.read_cpu(0x8000), Some(2));
+}
+
+#[test]
+fn mmc1_prg_modes() {
+    let mut mapper = from_cartridge(cartridge(1, 8, 2)).unwrap();
+    mmc1_write(&mut mapper, 0xE000, 3);
+
+    mmc1_write(&mut mapper, 0x8000, 0b0_00_00);
+    assert_eq!(mapper.read_cpu(0x8000), Some(4));
+    assert_eq!(mapper.read_cpu(0xC000), Some(6));
+
+    mmc1_write(&mut mapper, 0x8000, 0b0_10_00);
+    assert_eq!(mapper.read_cpu(0x8000), Some(0));
+    assert_eq!(mapper.read_cpu(0xC000), Some(6));
+
+    mmc1_write(&mut mapper, 0x8000, 0b0_11_00);
+    assert_eq!(mapper.read_cpu(0x8000), Some(6));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+}
+
+#[test]
+fn mmc1_chr_modes() {
+    let mut mapper = from_cartridge(cartridge(1, 2, 4)).unwrap();
+    mmc1_write(&mut mapper, 0xA000, 3);
+    mmc1_write(&mut mapper, 0xC000, 5);
+
+    assert_eq!(mapper.read_ppu(0x0000), 8);
+    assert_eq!(mapper.read_ppu(0x1000), 12);
+
+    mmc1_write(&mut mapper, 0x8000, 0b1_11_00);
+    assert_eq!(mapper.read_ppu(0x0000), 12);
+    assert_eq!(mapper.read_ppu(0x1000), 20);
+}
+
+#[test]
+fn mmc1_mirroring() {
+    let mut mapper = from_cartridge(cartridge(1, 2, 4)).unwrap();
+
+    mmc1_write(&mut mapper, 0x8000, 0b0_11_00);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenLower);
+    mmc1_write(&mut mapper, 0x8000, 0b0_11_01);
+    assert_eq!(mapper.mirroring(), Mirroring::SingleScreenUpper);
+    mmc1_write(&mut mapper, 0x8000, 0b0_11_10);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+    mmc1_write(&mut mapper, 0x8000, 0b0_11_11);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn mmc1_prg_ram_enable() {
+    let mut mapper = from_cartridge(cartridge(1, 8, 2)).unwrap();
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+
+    mmc1_write(&mut mapper, 0xE000, 0b1_0000);
+    assert_eq!(mapper.read_cpu(0x6000), None);
+    mapper.write_cpu(0x6000, 0x34);
+
+    mmc1_write(&mut mapper, 0xE000, 0b0_0000);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+}
+
+#[test]
+fn mmc1_snrom_prg_ram_disable() {
+    let mut mapper = from_cartridge(cartridge(1, 8, 0)).unwrap();
+    mapper.write_cpu(0x6000, 0x12);
+
+    mmc1_write(&mut mapper, 0xA000, 0b1_0000);
+    assert_eq!(mapper.read_cpu(0x6000), None);
+
+    mmc1_write(&mut mapper, 0xA000, 0b0_0000);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+}
+
+#[test]
+fn mmc1_sxrom() {
+    // 512 KiB PRG ROM, 32 KiB PRG RAM, 8 KiB CHR RAM
+    let mut bytes = b"NES\x1A".to_vec();
+    bytes.extend(&[32, 0, 0x10, 0b0000_1000, 0, 0, 0x09, 0x07]);
+    bytes.resize(HEADER_LEN, 0);
+
+    for i in 0..32 * 0x4000 {
+        bytes.push((i / 0x2000) as u8);
+    }
+
+    let mut mapper = from_cartridge(Cartridge::from_bytes(&bytes).unwrap()).unwrap();
+
+    // PRG RAM banks through bits 2-3
+    mapper.write_cpu(0x6000, 0x12);
+    mmc1_write(&mut mapper, 0xA000, 0b0_1100);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0));
+    mapper.write_cpu(0x6000, 0x34);
+    mmc1_write(&mut mapper, 0xA000, 0b0_0000);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+
+    // 256 KiB PRG ROM halves through bit 4, including the fixed bank
+    assert_eq!(mapper.read_cpu(0xC000), Some(30));
+    mmc1_write(&mut mapper, 0xA000, 0b1_0000);
+    assert_eq!(mapper.read_cpu(0x8000), Some(32));
+    assert_eq!(mapper.read_cpu(0xC000), Some(62));
+}
+
+/// Fetches a background tile followed by a sprite tile after a long enough A12 low period.
+fn mmc3_scanline(mapper: &mut Box<dyn Mapper>) {
+    mapper.read_ppu(0x0000);
+    for _ in 0..4 {
+        mapper.clock_cpu();
+    }
+    mapper.read_ppu(0x1000);
+}
+
+#[test]
+fn mmc3_prg_banks() {
+    let mut mapper = from_cartridge(cartridge(4, 8, 8)).unwrap();
+    mapper.write_cpu(0x8000, 6);
+    mapper.write_cpu(0x8001, 3);
+    mapper.write_cpu(0x8000, 7);
+    mapper.write_cpu(0x8001, 5);
+
+    assert_eq!(mapper.read_cpu(0x8000), Some(3));
+    assert_eq!(mapper.read_cpu(0xA000), Some(5));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+    assert_eq!(mapper.read_cpu(0xE000), Some(15));
+
+    mapper.write_cpu(0x8000, 0b0100_0000);
+    assert_eq!(mapper.read_cpu(0x8000), Some(14));
+    assert_eq!(mapper.read_cpu(0xA000), Some(5));
+    assert_eq!(mapper.read_cpu(0xC000), Some(3));
+    assert_eq!(mapper.read_cpu(0xE000), Some(15));
+}
+
+#[test]
+fn mmc3_chr_banks() {
+    let mut mapper = from_cartridge(cartridge(4, 8, 8)).unwrap();
+
+    for (register, bank) in [9, 12, 20, 21, 22, 23].iter().enumerate() {
+        mapper.write_cpu(0x8000, register as u8);
+        mapper.write_cpu(0x8001, *bank);
+    }
+
+    let banks = [8, 9, 12, 13, 20, 21, 22, 23];
+    for (i, &bank) in banks.iter().enumerate() {
+        assert_eq!(mapper.read_ppu(i as u16 * 0x0400), bank);
+    }
+
+    mapper.write_cpu(0x8000, 0b1000_0000);
+    for (i, &bank) in banks.iter().enumerate() {
+        assert_eq!(mapper.read_ppu((i as u16 * 0x0400) ^ 0x1000), bank);
+    }
+}
+
+#[test]
+fn mmc3_mirroring() {
+    let mut mapper = from_cartridge(cartridge(4, 8, 8)).unwrap();
+    mapper.write_cpu(0xA000, 0);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+    mapper.write_cpu(0xA000, 1);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn mmc3_prg_ram_protect() {
+    let mut mapper = from_cartridge(cartridge(4, 8, 8)).unwrap();
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+
+    mapper.write_cpu(0xA001, 0b1100_0000);
+    mapper.write_cpu(0x6000, 0x34);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+
+    mapper.write_cpu(0xA001, 0b0000_0000);
+    assert_eq!(mapper.read_cpu(0x6000), None);
+}
+
+#[test]
+fn mmc3_irq() {
+    let mut mapper = from_cartridge(cartridge(4, 8, 8)).unwrap();
+    mapper.write_cpu(0xC000, 2);
+    mapper.write_cpu(0xC001, 0);
+    mapper.write_cpu(0xE001, 0);
+
+    // reload to 2, then 1, then 0
+    mmc3_scanline(&mut mapper);
+    mmc3_scanline(&mut mapper);
+    assert!(!mapper.irq());
+    mmc3_scanline(&mut mapper);
+    assert!(mapper.irq());
+
+    mapper.write_cpu(0xE000, 0);
+    assert!(!mapper.irq());
+}
+
+#[test]
+fn mmc3_irq_a12_filter() {
+    let mut mapper = from_cartridge(cartridge(4, 8, 8)).unwrap();
+    mapper.write_cpu(0xC000, 0);
+    mapper.write_cpu(0xC001, 0);
+    mapper.write_cpu(0xE001, 0);
+
+    // sprite fetches only leave A12 low for a few PPU dots
+    mapper.read_ppu(0x1000);
+    mapper.read_ppu(0x0000);
+    mapper.clock_cpu();
+    mapper.read_ppu(0x1000);
+    assert!(!mapper.irq());
+}
+
+#[test]
+fn mmc3_irq_revisions() {
+    // with a latch of 0, revision B raises an IRQ on every scanline and revision A only once
+    for &(submapper, irq_expected) in &[(0, true), (4, false)] {
+        let mut mapper = from_cartridge(cartridge_submapper(4, submapper, 8, 8)).unwrap();
+        mapper.write_cpu(0xC000, 0);
+        mapper.write_cpu(0xC001, 0);
+        mapper.write_cpu(0xE001, 0);
+
+        mmc3_scanline(&mut mapper);
+        assert!(mapper.irq());
+
+        mapper.write_cpu(0xE000, 0);
+        mapper.write_cpu(0xE001, 0);
+        mmc3_scanline(&mut mapper);
+        assert_eq!(mapper.irq(), irq_expected);
+    }
+}
+
+fn mmc2_chr_banks(mapper: &mut Box<dyn Mapper>) {
+    mapper.write_cpu(0xB000, 1);
+    mapper.write_cpu(0xC000, 2);
+    mapper.write_cpu(0xD000, 3);
+    mapper.write_cpu(0xE000, 4);
+}
+
+#[test]
+fn mmc2_prg_banks() {
+    let mut mapper = from_cartridge(cartridge(9, 8, 16)).unwrap();
+    mapper.write_cpu(0xA000, 5);
+    assert_eq!(mapper.read_cpu(0x8000), Some(5));
+    assert_eq!(mapper.read_cpu(0xA000), Some(13));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+    assert_eq!(mapper.read_cpu(0xE000), Some(15));
+}
+
+#[test]
+fn mmc2_prg_small() {
+    let mut mapper = from_cartridge(cartridge(9, 1, 16)).unwrap();
+    assert_eq!(mapper.read_cpu(0xA000), Some(1));
+    assert_eq!(mapper.read_cpu(0xC000), Some(0));
+    assert_eq!(mapper.read_cpu(0xE000), Some(1));
+}
+
+#[test]
+fn mmc2_latches() {
+    let mut mapper = from_cartridge(cartridge(9, 8, 16)).unwrap();
+    mmc2_chr_banks(&mut mapper);
+    assert_eq!(mapper.read_ppu(0x0000), 8);
+    assert_eq!(mapper.read_ppu(0x1000), 16);
+
+    // the triggering fetch itself still comes from the old bank
+    assert_eq!(mapper.read_ppu(0x0FD8), 11);
+    assert_eq!(mapper.read_ppu(0x0000), 4);
+    assert_eq!(mapper.read_ppu(0x1000), 16);
+
+    assert_eq!(mapper.read_ppu(0x1FDF), 19);
+    assert_eq!(mapper.read_ppu(0x1000), 12);
+
+    mapper.read_ppu(0x0FE8);
+    mapper.read_ppu(0x1FE8);
+    assert_eq!(mapper.read_ppu(0x0000), 8);
+    assert_eq!(mapper.read_ppu(0x1000), 16);
+}
+
+#[test]
+fn mmc2_latch_lower_exact() {
+    let mut mapper = from_cartridge(cartridge(9, 8, 16)).unwrap();
+    mmc2_chr_banks(&mut mapper);
+
+    mapper.read_ppu(0x0FD9);
+    mapper.read_ppu(0x0FD0);
+    assert_eq!(mapper.read_ppu(0x0000), 8);
+}
+
+#[test]
+fn mmc2_mirroring() {
+    let mut mapper = from_cartridge(cartridge(9, 8, 16)).unwrap();
+    mapper.write_cpu(0xF000, 1);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    mapper.write_cpu(0xF000, 0);
+    assert_eq!(mapper.mirroring(), Mirroring::Vertical);
+}
+
+#[test]
+fn mmc4_prg_banks() {
+    let mut mapper = from_cartridge(cartridge(10, 8, 16)).unwrap();
+    mapper.write_cpu(0xA000, 2);
+    assert_eq!(mapper.read_cpu(0x8000), Some(4));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+}
+
+#[test]
+fn mmc4_latch_lower_ranged() {
+    let mut mapper = from_cartridge(cartridge(10, 8, 16)).unwrap();
+    mmc2_chr_banks(&mut mapper);
+
+    mapper.read_ppu(0x0FDF);
+    assert_eq!(mapper.read_ppu(0x0000), 4);
+    mapper.read_ppu(0x0FE9);
+    assert_eq!(mapper.read_ppu(0x0000), 8);
+}
+
+/// Fetches the nametable entry, attribute and low pattern byte of background tile `x` on the
+/// first nametable row.
+fn mmc5_tile(mapper: &mut Box<dyn Mapper>, x: u16) -> [u8; 3] {
+    let name = mapper.read_nametable(0x2000 + x % 32).unwrap_or(0);
+    let attribute = mapper.read_nametable(0x23C0 + (x % 32) / 4).unwrap_or(0);
+    let pattern = mapper.read_ppu((name as u16) << 4);
+    mapper.read_ppu(((name as u16) << 4) | 8);
+    [name, attribute, pattern]
+}
+
+/// Replays the fetches the PPU makes over dots 1-340 of a rendered scanline, returning what
+/// was fetched for background tiles 2-33 and the low pattern bytes of the eight sprites.
+fn mmc5_scanline(mapper: &mut Box<dyn Mapper>) -> (Vec<[u8; 3]>, Vec<u8>) {
+    let background = (2..34).map(|x| mmc5_tile(mapper, x)).collect();
+
+    let sprites = (0..8)
+        .map(|_| {
+            mapper.read_nametable(0x2000);
+            mapper.read_nametable(0x2000);
+            let pattern = mapper.read_ppu(0x1000);
+            mapper.read_ppu(0x1008);
+            pattern
+        })
+        .collect();
+
+    mmc5_tile(mapper, 0);
+    mmc5_tile(mapper, 1);
+    mapper.read_nametable(0x2002);
+    mapper.read_nametable(0x2002);
+
+    (background, sprites)
+}
+
+#[test]
+fn mmc5_prg_modes() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    // mode 3 with $5117 = $FF at power on
+    assert_eq!(mapper.read_cpu(0xE000), Some(7));
+    mapper.write_cpu(0x5114, 0x81);
+    mapper.write_cpu(0x5115, 0x82);
+    mapper.write_cpu(0x5116, 0x83);
+    assert_eq!(mapper.read_cpu(0x8000), Some(1));
+    assert_eq!(mapper.read_cpu(0xA000), Some(2));
+    assert_eq!(mapper.read_cpu(0xC000), Some(3));
+
+    mapper.write_cpu(0x5100, 0);
+    mapper.write_cpu(0x5117, 0x85);
+    assert_eq!(mapper.read_cpu(0x8000), Some(4));
+    assert_eq!(mapper.read_cpu(0xE000), Some(7));
+
+    mapper.write_cpu(0x5100, 1);
+    mapper.write_cpu(0x5115, 0x83);
+    mapper.write_cpu(0x5117, 0x86);
+    assert_eq!(mapper.read_cpu(0x8000), Some(2));
+    assert_eq!(mapper.read_cpu(0xA000), Some(3));
+    assert_eq!(mapper.read_cpu(0xC000), Some(6));
+    assert_eq!(mapper.read_cpu(0xE000), Some(7));
+
+    mapper.write_cpu(0x5100, 2);
+    mapper.write_cpu(0x5116, 0x85);
+    mapper.write_cpu(0x5117, 0x81);
+    assert_eq!(mapper.read_cpu(0xA000), Some(3));
+    assert_eq!(mapper.read_cpu(0xC000), Some(5));
+    assert_eq!(mapper.read_cpu(0xE000), Some(1));
+}
+
+#[test]
+fn mmc5_prg_ram() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0));
+
+    mapper.write_cpu(0x5102, 0b10);
+    mapper.write_cpu(0x5103, 0b01);
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+
+    // bit 7 clear maps PRG RAM into $8000-$DFFF
+    mapper.write_cpu(0x5114, 0x00);
+    assert_eq!(mapper.read_cpu(0x8000), Some(0x12));
+    mapper.write_cpu(0x8001, 0x34);
+    assert_eq!(mapper.read_cpu(0x6001), Some(0x34));
+}
+
+#[test]
+fn mmc5_chr_modes() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 8)).unwrap();
+    for i in 0..8 {
+        mapper.write_cpu(0x5120 + i, 10 + i as u8);
+    }
+    for i in 0..8 {
+        assert_eq!(mapper.read_ppu(i * 0x0400), 10 + i as u8);
+    }
+
+    // the background set is used when written last, repeated over both pattern tables
+    mapper.write_cpu(0x5129, 20);
+    assert_eq!(mapper.read_ppu(0x0400), 20);
+    assert_eq!(mapper.read_ppu(0x1400), 20);
+
+    mapper.write_cpu(0x5101, 0);
+    mapper.write_cpu(0x5127, 1);
+    assert_eq!(mapper.read_ppu(0x0400), 9);
+
+    mapper.write_cpu(0x5101, 1);
+    mapper.write_cpu(0x5123, 2);
+    mapper.write_cpu(0x5127, 3);
+    assert_eq!(mapper.read_ppu(0x0000), 8);
+    assert_eq!(mapper.read_ppu(0x1000), 12);
+
+    mapper.write_cpu(0x5101, 2);
+    mapper.write_cpu(0x5125, 5);
+    assert_eq!(mapper.read_ppu(0x1000), 10);
+}
+
+#[test]
+fn mmc5_chr_sets_8x16() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 8)).unwrap();
+    mapper.write_cpu(0x5124, 4);
+    mapper.write_cpu(0x5128, 30);
+    mapper.observe_cpu_write(0x2000, 0b0010_0000);
+
+    mmc5_scanline(&mut mapper);
+    let (background, sprites) = mmc5_scanline(&mut mapper);
+    assert!(background.iter().all(|tile| tile[2] == 30));
+    assert!(sprites.iter().all(|&pattern| pattern == 4));
+}
+
+#[test]
+fn mmc5_nametables() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    mapper.write_cpu(0x5105, 0b1110_0100);
+    mapper.write_cpu(0x5106, 0x42);
+    mapper.write_cpu(0x5107, 0b10);
+    assert_eq!(mapper.mirroring(), Mirroring::Custom([0, 1, 0, 1]));
+    assert_eq!(mapper.read_nametable(0x2000), None);
+    assert_eq!(mapper.read_nametable(0x2400), None);
+    assert!(!mapper.write_nametable(0x2400, 0x12));
+
+    assert!(mapper.write_nametable(0x2805, 0x12));
+    assert_eq!(mapper.read_nametable(0x2805), Some(0x12));
+
+    assert_eq!(mapper.read_nametable(0x2C00), Some(0x42));
+    assert_eq!(mapper.read_nametable(0x2FC0), Some(0b1010_1010));
+}
+
+#[test]
+fn mmc5_exram_modes() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    mapper.write_cpu(0x5104, 2);
+    mapper.write_cpu(0x5C00, 0x12);
+    assert_eq!(mapper.read_cpu(0x5C00), Some(0x12));
+
+    mapper.write_cpu(0x5104, 3);
+    mapper.write_cpu(0x5C00, 0x34);
+    assert_eq!(mapper.read_cpu(0x5C00), Some(0x12));
+
+    // not readable as a nametable mode, and written as zero outside rendering
+    mapper.write_cpu(0x5104, 0);
+    assert_eq!(mapper.read_cpu(0x5C00), None);
+    mapper.write_cpu(0x5C00, 0x56);
+    mapper.write_cpu(0x5104, 2);
+    assert_eq!(mapper.read_cpu(0x5C00), Some(0));
+}
+
+#[test]
+fn mmc5_extended_attributes() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 8)).unwrap();
+    mapper.write_cpu(0x5104, 2);
+    mapper.write_cpu(0x5C02, 0b1100_0101);
+    mapper.write_cpu(0x5104, 1);
+
+    mmc5_scanline(&mut mapper);
+    let (background, _) = mmc5_scanline(&mut mapper);
+    assert_eq!(background[0], [0, 0xFF, 20]);
+    assert_eq!(background[1], [0, 0x00, 0]);
+}
+
+#[test]
+fn mmc5_vertical_split() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 8)).unwrap();
+    mapper.write_cpu(0x5104, 2);
+    mapper.write_cpu(0x5C00 + 8 * 32 + 2, 0x42);
+    mapper.write_cpu(0x5C00 + 0x3C0 + 16, 0b0000_1000);
+    mapper.write_cpu(0x5104, 0);
+    mapper.write_cpu(0x5200, 0b1000_0100);
+    mapper.write_cpu(0x5201, 63);
+    mapper.write_cpu(0x5202, 2);
+
+    mmc5_scanline(&mut mapper);
+    mmc5_scanline(&mut mapper);
+    // the second scanline shows split row 64
+    let (background, _) = mmc5_scanline(&mut mapper);
+    assert_eq!(background[0], [0x42, 0b1010_1010, 9]);
+    assert_eq!(background[1], [0, 0b1010_1010, 8]);
+    assert_eq!(background[2], [0, 0, 0]);
+}
+
+#[test]
+fn mmc5_scanline_irq() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    mapper.write_cpu(0x5203, 2);
+    mapper.write_cpu(0x5204, 0x80);
+
+    for _ in 0..3 {
+        mmc5_scanline(&mut mapper);
+        assert!(!mapper.irq());
+    }
+
+    mmc5_scanline(&mut mapper);
+    assert!(mapper.irq());
+    assert_eq!(mapper.read_cpu(0x5204), Some(0b1100_0000));
+    assert!(!mapper.irq());
+}
+
+#[test]
+fn mmc5_frame_end() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    mmc5_scanline(&mut mapper);
+    mmc5_scanline(&mut mapper);
+    assert_eq!(mapper.read_cpu(0x5204), Some(0b0100_0000));
+    mapper.read_cpu(0xFFFA);
+    assert_eq!(mapper.read_cpu(0x5204), Some(0));
+
+    mmc5_scanline(&mut mapper);
+    mmc5_scanline(&mut mapper);
+    for _ in 0..3 {
+        mapper.clock_cpu();
+    }
+    assert_eq!(mapper.read_cpu(0x5204), Some(0));
+}
+
+#[test]
+fn mmc5_multiplier() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    mapper.write_cpu(0x5205, 200);
+    mapper.write_cpu(0x5206, 100);
+    assert_eq!(mapper.read_cpu(0x5205), Some(0x20));
+    assert_eq!(mapper.read_cpu(0x5206), Some(0x4E));
+}
+
+#[test]
+fn mmc5_audio() {
+    let mut mapper = from_cartridge(cartridge(5, 4, 1)).unwrap();
+    mapper.write_cpu(0x5015, 0b01);
+    mapper.write_cpu(0x5000, 0b1011_1111);
+    mapper.write_cpu(0x5002, 0x10);
+    mapper.write_cpu(0x5003, 0x08);
+    assert_eq!(mapper.read_cpu(0x5015), Some(0b01));
+
+    let mut levels = Vec::new();
+    for _ in 0..200 {
+        mapper.clock_cpu();
+        levels.push(mapper.audio_output());
+    }
+    assert!(levels.contains(&0.0));
+    assert!(levels.iter().any(|&level| level > 0.0));
+
+    mapper.write_cpu(0x5015, 0);
+    mapper.write_cpu(0x5011, 0xFF);
+    assert_eq!(mapper.audio_output(), 0.5);
+
+    // writing a zero sample raises the PCM IRQ instead of playing it
+    mapper.write_cpu(0x5010, 0x80);
+    mapper.write_cpu(0x5011, 0);
+    assert!(mapper.irq());
+    assert_eq!(mapper.read_cpu(0x5010), Some(0x80));
+    assert!(!mapper.irq());
+}
+
+#[test]
+fn vrc4_address_lines() {
+    let boards = [
+        (21, 1, 0x02, 0x04),
+        (21, 2, 0x40, 0x80),
+        (21, 0, 0x02, 0x04),
+        (21, 0, 0x40, 0x80),
+        (23, 1, 0x01, 0x02),
+        (23, 2, 0x04, 0x08),
+        (23, 3, 0x01, 0x02),
+        (23, 0, 0x01, 0x02),
+        (23, 0, 0x04, 0x08),
+        (25, 1, 0x02, 0x01),
+        (25, 2, 0x08, 0x04),
+        (25, 3, 0x02, 0x01),
+        (25, 0, 0x02, 0x01),
+        (25, 0, 0x08, 0x04),
+    ];
+
+    for (number, submapper, a0, a1) in boards {
+        let mut mapper = from_cartridge(cartridge_submapper(number, submapper, 8, 4)).unwrap();
+        mapper.write_cpu(0xB000, 5);
+        mapper.write_cpu(0xB000 | a0, 1);
+        mapper.write_cpu(0xB000 | a1, 3);
+        assert_eq!(mapper.read_ppu(0x0000), 21, "mapper {} submapper {}", number, submapper);
+        assert_eq!(mapper.read_ppu(0x0400), 3, "mapper {} submapper {}", number, submapper);
+    }
+}
+
+#[test]
+fn vrc2_chips() {
+    let mapper = Vrc4::new(cartridge_submapper(23, 3, 8, 4));
+    assert_eq!(mapper.chip(), VrcChip::Vrc2);
+    let mapper = Vrc4::new(cartridge_submapper(23, 0, 8, 4));
+    assert_eq!(mapper.chip(), VrcChip::Vrc4);
+
+    // VRC2a ignores the low bit of the CHR banks
+    let mut mapper = from_cartridge(cartridge(22, 8, 4)).unwrap();
+    mapper.write_cpu(0xB000, 6);
+    assert_eq!(mapper.read_ppu(0x0000), 3);
+}
+
+#[test]
+fn vrc4_prg_banks() {
+    let mut mapper = from_cartridge(cartridge_submapper(21, 1, 8, 4)).unwrap();
+    mapper.write_cpu(0x8000, 3);
+    mapper.write_cpu(0xA000, 5);
+    assert_eq!(mapper.read_cpu(0x8000), Some(3));
+    assert_eq!(mapper.read_cpu(0xA000), Some(5));
+    assert_eq!(mapper.read_cpu(0xC000), Some(14));
+    assert_eq!(mapper.read_cpu(0xE000), Some(15));
+
+    mapper.write_cpu(0x9004, 0b10);
+    assert_eq!(mapper.read_cpu(0x8000), Some(14));
+    assert_eq!(mapper.read_cpu(0xC000), Some(3));
+
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+}
+
+#[test]
+fn vrc4_mirroring() {
+    let mut mapper = from_cartridge(cartridge_submapper(21, 1, 8, 4)).unwrap();
+    let modes = [
+        Mirroring::Vertical,
+        Mirroring::Horizontal,
+        Mirroring::SingleScreenLower,
+        Mirroring::SingleScreenUpper,
+    ];
+
+    for (value, &mirroring) in modes.iter().enumerate() {
+        mapper.write_cpu(0x9000, value as u8);
+        assert_eq!(mapper.mirroring(), mirroring);
+    }
+}
+
+#[test]
+fn vrc4_irq_cycle_mode() {
+    let mut mapper = from_cartridge(cartridge_submapper(21, 1, 8, 4)).unwrap();
+    mapper.write_cpu(0xF000, 0x0E);
+    mapper.write_cpu(0xF002, 0x0F);
+    mapper.write_cpu(0xF004, 0b110);
+    assert_eq!(mapper.cycles_until_irq(), Some(2));
+
+    mapper.clock_cpu();
+    assert!(!mapper.irq());
+    mapper.clock_cpu();
+    assert!(mapper.irq());
+
+    // acknowledging without the enable-after-acknowledge bit stops the counter
+    mapper.write_cpu(0xF006, 0);
+    assert!(!mapper.irq());
+    assert_eq!(mapper.cycles_until_irq(), None);
+    for _ in 0..0x200 {
+        mapper.clock_cpu();
+    }
+    assert!(!mapper.irq());
+}
+
+#[test]
+fn vrc4_irq_scanline_mode() {
+    let mut mapper = from_cartridge(cartridge_submapper(21, 1, 8, 4)).unwrap();
+    mapper.write_cpu(0xF000, 0x0F);
+    mapper.write_cpu(0xF002, 0x0F);
+    mapper.write_cpu(0xF004, 0b011);
+    assert_eq!(mapper.cycles_until_irq(), Some(114));
+
+    // one scanline is 341 PPU dots, 113 2/3 CPU cycles
+    for _ in 0..113 {
+        mapper.clock_cpu();
+    }
+    assert!(!mapper.irq());
+    mapper.clock_cpu();
+    assert!(mapper.irq());
+
+    mapper.write_cpu(0xF006, 0);
+    for _ in 0..114 {
+        mapper.clock_cpu();
+    }
+    assert!(mapper.irq());
+}
+
+#[test]
+fn vrc6_banks() {
+    let mut mapper = from_cartridge(cartridge(24, 8, 4)).unwrap();
+    mapper.write_cpu(0x8000, 2);
+    mapper.write_cpu(0xC000, 7);
+    assert_eq!(mapper.read_cpu(0x8000), Some(4));
+    assert_eq!(mapper.read_cpu(0xA000), Some(5));
+    assert_eq!(mapper.read_cpu(0xC000), Some(7));
+    assert_eq!(mapper.read_cpu(0xE000), Some(15));
+
+    for i in 0..4 {
+        mapper.write_cpu(0xD000 + i, 3 + i as u8);
+        mapper.write_cpu(0xE000 + i, 7 + i as u8);
+    }
+    for i in 0..8 {
+        assert_eq!(mapper.read_ppu(i * 0x0400), 3 + i as u8);
+    }
+
+    // mapper 26 swaps A0 and A1
+    let mut mapper = from_cartridge(cartridge(26, 8, 4)).unwrap();
+    mapper.write_cpu(0xD001, 9);
+    assert_eq!(mapper.read_ppu(0x0800), 9);
+}
+
+#[test]
+fn vrc6_banking_control() {
+    let mut mapper = from_cartridge(cartridge(24, 8, 4)).unwrap();
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), None);
+
+    mapper.write_cpu(0xB003, 0b1000_0100);
+    assert_eq!(mapper.mirroring(), Mirroring::Horizontal);
+    mapper.write_cpu(0x6000, 0x12);
+    assert_eq!(mapper.read_cpu(0x6000), Some(0x12));
+}
+
+#[test]
+fn vrc6_irq() {
+    let mut mapper = from_cartridge(cartridge(24, 8, 4)).unwrap();
+    mapper.write_cpu(0xF000, 0xFF);
+    mapper.write_cpu(0xF001, 0b111);
+    mapper.clock_cpu();
+    assert!(mapper.irq());
+
+    // enable-after-acknowledge keeps it counting
+    mapper.write_cpu(0xF002, 0);
+    assert!(!mapper.irq());
+    mapper.clock_cpu();
+    assert!(mapper.irq());
+}
+
+#[test]
+fn vrc6_audio() {
+    let mut mapper = from_cartridge(cartridge(24, 8, 4)).unwrap();
+    mapper.write_cpu(0x9000, 0b1000_1111);
+    mapper.write_cpu(0x9002, 0x80);
+    mapper.clock_cpu();
+    let level = mapper.audio_output();
+    assert!(level > 0.0);
+
+    mapper.write_cpu(0xA000, 0b1000_1111);
+    mapper.write_cpu(0xA002, 0x80);
+    mapper.clock_cpu();
+    assert_eq!(mapper.audio_output(), level * 2.0);
+}
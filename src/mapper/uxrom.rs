@@ -0,0 +1,61 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+
+const PRG_BANK_LEN: usize = 0x4000;
+const CHR_BANK_LEN: usize = 0x2000;
+
+/// Mapper 2: a switchable 16 KiB PRG bank at $8000 and the last bank fixed at $C000.
+pub struct Uxrom {
+    prg_rom: Memory,
+    chr: Memory,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    prg_bank: usize,
+}
+
+impl Uxrom {
+    pub fn new(cartridge: Cartridge, bus_conflicts: bool) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            chr: super::chr_memory(chr_rom, &header),
+            mirroring: header.mirroring(),
+            bus_conflicts,
+            prg_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x8000..=0xBFFF => self.prg_rom.read(self.prg_bank, PRG_BANK_LEN, address as usize),
+            0xC000..=0xFFFF => {
+                let bank_last = self.prg_rom.bank_count(PRG_BANK_LEN) - 1;
+                self.prg_rom.read(bank_last, PRG_BANK_LEN, address as usize)
+            },
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let value = super::resolve_bus_conflict(self, self.bus_conflicts, address, value);
+            self.prg_bank = value as usize;
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr.read(0, CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(0, CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,277 @@
+use super::{Mapper, Memory};
+use crate::cartridge::{Cartridge, Mirroring};
+use crate::types::BitRead;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+/// PPU dots per scanline, which the VRC IRQ prescaler counts down in steps of 3 per CPU cycle.
+const IRQ_PRESCALER: i16 = 341;
+
+/// Mappers 21, 22, 23 and 25: the Konami VRC2 and VRC4.
+///
+/// Boards connect the chip's two register select pins to different CPU address lines. The
+/// NES 2.0 submapper tells which; without one, both candidate lines of the mapper number
+/// are decoded together, which works for every known board.
+pub struct Vrc4 {
+    prg_rom: Memory,
+    prg_ram: Memory,
+    chr: Memory,
+    chip: VrcChip,
+    /// CPU address lines wired to the register select pins A0 and A1.
+    lines: [u16; 2],
+    /// VRC2a ignores the lowest bit of the CHR bank registers.
+    chr_shift: u8,
+    prg_banks: [u8; 2],
+    prg_swapped: bool,
+    chr_banks: [u16; 8],
+    mirroring: Mirroring,
+    /// VRC2 boards without PRG RAM have a one-bit latch at $6000-$6FFF instead.
+    latch: u8,
+    irq: VrcIrq,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VrcChip {
+    Vrc2,
+    Vrc4,
+}
+
+impl Vrc4 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        let (chip, lines) = match (header.mapper(), header.submapper()) {
+            (21, 1) => (VrcChip::Vrc4, [0x02, 0x04]),
+            (21, 2) => (VrcChip::Vrc4, [0x40, 0x80]),
+            (21, _) => (VrcChip::Vrc4, [0x42, 0x84]),
+            (22, _) => (VrcChip::Vrc2, [0x02, 0x01]),
+            (23, 1) => (VrcChip::Vrc4, [0x01, 0x02]),
+            (23, 2) => (VrcChip::Vrc4, [0x04, 0x08]),
+            (23, 3) => (VrcChip::Vrc2, [0x01, 0x02]),
+            (23, _) => (VrcChip::Vrc4, [0x05, 0x0A]),
+            (_, 1) => (VrcChip::Vrc4, [0x02, 0x01]),
+            (_, 2) => (VrcChip::Vrc4, [0x08, 0x04]),
+            (_, 3) => (VrcChip::Vrc2, [0x02, 0x01]),
+            (_, _) => (VrcChip::Vrc4, [0x0A, 0x05]),
+        };
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            prg_ram: Memory::ram(header.prg_ram_len() + header.prg_nvram_len()),
+            chr: super::chr_memory(chr_rom, &header),
+            chip,
+            lines,
+            chr_shift: (header.mapper() == 22) as u8,
+            prg_banks: [0; 2],
+            prg_swapped: false,
+            chr_banks: [0; 8],
+            mirroring: header.mirroring(),
+            latch: 0,
+            irq: VrcIrq::default(),
+        }
+    }
+
+    pub fn chip(&self) -> VrcChip {
+        self.chip
+    }
+
+    /// Register number 0-3 within the $x000 block selected by the wired address lines.
+    fn register(&self, address: u16) -> u16 {
+        (address & self.lines[0] != 0) as u16 | ((address & self.lines[1] != 0) as u16) << 1
+    }
+
+    fn prg_bank(&self, address: u16) -> usize {
+        let bank_second_last = self.prg_rom.bank_count(PRG_BANK_LEN).saturating_sub(2);
+
+        match ((address >> 13) & 0b11, self.prg_swapped) {
+            (0, false) | (2, true) => self.prg_banks[0] as usize,
+            (0, true) | (2, false) => bank_second_last,
+            (1, _) => self.prg_banks[1] as usize,
+            _ => bank_second_last + 1,
+        }
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        (self.chr_banks[(address >> 10) as usize] >> self.chr_shift) as usize
+    }
+
+    fn write_chr_bank(&mut self, address: u16, register: u16, value: u8) {
+        let bank = &mut self.chr_banks[(((address - 0xB000) >> 12) * 2 + (register >> 1)) as usize];
+
+        if register & 1 == 1 {
+            let mask = match self.chip {
+                VrcChip::Vrc2 => 0x0F,
+                VrcChip::Vrc4 => 0x1F,
+            };
+            *bank = (*bank & 0x0F) | ((value as u16 & mask) << 4);
+        } else {
+            *bank = (*bank & !0x0F) | (value as u16 & 0x0F);
+        }
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => self.prg_ram.read(0, PRG_BANK_LEN, address as usize),
+            0x6000..=0x6FFF if self.chip == VrcChip::Vrc2 => Some(self.latch),
+            0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank(address), PRG_BANK_LEN, address as usize),
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        let register = self.register(address);
+
+        match (address & 0xF000, register) {
+            (0x6000 | 0x7000, _) if !self.prg_ram.is_empty() => {
+                self.prg_ram.write(0, PRG_BANK_LEN, address as usize, value)
+            },
+            (0x6000, _) if self.chip == VrcChip::Vrc2 => self.latch = value & 1,
+            (0x8000, _) => self.prg_banks[0] = value & 0b1_1111,
+            (0x9000, 2) if self.chip == VrcChip::Vrc4 => self.prg_swapped = value.is_bit_set(1),
+            (0x9000, 3) if self.chip == VrcChip::Vrc4 => {},
+            (0x9000, _) => {
+                self.mirroring = match (self.chip, value & 0b11) {
+                    (VrcChip::Vrc2, mode) if mode & 1 == 0 => Mirroring::Vertical,
+                    (VrcChip::Vrc2, _) => Mirroring::Horizontal,
+                    (_, 0) => Mirroring::Vertical,
+                    (_, 1) => Mirroring::Horizontal,
+                    (_, 2) => Mirroring::SingleScreenLower,
+                    (_, _) => Mirroring::SingleScreenUpper,
+                }
+            },
+            (0xA000, _) => self.prg_banks[1] = value & 0b1_1111,
+            (0xB000..=0xE000, _) => self.write_chr_bank(address & 0xF000, register, value),
+            (0xF000, _) if self.chip == VrcChip::Vrc4 => self.irq.write(register, value),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_bank(address), CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(self.chr_bank(address), CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.bytes()
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        self.prg_ram.bytes_mut()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.irq.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn cycles_until_irq(&self) -> Option<u32> {
+        self.irq.cycles_until_irq()
+    }
+}
+
+/// The IRQ counter shared by the VRC4, VRC6 and VRC7: an 8-bit up counter raising an IRQ
+/// when it overflows, clocked either every CPU cycle or once per scanline by a prescaler.
+#[derive(Default)]
+pub(super) struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enabled_after_ack: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl VrcIrq {
+    /// Handles a write to the VRC4's four IRQ registers: latch low nibble, latch high nibble,
+    /// control and acknowledge.
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.latch = (self.latch & 0xF0) | (value & 0x0F),
+            1 => self.latch = (self.latch & 0x0F) | (value << 4),
+            2 => self.write_control(value),
+            _ => self.acknowledge(),
+        }
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    /// CPU cycles until the counter can next overflow, at least; with the prescaler, scanlines
+    /// are counted at their shortest of 113 cycles.
+    pub fn cycles_until_irq(&self) -> Option<u32> {
+        if !self.enabled {
+            return None;
+        }
+
+        let counts = (0xFF - self.counter) as u32;
+
+        if self.cycle_mode {
+            Some(counts + 1)
+        } else {
+            Some((self.prescaler.max(0) as u32).div_ceil(3) + counts * (IRQ_PRESCALER as u32 / 3))
+        }
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        self.enabled_after_ack = value.is_bit_set(0);
+        self.enabled = value.is_bit_set(1);
+        self.cycle_mode = value.is_bit_set(2);
+        self.pending = false;
+
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = IRQ_PRESCALER;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enabled_after_ack;
+    }
+
+    /// Called once every CPU cycle.
+    pub fn clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.cycle_mode {
+            self.clock_counter();
+        } else {
+            self.prescaler -= 3;
+
+            if self.prescaler <= 0 {
+                self.prescaler += IRQ_PRESCALER;
+                self.clock_counter();
+            }
+        }
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use super::vrc4::VrcIrq;
+use super::{Mapper, Memory};
+use crate::apu::{Vrc6Pulse, Vrc6Sawtooth};
+use crate::cartridge::{Cartridge, Mirroring};
+use crate::types::BitRead;
+
+const PRG_BANK_LEN: usize = 0x2000;
+const CHR_BANK_LEN: usize = 0x0400;
+/// Output of one step of the VRC6 channels, close to one step of a 2A03 pulse channel.
+const AUDIO_LEVEL_STEP: f32 = 0.00752;
+
+/// Mappers 24 and 26: the Konami VRC6, with the VRC IRQ counter and two pulse channels and
+/// a sawtooth channel of expansion audio. Mapper 26 swaps the register select lines.
+///
+/// Only the plain 1 KiB CHR banking of $B003 is done; nametables from CHR ROM are not.
+pub struct Vrc6 {
+    prg_rom: Memory,
+    prg_ram: Memory,
+    chr: Memory,
+    swapped_lines: bool,
+    prg_bank_16k: u8,
+    prg_bank_8k: u8,
+    chr_banks: [u8; 8],
+    banking: u8,
+    irq: VrcIrq,
+    pulses: [Vrc6Pulse; 2],
+    sawtooth: Vrc6Sawtooth,
+    audio_halted: bool,
+    audio_shift: u8,
+}
+
+impl Vrc6 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = cartridge.header().clone();
+        let (prg_rom, chr_rom) = cartridge.into_rom();
+
+        Self {
+            prg_rom: Memory::rom(prg_rom),
+            prg_ram: Memory::ram(header.prg_ram_len() + header.prg_nvram_len()),
+            chr: super::chr_memory(chr_rom, &header),
+            swapped_lines: header.mapper() == 26,
+            prg_bank_16k: 0,
+            prg_bank_8k: 0,
+            chr_banks: [0; 8],
+            banking: 0,
+            irq: VrcIrq::default(),
+            pulses: [Vrc6Pulse::default(), Vrc6Pulse::default()],
+            sawtooth: Vrc6Sawtooth::default(),
+            audio_halted: false,
+            audio_shift: 0,
+        }
+    }
+
+    /// Register number 0-3 within the $x000 block, from A0 and A1 (swapped on mapper 26).
+    fn register(&self, address: u16) -> u16 {
+        if self.swapped_lines {
+            ((address & 1) << 1) | ((address >> 1) & 1)
+        } else {
+            address & 0b11
+        }
+    }
+
+    fn prg_bank(&self, address: u16) -> usize {
+        match address {
+            0x8000..=0xBFFF => (self.prg_bank_16k as usize) << 1 | ((address >> 13) & 1) as usize,
+            0xC000..=0xDFFF => self.prg_bank_8k as usize,
+            _ => self.prg_rom.bank_count(PRG_BANK_LEN) - 1,
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.banking.is_bit_set(7)
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        self.chr_banks[(address >> 10) as usize] as usize
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn read_cpu(&mut self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.prg_ram.read(0, PRG_BANK_LEN, address as usize),
+            0x8000..=0xFFFF => self.prg_rom.read(self.prg_bank(address), PRG_BANK_LEN, address as usize),
+            _ => None,
+        }
+    }
+
+    fn write_cpu(&mut self, address: u16, value: u8) {
+        let register = self.register(address);
+
+        match (address & 0xF000, register) {
+            (0x6000 | 0x7000, _) if self.prg_ram_enabled() => {
+                self.prg_ram.write(0, PRG_BANK_LEN, address as usize, value)
+            },
+            (0x8000, _) => self.prg_bank_16k = value & 0x0F,
+            (0x9000, 3) => {
+                self.audio_halted = value.is_bit_set(0);
+                self.audio_shift = if value.is_bit_set(2) {
+                    8
+                } else if value.is_bit_set(1) {
+                    4
+                } else {
+                    0
+                };
+            },
+            (0x9000, _) => self.pulses[0].write(register, value),
+            (0xA000, _) => self.pulses[1].write(register, value),
+            (0xB000, 3) => self.banking = value,
+            (0xB000, _) => self.sawtooth.write(register, value),
+            (0xC000, _) => self.prg_bank_8k = value & 0x1F,
+            (0xD000, _) => self.chr_banks[register as usize] = value,
+            (0xE000, _) => self.chr_banks[4 + register as usize] = value,
+            (0xF000, 0) => self.irq.write_latch(value),
+            (0xF000, 1) => self.irq.write_control(value),
+            (0xF000, 2) => self.irq.acknowledge(),
+            _ => {},
+        }
+    }
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr.read(self.chr_bank(address), CHR_BANK_LEN, address as usize).unwrap_or(0)
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr.write(self.chr_bank(address), CHR_BANK_LEN, address as usize, value);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking >> 2) & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn prg_ram(&self) -> &[u8] {
+        self.prg_ram.bytes()
+    }
+
+    fn prg_ram_mut(&mut self) -> &mut [u8] {
+        self.prg_ram.bytes_mut()
+    }
+
+    fn clock_cpu(&mut self) {
+        self.irq.clock();
+
+        if !self.audio_halted {
+            let shift = self.audio_shift;
+            self.pulses.iter_mut().for_each(|pulse| pulse.clock(shift));
+            self.sawtooth.clock(shift);
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn cycles_until_irq(&self) -> Option<u32> {
+        self.irq.cycles_until_irq()
+    }
+
+    fn audio_output(&self) -> f32 {
+        let level = self.pulses[0].output() + self.pulses[1].output() + self.sawtooth.output();
+        level as f32 * AUDIO_LEVEL_STEP
+    }
+}
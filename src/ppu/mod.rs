@@ -0,0 +1,760 @@
+mod tests;
+
+use crate::cpu::clock::ClockMode;
+use crate::mapper::Mapper;
+
+const VRAM_LEN: usize = 0x1000;
+const OAM_LEN: usize = 0x0100;
+const PALETTE_LEN: usize = 0x20;
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+const DOTS_PER_SCANLINE: u16 = 341;
+/// Sprites the hardware can show on one scanline.
+const SPRITES_PER_SCANLINE: usize = 8;
+
+/// The 2C02 picture processing unit, seen by the CPU through eight registers at $2000-$2007.
+///
+/// Its own address space is laid out as follows:
+///
+/// | Range         | Memory                                                 |
+/// |---------------|--------------------------------------------------------|
+/// | $0000-$1FFF   | Pattern tables, on the cartridge                       |
+/// | $2000-$2FFF   | Nametables, CIRAM arranged by the cartridge's mirroring |
+/// | $3000-$3EFF   | Mirror of $2000-$2EFF                                  |
+/// | $3F00-$3FFF   | Palette RAM, 32 bytes mirrored                         |
+///
+/// Rendering is stepped one dot at a time with `tick`. A frame is 341 dots by 262 scanlines on
+/// NTSC and 312 scanlines on PAL and Dendy: the 240 visible lines, one idle line, vertical
+/// blank, and the pre-render line that prefetches the first tiles of the next frame.
+#[derive(CopyGetters)]
+pub struct Ppu {
+    clock_mode: ClockMode,
+    ctrl: Control,
+    mask: Mask,
+    status: Status,
+    oam_address: u8,
+    oam: [u8; OAM_LEN],
+    /// Current VRAM address (loopy's v), 15 bits.
+    v: u16,
+    /// Temporary VRAM address (loopy's t), the top left of the screen while rendering.
+    t: u16,
+    /// Fine X scroll, 3 bits.
+    x: u8,
+    /// Write toggle shared by $2005 and $2006, set after the first write.
+    w: bool,
+    read_buffer: u8,
+    /// Value last driven on the PPU's data bus to the CPU, read back from write-only registers.
+    io_latch: u8,
+    /// CIRAM, sized for four screens so boards with extra nametable RAM are covered too.
+    vram: [u8; VRAM_LEN],
+    palette: [u8; PALETTE_LEN],
+    /// Scanline being rendered, where 0-239 are visible and the last one is the pre-render line.
+    #[getset(get_copy = "pub")]
+    scanline: u16,
+    /// Dot within the scanline, 0-340.
+    #[getset(get_copy = "pub")]
+    dot: u16,
+    /// Frames completed since power-on.
+    #[getset(get_copy = "pub")]
+    frame: u64,
+    /// Level of the NMI output to the CPU.
+    #[getset(get_copy = "pub")]
+    nmi: bool,
+    /// Set by a status read just before vertical blank, which keeps the flag from being set.
+    vblank_suppressed: bool,
+    background: Background,
+    /// Sprites the evaluation on this scanline found for the next one.
+    secondary_oam: Vec<[u8; 4]>,
+    /// Whether sprite 0 is the first of them.
+    sprite_zero_next: bool,
+    /// Dot at which the evaluation on this scanline raises the overflow flag.
+    overflow_dot: Option<u16>,
+    /// Sprites fetched for the scanline being drawn.
+    sprites: Vec<Sprite>,
+    /// Whether at most 8 sprites are drawn per scanline like on hardware, causing flicker.
+    #[getset(get_copy = "pub")]
+    sprite_limit: bool,
+    frame_buffer: Vec<u8>,
+}
+
+/// Latches and shift registers of the background pipeline. Each tile is fetched over eight
+/// dots into the latches, then loaded into the low byte of the shift registers, which are
+/// shifted left every dot so that the pixel under fine X is always found in the high byte.
+#[derive(Default)]
+struct Background {
+    tile: u8,
+    /// Palette number of the latched tile, taken from its quadrant of the attribute byte.
+    palette: u8,
+    pattern: [u8; 2],
+    pattern_shift: [u16; 2],
+    palette_shift: [u16; 2],
+}
+
+impl Background {
+    fn load(&mut self) {
+        for plane in 0..2 {
+            let palette_bits = if self.palette >> plane & 1 == 1 { 0xFF } else { 0x00 };
+            self.pattern_shift[plane] = (self.pattern_shift[plane] & 0xFF00) | self.pattern[plane] as u16;
+            self.palette_shift[plane] = (self.palette_shift[plane] & 0xFF00) | palette_bits;
+        }
+    }
+
+    fn shift(&mut self) {
+        for plane in 0..2 {
+            self.pattern_shift[plane] <<= 1;
+            self.palette_shift[plane] <<= 1;
+        }
+    }
+
+    /// Palette number and color number of the current pixel, offset by fine X scroll.
+    fn pixel(&self, fine_x: u8) -> (u8, u8) {
+        let bit = 15 - fine_x;
+        let pick = |shift: &[u16; 2]| ((shift[1] >> bit) as u8 & 1) << 1 | ((shift[0] >> bit) as u8 & 1);
+        (pick(&self.palette_shift), pick(&self.pattern_shift))
+    }
+}
+
+/// A sprite fetched for the scanline being drawn.
+#[derive(Copy, Clone)]
+struct Sprite {
+    x: u8,
+    attributes: SpriteAttributes,
+    pattern: [u8; 2],
+    zero: bool,
+}
+
+impl Sprite {
+    /// Color number of the sprite at screen column `x`, 0 where it is transparent or absent.
+    fn pixel(&self, x: usize) -> u8 {
+        let offset = x.wrapping_sub(self.x as usize);
+
+        if offset >= 8 {
+            return 0;
+        }
+
+        let bit = 7 - offset;
+        (self.pattern[1] >> bit & 1) << 1 | (self.pattern[0] >> bit & 1)
+    }
+}
+
+bitflags! {
+    /// Byte 2 of a sprite in OAM
+    struct SpriteAttributes: u8 {
+        const FLIP_VERTICAL = 0b1000_0000;
+        const FLIP_HORIZONTAL = 0b0100_0000;
+        const BEHIND_BACKGROUND = 0b0010_0000;
+        const PALETTE = 0b0000_0011;
+    }
+}
+
+bitflags! {
+    /// PPUCTRL ($2000)
+    struct Control: u8 {
+        const NMI_ENABLE = 0b1000_0000;
+        const MASTER_SLAVE = 0b0100_0000;
+        const SPRITE_SIZE_16 = 0b0010_0000;
+        const BACKGROUND_TABLE = 0b0001_0000;
+        const SPRITE_TABLE = 0b0000_1000;
+        const INCREMENT_32 = 0b0000_0100;
+        const NAMETABLE = 0b0000_0011;
+    }
+}
+
+bitflags! {
+    /// PPUMASK ($2001)
+    struct Mask: u8 {
+        const EMPHASIZE_BLUE = 0b1000_0000;
+        const EMPHASIZE_GREEN = 0b0100_0000;
+        const EMPHASIZE_RED = 0b0010_0000;
+        const SHOW_SPRITES = 0b0001_0000;
+        const SHOW_BACKGROUND = 0b0000_1000;
+        const SHOW_SPRITES_LEFT = 0b0000_0100;
+        const SHOW_BACKGROUND_LEFT = 0b0000_0010;
+        const GRAYSCALE = 0b0000_0001;
+    }
+}
+
+bitflags! {
+    /// PPUSTATUS ($2002)
+    struct Status: u8 {
+        const VBLANK = 0b1000_0000;
+        const SPRITE_0_HIT = 0b0100_0000;
+        const SPRITE_OVERFLOW = 0b0010_0000;
+    }
+}
+
+impl Ppu {
+    pub fn new(clock_mode: ClockMode) -> Self {
+        Self {
+            clock_mode,
+            ctrl: Control::empty(),
+            mask: Mask::empty(),
+            status: Status::empty(),
+            oam_address: 0,
+            oam: [0; OAM_LEN],
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+            read_buffer: 0,
+            io_latch: 0,
+            vram: [0; VRAM_LEN],
+            palette: [0; PALETTE_LEN],
+            scanline: 0,
+            dot: 0,
+            frame: 0,
+            nmi: false,
+            vblank_suppressed: false,
+            background: Background::default(),
+            secondary_oam: Vec::with_capacity(SPRITES_PER_SCANLINE),
+            sprite_zero_next: false,
+            overflow_dot: None,
+            sprites: Vec::with_capacity(SPRITES_PER_SCANLINE),
+            sprite_limit: true,
+            frame_buffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+
+    /// The last rendered picture, 256x240 palette RAM values (NES color indices 0-63) in rows
+    /// from the top left.
+    pub fn frame_buffer(&self) -> &[u8] {
+        &self.frame_buffer
+    }
+
+    /// Sets whether at most 8 sprites are drawn per scanline. Without the limit, every sprite is
+    /// drawn and the flicker is gone, while the overflow flag is still raised as on hardware
+    /// since games time raster effects with it.
+    pub fn set_sprite_limit(&mut self, enabled: bool) {
+        self.sprite_limit = enabled;
+    }
+
+    /// Advances rendering by one dot.
+    pub fn tick(&mut self, mapper: &mut dyn Mapper) {
+        let rendering = self.is_rendering_enabled();
+        let pre_render = self.scanline == self.pre_render_scanline();
+        let visible = (self.scanline as usize) < SCREEN_HEIGHT;
+
+        if rendering && (visible || pre_render) {
+            self.render_background(mapper, pre_render);
+            self.render_sprites(mapper, visible);
+        }
+
+        if visible && (1..=SCREEN_WIDTH as u16).contains(&self.dot) {
+            self.output_pixel(rendering);
+        }
+
+        if self.scanline == self.vblank_scanline() {
+            match self.dot {
+                1 if !self.vblank_suppressed => self.status.insert(Status::VBLANK),
+                1 => self.vblank_suppressed = false,
+                3 => self.update_nmi(),
+                _ => {},
+            }
+        } else if pre_render && self.dot == 1 {
+            self.status.remove(Status::VBLANK | Status::SPRITE_0_HIT | Status::SPRITE_OVERFLOW);
+            self.update_nmi();
+        }
+
+        self.dot += 1;
+
+        // NTSC drops the last dot of the pre-render line on odd frames while rendering
+        let skip_dot = pre_render && rendering && self.frame & 1 == 1 && self.clock_mode == ClockMode::Ntsc;
+
+        if self.dot == DOTS_PER_SCANLINE || (skip_dot && self.dot == DOTS_PER_SCANLINE - 1) {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline > self.pre_render_scanline() {
+                self.scanline = 0;
+                self.frame += 1;
+            }
+        }
+    }
+
+    /// Dots to run until the NMI output may change by itself, as the flag is set at the start
+    /// of vertical blank or cleared at its end; anything else is a register access.
+    pub fn dots_until_nmi_change(&self) -> u64 {
+        let position = |scanline: u16, dot: u16| scanline as u64 * DOTS_PER_SCANLINE as u64 + dot as u64;
+        let now = position(self.scanline, self.dot);
+        let frame_len = position(self.pre_render_scanline() + 1, 0);
+
+        // the dot skipped at the end of the pre-render line of odd frames
+        let skip = self.is_rendering_enabled()
+            && self.frame & 1 == 1
+            && self.clock_mode == ClockMode::Ntsc
+            && now <= position(self.pre_render_scanline(), DOTS_PER_SCANLINE - 2);
+
+        let targets = [position(self.vblank_scanline(), 3), position(self.pre_render_scanline(), 1)];
+
+        targets.iter()
+            .map(|&target| if target >= now {
+                target - now + 1
+            } else {
+                frame_len - now + target + 1 - skip as u64
+            })
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Drives the NMI output from the vblank flag and the enable bit. At the start of vertical
+    /// blank the output follows the flag two dots late, which is how a status read within
+    /// that window both sees the flag and suppresses the NMI.
+    fn update_nmi(&mut self) {
+        self.nmi = self.status.contains(Status::VBLANK) && self.ctrl.contains(Control::NMI_ENABLE);
+    }
+
+    fn pre_render_scanline(&self) -> u16 {
+        match self.clock_mode {
+            ClockMode::Ntsc => 261,
+            ClockMode::Pal | ClockMode::Dendy => 311,
+        }
+    }
+
+    /// Scanline starting vertical blank; Dendy adds its 50 extra lines before it instead of after.
+    fn vblank_scanline(&self) -> u16 {
+        match self.clock_mode {
+            ClockMode::Ntsc | ClockMode::Pal => 241,
+            ClockMode::Dendy => 291,
+        }
+    }
+
+    fn is_rendering_enabled(&self) -> bool {
+        self.mask.intersects(Mask::SHOW_BACKGROUND | Mask::SHOW_SPRITES)
+    }
+
+    /// Performs the background fetches and scroll updates of the current dot on a visible or
+    /// the pre-render scanline.
+    ///
+    /// Each memory access takes two dots and is done here on the first. Tiles for dots 1-256
+    /// are fetched eight dots ahead, starting with the first two tiles of the next line at dots
+    /// 321-336.
+    fn render_background(&mut self, mapper: &mut dyn Mapper, pre_render: bool) {
+        let dot = self.dot;
+
+        if (2..=257).contains(&dot) || (322..=337).contains(&dot) {
+            self.background.shift();
+        }
+
+        if dot % 8 == 1 && ((9..=257).contains(&dot) || (329..=337).contains(&dot)) {
+            self.background.load();
+        }
+
+        if (1..=256).contains(&dot) || (321..=336).contains(&dot) {
+            match dot % 8 {
+                1 => self.background.tile = self.read_memory(self.tile_address(), mapper),
+                3 => {
+                    let v = self.v;
+                    let attribute = self.read_memory(0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07), mapper);
+                    let shift = ((v >> 4) & 0b100) | (v & 0b10);
+                    self.background.palette = (attribute >> shift) & 0b11;
+                },
+                5 => self.background.pattern[0] = self.read_memory(self.pattern_address(), mapper),
+                7 => self.background.pattern[1] = self.read_memory(self.pattern_address() + 8, mapper),
+                0 => self.increment_coarse_x(),
+                _ => {},
+            }
+        }
+
+        match dot {
+            256 => self.increment_y(),
+            // copy the horizontal scroll from t
+            257 => self.v = (self.v & !0x041F) | (self.t & 0x041F),
+            // copy the vertical scroll from t
+            280..=304 if pre_render => self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0),
+            // two unused fetches of the next tile, which MMC5 uses to detect scanlines
+            337 | 339 => {
+                self.read_memory(self.tile_address(), mapper);
+            },
+            _ => {},
+        }
+    }
+
+    /// Performs the sprite evaluation and fetches of the current dot on a visible or the
+    /// pre-render scanline.
+    ///
+    /// Secondary OAM is cleared over dots 1-64 and filled by the evaluation over dots 65-256,
+    /// which is done at once here except for the timing of the overflow flag. Dots 257-320 then
+    /// fetch the patterns of eight sprites, whether found or not.
+    fn render_sprites(&mut self, mapper: &mut dyn Mapper, visible: bool) {
+        let dot = self.dot;
+
+        match dot {
+            1 => {
+                self.secondary_oam.clear();
+                self.sprite_zero_next = false;
+                self.overflow_dot = None;
+            },
+            65 if visible => self.evaluate_sprites(),
+            257..=320 => {
+                self.oam_address = 0;
+                let slot = (dot - 257) as usize / 8;
+
+                match (dot - 257) % 8 {
+                    0 if slot == 0 => {
+                        self.sprites.clear();
+                        self.read_memory(self.tile_address(), mapper);
+                    },
+                    // two unused nametable fetches take the place of the background's
+                    0 | 2 => {
+                        self.read_memory(self.tile_address(), mapper);
+                    },
+                    4 => self.fetch_sprite(slot, 0, mapper),
+                    6 => {
+                        self.fetch_sprite(slot, 1, mapper);
+
+                        // without the limit, the sprites beyond the eighth are fetched after it
+                        if slot == SPRITES_PER_SCANLINE - 1 {
+                            for slot in SPRITES_PER_SCANLINE..self.secondary_oam.len() {
+                                self.fetch_sprite(slot, 0, mapper);
+                                self.fetch_sprite(slot, 1, mapper);
+                            }
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            _ => {},
+        }
+
+        if self.overflow_dot == Some(dot) {
+            self.status.insert(Status::SPRITE_OVERFLOW);
+        }
+    }
+
+    /// Finds the sprites on the next scanline, which are those whose Y coordinate, one less
+    /// than their top row, is on this one.
+    ///
+    /// After 8 sprites, the hardware goes on checking for overflow but also steps to the next
+    /// byte of every sprite it moves past, comparing tiles, attributes and X coordinates as if
+    /// they were Y coordinates. This both misses overflows and reports false ones.
+    fn evaluate_sprites(&mut self) {
+        let height = if self.ctrl.contains(Control::SPRITE_SIZE_16) { 16 } else { 8 };
+        let scanline = self.scanline;
+        let in_range = |y: u8| scanline.wrapping_sub(y as u16) < height;
+
+        let mut dot = 65;
+        let mut byte = 0;
+
+        for sprite in 0..64 {
+            let entry = &self.oam[sprite * 4..sprite * 4 + 4];
+
+            if self.secondary_oam.len() < SPRITES_PER_SCANLINE {
+                if in_range(entry[0]) {
+                    self.sprite_zero_next |= sprite == 0;
+                    self.secondary_oam.push([entry[0], entry[1], entry[2], entry[3]]);
+                    dot += 8;
+                } else {
+                    dot += 2;
+                }
+            } else if in_range(entry[byte]) {
+                self.overflow_dot = Some(dot);
+                break;
+            } else {
+                byte = (byte + 1) & 0b11;
+                dot += 2;
+            }
+        }
+
+        if !self.sprite_limit {
+            let first = self.secondary_oam.len();
+            let extra = self.oam.chunks(4).filter(|entry| in_range(entry[0])).skip(first);
+            self.secondary_oam.extend(extra.map(|entry| [entry[0], entry[1], entry[2], entry[3]]));
+        }
+    }
+
+    /// Fetches one pattern plane of the sprite in `slot` of secondary OAM. Empty slots fetch
+    /// tile $FF, as the cleared secondary OAM holds $FF throughout.
+    fn fetch_sprite(&mut self, slot: usize, plane: u16, mapper: &mut dyn Mapper) {
+        let entry = self.secondary_oam.get(slot).copied().unwrap_or([0xFF; 4]);
+        let attributes = SpriteAttributes::from_bits_truncate(entry[2]);
+        let address = self.sprite_pattern_address(entry) + plane * 8;
+        let mut pattern = self.read_memory(address, mapper);
+
+        if attributes.contains(SpriteAttributes::FLIP_HORIZONTAL) {
+            pattern = pattern.reverse_bits();
+        }
+
+        if slot >= self.secondary_oam.len() {
+            return;
+        }
+
+        if plane == 0 {
+            self.sprites.push(Sprite {
+                x: entry[3],
+                attributes,
+                pattern: [pattern, 0],
+                zero: slot == 0 && self.sprite_zero_next,
+            });
+        } else if let Some(sprite) = self.sprites.get_mut(slot) {
+            sprite.pattern[1] = pattern;
+        }
+    }
+
+    fn sprite_pattern_address(&self, entry: [u8; 4]) -> u16 {
+        let attributes = SpriteAttributes::from_bits_truncate(entry[2]);
+        let flip = attributes.contains(SpriteAttributes::FLIP_VERTICAL);
+        let row = (self.scanline as u8).wrapping_sub(entry[0]) as u16;
+        let tile = entry[1] as u16;
+
+        if self.ctrl.contains(Control::SPRITE_SIZE_16) {
+            // the table comes from bit 0 of the tile, the bottom half is the next tile
+            let row = if flip { 15 - (row & 15) } else { row & 15 };
+            (tile & 1) << 12 | (tile & 0xFE) << 4 | (row & 8) << 1 | (row & 7)
+        } else {
+            let table = if self.ctrl.contains(Control::SPRITE_TABLE) { 0x1000 } else { 0 };
+            let row = if flip { 7 - (row & 7) } else { row & 7 };
+            table | tile << 4 | row
+        }
+    }
+
+    fn tile_address(&self) -> u16 {
+        0x2000 | (self.v & 0x0FFF)
+    }
+
+    fn pattern_address(&self) -> u16 {
+        let table = if self.ctrl.contains(Control::BACKGROUND_TABLE) { 0x1000 } else { 0 };
+        table | (self.background.tile as u16) << 4 | (self.v >> 12)
+    }
+
+    fn output_pixel(&mut self, rendering: bool) {
+        let x = self.dot as usize - 1;
+        let show_background = self.mask.contains(Mask::SHOW_BACKGROUND)
+            && (x >= 8 || self.mask.contains(Mask::SHOW_BACKGROUND_LEFT));
+
+        let show_sprites = self.mask.contains(Mask::SHOW_SPRITES)
+            && (x >= 8 || self.mask.contains(Mask::SHOW_SPRITES_LEFT));
+
+        let (background_palette, background_pixel) = if show_background {
+            self.background.pixel(self.x)
+        } else {
+            (0, 0)
+        };
+
+        // the first opaque sprite wins, even when it is behind the background
+        let sprite = self.sprites.iter()
+            .filter(|_| show_sprites)
+            .map(|sprite| (sprite, sprite.pixel(x)))
+            .find(|&(_, pixel)| pixel != 0);
+
+        let (palette, pixel) = match sprite {
+            Some((sprite, pixel)) => {
+                if sprite.zero && background_pixel != 0 && x != SCREEN_WIDTH - 1 {
+                    self.status.insert(Status::SPRITE_0_HIT);
+                }
+
+                if background_pixel != 0 && sprite.attributes.contains(SpriteAttributes::BEHIND_BACKGROUND) {
+                    (background_palette, background_pixel)
+                } else {
+                    ((sprite.attributes & SpriteAttributes::PALETTE).bits() + 4, pixel)
+                }
+            },
+            None => (background_palette, background_pixel),
+        };
+
+        let color = if !rendering && self.v & 0x3F00 == 0x3F00 {
+            // with rendering off, the backdrop is replaced by the palette entry v points to
+            self.read_palette(self.v)
+        } else if pixel == 0 {
+            self.read_palette(0x3F00)
+        } else {
+            self.read_palette(0x3F00 | (palette as u16) << 2 | pixel as u16)
+        };
+
+        self.frame_buffer[self.scanline as usize * SCREEN_WIDTH + x] = color;
+    }
+
+    /// Reads the register at `address` in $2000-$3FFF, mirrored every 8 bytes.
+    pub fn read_register(&mut self, address: u16, mapper: &mut dyn Mapper) -> u8 {
+        match address & 0b111 {
+            2 => {
+                // a read just before the flag is set sees it clear and keeps it from being set
+                if self.scanline == self.vblank_scanline() && self.dot == 1 {
+                    self.vblank_suppressed = true;
+                }
+
+                self.io_latch = self.status.bits() | (self.io_latch & 0b0001_1111);
+                self.status.remove(Status::VBLANK);
+                self.w = false;
+                self.update_nmi();
+            },
+            4 => {
+                // reads see secondary OAM being cleared to $FF
+                let clearing = self.is_rendering_enabled()
+                    && (self.scanline as usize) < SCREEN_HEIGHT
+                    && (1..=64).contains(&self.dot);
+
+                let mut value = self.oam[self.oam_address as usize];
+
+                // the attribute byte has no storage for bits 2-4
+                if self.oam_address & 0b11 == 2 {
+                    value &= 0b1110_0011;
+                }
+
+                self.io_latch = if clearing { 0xFF } else { value };
+            },
+            7 => {
+                let address = self.v & 0x3FFF;
+
+                if address < 0x3F00 {
+                    self.io_latch = self.read_buffer;
+                    self.read_buffer = self.read_memory(address, mapper);
+                } else {
+                    // palette reads are immediate, while the buffer gets the nametable below
+                    self.io_latch = self.read_palette(address) | (self.io_latch & 0b1100_0000);
+                    self.read_buffer = self.read_memory(address - 0x1000, mapper);
+                }
+
+                self.increment_v();
+            },
+            _ => {},
+        }
+
+        self.io_latch
+    }
+
+    /// Writes the register at `address` in $2000-$3FFF, mirrored every 8 bytes.
+    pub fn write_register(&mut self, address: u16, value: u8, mapper: &mut dyn Mapper) {
+        self.io_latch = value;
+
+        match address & 0b111 {
+            0 => {
+                self.ctrl = Control::from_bits_truncate(value);
+                self.t = (self.t & !0x0C00) | ((value as u16 & 0b11) << 10);
+
+                // the enable bit acts at once, except while the output still lags the flag
+                if !(self.scanline == self.vblank_scanline() && self.dot < 3) {
+                    self.update_nmi();
+                }
+            },
+            1 => self.mask = Mask::from_bits_truncate(value),
+            3 => self.oam_address = value,
+            4 => {
+                self.oam[self.oam_address as usize] = value;
+                self.oam_address = self.oam_address.wrapping_add(1);
+            },
+            5 => {
+                if self.w {
+                    self.t = (self.t & !0x73E0) | ((value as u16 & 0b111) << 12) | ((value as u16 & 0xF8) << 2);
+                } else {
+                    self.t = (self.t & !0x001F) | (value as u16 >> 3);
+                    self.x = value & 0b111;
+                }
+
+                self.w = !self.w;
+            },
+            6 => {
+                if self.w {
+                    self.t = (self.t & 0xFF00) | value as u16;
+                    self.v = self.t;
+                } else {
+                    self.t = (self.t & 0x00FF) | ((value as u16 & 0x3F) << 8);
+                }
+
+                self.w = !self.w;
+            },
+            7 => {
+                self.write_memory(self.v & 0x3FFF, value, mapper);
+                self.increment_v();
+            },
+            _ => {},
+        }
+    }
+
+    /// Reads the PPU address space at `address` in $0000-$3FFF.
+    fn read_memory(&mut self, address: u16, mapper: &mut dyn Mapper) -> u8 {
+        match address {
+            0x0000..=0x1FFF => mapper.read_ppu(address),
+            0x2000..=0x3EFF => {
+                let address = 0x2000 | (address & 0x0FFF);
+                mapper.read_nametable(address).unwrap_or_else(|| self.vram[vram_index(address, mapper)])
+            },
+            _ => self.read_palette(address),
+        }
+    }
+
+    /// Writes the PPU address space at `address` in $0000-$3FFF.
+    fn write_memory(&mut self, address: u16, value: u8, mapper: &mut dyn Mapper) {
+        match address {
+            0x0000..=0x1FFF => mapper.write_ppu(address, value),
+            0x2000..=0x3EFF => {
+                let address = 0x2000 | (address & 0x0FFF);
+
+                if !mapper.write_nametable(address, value) {
+                    self.vram[vram_index(address, mapper)] = value;
+                }
+            },
+            _ => self.palette[palette_index(address)] = value & 0b0011_1111,
+        }
+    }
+
+    fn read_palette(&self, address: u16) -> u8 {
+        let value = self.palette[palette_index(address)];
+
+        if self.mask.contains(Mask::GRAYSCALE) {
+            value & 0b0011_0000
+        } else {
+            value
+        }
+    }
+
+    /// Steps v after a $2007 access, which during rendering glitches into both scroll increments.
+    fn increment_v(&mut self) {
+        let rendering_line = (self.scanline as usize) < SCREEN_HEIGHT || self.scanline == self.pre_render_scanline();
+
+        if self.is_rendering_enabled() && rendering_line {
+            self.increment_coarse_x();
+            self.increment_y();
+        } else {
+            let increment = if self.ctrl.contains(Control::INCREMENT_32) { 32 } else { 1 };
+            self.v = (self.v + increment) & 0x7FFF;
+        }
+    }
+
+    /// Moves v to the next tile, wrapping into the horizontally adjacent nametable.
+    fn increment_coarse_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v = (self.v & !0x001F) ^ 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    /// Moves v down one pixel row, wrapping into the vertically adjacent nametable after row 29.
+    /// Coarse Y set to 30 or 31 through $2006 reads attribute bytes and wraps without switching.
+    fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !0x7000;
+
+        let coarse_y = match (self.v & 0x03E0) >> 5 {
+            29 => {
+                self.v ^= 0x0800;
+                0
+            },
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+
+        self.v = (self.v & !0x03E0) | coarse_y << 5;
+    }
+}
+
+/// Index into CIRAM of the nametable byte at `address`, following the cartridge's mirroring.
+fn vram_index(address: u16, mapper: &dyn Mapper) -> usize {
+    mapper.mirroring().page(address) * 0x0400 + (address & 0x03FF) as usize
+}
+
+/// Index into palette RAM, where the backdrop entries of the sprite palettes $3F10, $3F14,
+/// $3F18 and $3F1C mirror those of the background palettes.
+fn palette_index(address: u16) -> usize {
+    let index = address as usize & 0x1F;
+
+    if index & 0b1_0011 == 0b1_0000 {
+        index & 0x0F
+    } else {
+        index
+    }
+}
@@ -0,0 +1,606 @@
+#![cfg(test)]
+
+use super::*;
+use crate::cartridge::Mirroring;
+
+/// 8 KiB of CHR RAM with fixed mirroring, like a plain NROM board.
+struct ChrRam {
+    chr: Vec<u8>,
+    mirroring: Mirroring,
+}
+
+impl Mapper for ChrRam {
+    fn read_cpu(&mut self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    fn write_cpu(&mut self, _address: u16, _value: u8) {}
+
+    fn read_ppu(&mut self, address: u16) -> u8 {
+        self.chr[address as usize]
+    }
+
+    fn write_ppu(&mut self, address: u16, value: u8) {
+        self.chr[address as usize] = value;
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+fn ppu(mirroring: Mirroring) -> (Ppu, ChrRam) {
+    (Ppu::new(ClockMode::Ntsc), ChrRam { chr: vec![0; 0x2000], mirroring })
+}
+
+fn write(ppu: &mut Ppu, mapper: &mut ChrRam, address: u16, value: u8) {
+    ppu.write_register(address, value, mapper);
+}
+
+fn read(ppu: &mut Ppu, mapper: &mut ChrRam, address: u16) -> u8 {
+    ppu.read_register(address, mapper)
+}
+
+/// Writes `value` to PPU memory at `address` through PPUADDR and PPUDATA.
+fn poke(ppu: &mut Ppu, mapper: &mut ChrRam, address: u16, value: u8) {
+    write(ppu, mapper, 0x2006, (address >> 8) as u8);
+    write(ppu, mapper, 0x2006, address as u8);
+    write(ppu, mapper, 0x2007, value);
+}
+
+/// Reads PPU memory at `address` through PPUADDR and PPUDATA, skipping the buffered value.
+fn peek(ppu: &mut Ppu, mapper: &mut ChrRam, address: u16) -> u8 {
+    write(ppu, mapper, 0x2006, (address >> 8) as u8);
+    write(ppu, mapper, 0x2006, address as u8);
+    read(ppu, mapper, 0x2007);
+    write(ppu, mapper, 0x2006, (address >> 8) as u8);
+    write(ppu, mapper, 0x2006, address as u8);
+    read(ppu, mapper, 0x2007)
+}
+
+#[test]
+fn scroll_registers() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    write(&mut ppu, &mut mapper, 0x2000, 0b10);
+    assert_eq!(ppu.t, 0x0800);
+
+    write(&mut ppu, &mut mapper, 0x2005, 0x7D);
+    assert_eq!((ppu.t, ppu.x, ppu.w), (0x080F, 0b101, true));
+    write(&mut ppu, &mut mapper, 0x2005, 0x5E);
+    assert_eq!((ppu.t, ppu.w), (0x696F, false));
+
+    write(&mut ppu, &mut mapper, 0x2006, 0x3D);
+    assert_eq!((ppu.t, ppu.w), (0x3D6F, true));
+    write(&mut ppu, &mut mapper, 0x2006, 0xF0);
+    assert_eq!((ppu.t, ppu.v, ppu.w), (0x3DF0, 0x3DF0, false));
+}
+
+#[test]
+fn status_read() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    ppu.status = Status::VBLANK | Status::SPRITE_0_HIT;
+    write(&mut ppu, &mut mapper, 0x2005, 0x12);
+    assert!(ppu.w);
+
+    // the low bits come from whatever was last on the bus
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2002), 0b1101_0010);
+    assert!(!ppu.w);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2002), 0b0101_0010);
+}
+
+#[test]
+fn write_only_registers_read_latch() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    write(&mut ppu, &mut mapper, 0x2001, 0x5A);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2000), 0x5A);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2005), 0x5A);
+}
+
+#[test]
+fn data_read_buffer() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    poke(&mut ppu, &mut mapper, 0x2000, 0x11);
+    write(&mut ppu, &mut mapper, 0x2007, 0x22);
+
+    write(&mut ppu, &mut mapper, 0x2006, 0x20);
+    write(&mut ppu, &mut mapper, 0x2006, 0x00);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2007), 0x00);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2007), 0x11);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2007), 0x22);
+}
+
+#[test]
+fn data_increment() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    write(&mut ppu, &mut mapper, 0x2000, 0b100);
+    poke(&mut ppu, &mut mapper, 0x2000, 0x11);
+    write(&mut ppu, &mut mapper, 0x2007, 0x22);
+    assert_eq!(ppu.v, 0x2040);
+
+    write(&mut ppu, &mut mapper, 0x2000, 0);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x2020), 0x22);
+    assert_eq!(ppu.v, 0x2021);
+}
+
+#[test]
+fn pattern_tables_on_cartridge() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    poke(&mut ppu, &mut mapper, 0x1234, 0x56);
+    assert_eq!(mapper.chr[0x1234], 0x56);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x1234), 0x56);
+}
+
+#[test]
+fn nametable_mirroring() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    poke(&mut ppu, &mut mapper, 0x2001, 0x12);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x2401), 0x12);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x2801), 0x00);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x3001), 0x12);
+
+    mapper.mirroring = Mirroring::Vertical;
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x2401), 0x00);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x2801), 0x12);
+}
+
+#[test]
+fn palette() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    poke(&mut ppu, &mut mapper, 0x3F10, 0xFF);
+    poke(&mut ppu, &mut mapper, 0x3F05, 0x15);
+    poke(&mut ppu, &mut mapper, 0x2F25, 0x77);
+
+    // palette reads skip the buffer, which is filled from the nametable underneath
+    write(&mut ppu, &mut mapper, 0x2006, 0x3F);
+    write(&mut ppu, &mut mapper, 0x2006, 0x00);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2007) & 0x3F, 0x3F);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2007) & 0x3F, 0x00);
+    write(&mut ppu, &mut mapper, 0x2006, 0x3F);
+    write(&mut ppu, &mut mapper, 0x2006, 0x25);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2007) & 0x3F, 0x15);
+    assert_eq!(ppu.read_buffer, 0x77);
+
+    // only the backdrop entries of the sprite palettes are mirrored
+    poke(&mut ppu, &mut mapper, 0x3F11, 0x01);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x3F01) & 0x3F, 0x00);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x3FF1) & 0x3F, 0x01);
+
+    write(&mut ppu, &mut mapper, 0x2001, 0b1);
+    assert_eq!(peek(&mut ppu, &mut mapper, 0x3F05) & 0x3F, 0x10);
+}
+
+#[test]
+fn oam_data() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    write(&mut ppu, &mut mapper, 0x2003, 0x01);
+    write(&mut ppu, &mut mapper, 0x2004, 0xFF);
+    write(&mut ppu, &mut mapper, 0x2004, 0xFF);
+    assert_eq!(ppu.oam_address, 0x03);
+
+    write(&mut ppu, &mut mapper, 0x2003, 0x01);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2004), 0xFF);
+    write(&mut ppu, &mut mapper, 0x2003, 0x02);
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2004), 0xE3);
+}
+
+/// Ticks until the PPU is about to render `dot` of `scanline`.
+fn run_until(ppu: &mut Ppu, mapper: &mut ChrRam, scanline: u16, dot: u16) {
+    while (ppu.scanline, ppu.dot) != (scanline, dot) {
+        ppu.tick(mapper);
+    }
+}
+
+/// Renders a whole frame, starting from the pre-render line.
+fn render_frame(ppu: &mut Ppu, mapper: &mut ChrRam) {
+    run_until(ppu, mapper, 261, 0);
+    run_until(ppu, mapper, 240, 0);
+}
+
+fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+    ppu.frame_buffer()[y * SCREEN_WIDTH + x]
+}
+
+/// Tiles 1 and 2 are solid in colors 1 and 3 and the top left of the first nametable reads
+/// 1, 2, 1 in palettes 1, 1 and 0, all on a black backdrop.
+fn background(ppu: &mut Ppu, mapper: &mut ChrRam) {
+    for row in 0..8 {
+        mapper.chr[0x10 + row] = 0xFF;
+        mapper.chr[0x20 + row] = 0xFF;
+        mapper.chr[0x28 + row] = 0xFF;
+    }
+
+    for &(address, value) in &[(0x2000, 1), (0x2001, 2), (0x2002, 1), (0x23C0, 0b01)] {
+        poke(ppu, mapper, address, value);
+    }
+
+    for &(address, value) in &[(0x3F00, 0x0F), (0x3F01, 0x11), (0x3F03, 0x13), (0x3F05, 0x21), (0x3F07, 0x23)] {
+        poke(ppu, mapper, address, value);
+    }
+
+    write(ppu, mapper, 0x2006, 0x00);
+    write(ppu, mapper, 0x2006, 0x00);
+    write(ppu, mapper, 0x2001, 0b1010);
+}
+
+#[test]
+fn frame_timing() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    run_until(&mut ppu, &mut mapper, 241, 1);
+    assert!(!ppu.status.contains(Status::VBLANK));
+    ppu.tick(&mut mapper);
+    assert!(ppu.status.contains(Status::VBLANK));
+
+    run_until(&mut ppu, &mut mapper, 261, 1);
+    ppu.tick(&mut mapper);
+    assert!(!ppu.status.contains(Status::VBLANK));
+
+    // frames are 341x262 dots, one shorter on odd frames while rendering
+    run_until(&mut ppu, &mut mapper, 0, 0);
+    let mut dots = 0;
+    while ppu.frame() < 3 {
+        ppu.tick(&mut mapper);
+        dots += 1;
+    }
+    assert_eq!(dots, 2 * 341 * 262);
+
+    write(&mut ppu, &mut mapper, 0x2001, 0b1000);
+    let mut dots = 0;
+    while ppu.frame() < 5 {
+        ppu.tick(&mut mapper);
+        dots += 1;
+    }
+    assert_eq!(dots, 2 * 341 * 262 - 1);
+}
+
+#[test]
+fn pal_frame_timing() {
+    let mut ppu = Ppu::new(ClockMode::Pal);
+    let mut mapper = ChrRam { chr: vec![0; 0x2000], mirroring: Mirroring::Horizontal };
+    write(&mut ppu, &mut mapper, 0x2001, 0b1000);
+
+    let mut dots = 0;
+    while ppu.frame() < 2 {
+        ppu.tick(&mut mapper);
+        dots += 1;
+    }
+    assert_eq!(dots, 2 * 341 * 312);
+}
+
+#[test]
+fn background_tiles() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    background(&mut ppu, &mut mapper);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 0, 0), 0x21);
+    assert_eq!(pixel(&ppu, 7, 7), 0x21);
+    assert_eq!(pixel(&ppu, 8, 0), 0x23);
+    assert_eq!(pixel(&ppu, 16, 0), 0x11);
+    assert_eq!(pixel(&ppu, 24, 0), 0x0F);
+    assert_eq!(pixel(&ppu, 0, 8), 0x0F);
+    assert_eq!(pixel(&ppu, 255, 239), 0x0F);
+}
+
+#[test]
+fn background_scroll() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    background(&mut ppu, &mut mapper);
+    write(&mut ppu, &mut mapper, 0x2005, 4);
+    write(&mut ppu, &mut mapper, 0x2005, 0);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 3, 0), 0x21);
+    assert_eq!(pixel(&ppu, 4, 0), 0x23);
+    assert_eq!(pixel(&ppu, 12, 0), 0x11);
+    assert_eq!(pixel(&ppu, 20, 0), 0x0F);
+
+    write(&mut ppu, &mut mapper, 0x2005, 0);
+    write(&mut ppu, &mut mapper, 0x2005, 5);
+    render_frame(&mut ppu, &mut mapper);
+    assert_eq!(pixel(&ppu, 0, 2), 0x21);
+    assert_eq!(pixel(&ppu, 0, 3), 0x0F);
+}
+
+#[test]
+fn background_left_column_hidden() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    background(&mut ppu, &mut mapper);
+    write(&mut ppu, &mut mapper, 0x2001, 0b1000);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 7, 0), 0x0F);
+    assert_eq!(pixel(&ppu, 8, 0), 0x23);
+}
+
+#[test]
+fn mid_frame_scroll_split() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    background(&mut ppu, &mut mapper);
+    poke(&mut ppu, &mut mapper, 0x2180, 2);
+    poke(&mut ppu, &mut mapper, 0x2181, 1);
+    write(&mut ppu, &mut mapper, 0x2006, 0x00);
+    write(&mut ppu, &mut mapper, 0x2006, 0x00);
+
+    // horizontal scroll written during a line is picked up from the next one
+    run_until(&mut ppu, &mut mapper, 261, 0);
+    run_until(&mut ppu, &mut mapper, 100, 200);
+    write(&mut ppu, &mut mapper, 0x2005, 8);
+    write(&mut ppu, &mut mapper, 0x2005, 0);
+    run_until(&mut ppu, &mut mapper, 240, 0);
+
+    assert_eq!(pixel(&ppu, 0, 0), 0x21);
+    assert_eq!(pixel(&ppu, 0, 100), 0x13);
+    assert_eq!(pixel(&ppu, 0, 101), 0x11);
+    assert_eq!(pixel(&ppu, 8, 101), 0x0F);
+}
+
+#[test]
+fn rendering_disabled_backdrop() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    background(&mut ppu, &mut mapper);
+    write(&mut ppu, &mut mapper, 0x2001, 0);
+    render_frame(&mut ppu, &mut mapper);
+    assert!(ppu.frame_buffer().iter().all(|&color| color == 0x0F));
+
+    // the backdrop shows the palette entry v points to
+    write(&mut ppu, &mut mapper, 0x2006, 0x3F);
+    write(&mut ppu, &mut mapper, 0x2006, 0x05);
+    render_frame(&mut ppu, &mut mapper);
+    assert!(ppu.frame_buffer().iter().all(|&color| color == 0x21));
+}
+
+#[test]
+fn data_access_while_rendering() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    write(&mut ppu, &mut mapper, 0x2001, 0b1000);
+    run_until(&mut ppu, &mut mapper, 10, 100);
+    let v = ppu.v;
+
+    read(&mut ppu, &mut mapper, 0x2007);
+    assert_eq!(ppu.v & 0x1F, (v + 1) & 0x1F);
+    assert_eq!(ppu.v & 0x7000, (v + 0x1000) & 0x7000);
+}
+
+/// Places sprite `index` in OAM, with Y one above its top row.
+fn sprite(ppu: &mut Ppu, index: usize, y: u8, tile: u8, attributes: u8, x: u8) {
+    ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attributes, x]);
+}
+
+/// The background of `background` with sprites shown everywhere and sprite palette 2 set up.
+fn sprites(ppu: &mut Ppu, mapper: &mut ChrRam) {
+    background(ppu, mapper);
+    ppu.oam = [0xFF; OAM_LEN];
+
+    for &(address, value) in &[(0x3F19, 0x2A), (0x3F1A, 0x2B), (0x3F1B, 0x2C)] {
+        poke(ppu, mapper, address, value);
+    }
+
+    write(ppu, mapper, 0x2006, 0x00);
+    write(ppu, mapper, 0x2006, 0x00);
+    write(ppu, mapper, 0x2001, 0b1_1110);
+}
+
+#[test]
+fn sprite_rendering() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+    sprite(&mut ppu, 0, 9, 1, 0b10, 20);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 20, 9), 0x0F);
+    assert_eq!(pixel(&ppu, 20, 10), 0x2A);
+    assert_eq!(pixel(&ppu, 27, 17), 0x2A);
+    assert_eq!(pixel(&ppu, 28, 10), 0x0F);
+    assert_eq!(pixel(&ppu, 20, 18), 0x0F);
+}
+
+#[test]
+fn sprite_flipping() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+    // tile 3 has only its top left pixel set
+    mapper.chr[0x30] = 0x80;
+    sprite(&mut ppu, 0, 19, 3, 0b0000_0010, 40);
+    sprite(&mut ppu, 1, 19, 3, 0b0100_0010, 50);
+    sprite(&mut ppu, 2, 19, 3, 0b1000_0010, 60);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 40, 20), 0x2A);
+    assert_eq!(pixel(&ppu, 57, 20), 0x2A);
+    assert_eq!(pixel(&ppu, 50, 20), 0x0F);
+    assert_eq!(pixel(&ppu, 60, 27), 0x2A);
+    assert_eq!(pixel(&ppu, 60, 20), 0x0F);
+}
+
+#[test]
+fn sprites_8x16() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+    write(&mut ppu, &mut mapper, 0x2000, 0b10_0000);
+    // odd tiles come from $1000, the top half is tile 2 and the bottom half tile 3
+    mapper.chr[0x1020] = 0xFF;
+    mapper.chr[0x1038] = 0xFF;
+    sprite(&mut ppu, 0, 49, 3, 0b10, 40);
+    sprite(&mut ppu, 1, 49, 3, 0b1000_0010, 60);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 40, 50), 0x2A);
+    assert_eq!(pixel(&ppu, 40, 51), 0x0F);
+    assert_eq!(pixel(&ppu, 40, 58), 0x2B);
+    assert_eq!(pixel(&ppu, 40, 66), 0x0F);
+    assert_eq!(pixel(&ppu, 60, 57), 0x2B);
+    assert_eq!(pixel(&ppu, 60, 65), 0x2A);
+}
+
+#[test]
+fn sprite_priority() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+    // a sprite behind the background still hides the sprites after it
+    sprite(&mut ppu, 0, 0, 1, 0b0010_0010, 0);
+    sprite(&mut ppu, 1, 0, 2, 0b10, 4);
+    render_frame(&mut ppu, &mut mapper);
+
+    assert_eq!(pixel(&ppu, 0, 1), 0x21);
+    assert_eq!(pixel(&ppu, 7, 1), 0x21);
+    assert_eq!(pixel(&ppu, 8, 1), 0x2C);
+    assert_eq!(pixel(&ppu, 8, 0), 0x23);
+
+    sprite(&mut ppu, 0, 0, 1, 0b0010_0010, 24);
+    render_frame(&mut ppu, &mut mapper);
+    assert_eq!(pixel(&ppu, 24, 1), 0x2A);
+}
+
+#[test]
+fn sprite_zero_hit() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+    sprite(&mut ppu, 0, 3, 1, 0, 4);
+    run_until(&mut ppu, &mut mapper, 261, 0);
+
+    run_until(&mut ppu, &mut mapper, 4, 5);
+    assert!(!ppu.status.contains(Status::SPRITE_0_HIT));
+    ppu.tick(&mut mapper);
+    assert!(ppu.status.contains(Status::SPRITE_0_HIT));
+
+    run_until(&mut ppu, &mut mapper, 261, 2);
+    assert!(!ppu.status.contains(Status::SPRITE_0_HIT));
+
+    // not over a transparent background
+    sprite(&mut ppu, 0, 3, 1, 0, 24);
+    render_frame(&mut ppu, &mut mapper);
+    assert!(!ppu.status.contains(Status::SPRITE_0_HIT));
+
+    // nor in the hidden left column
+    sprite(&mut ppu, 0, 3, 1, 0, 0);
+    write(&mut ppu, &mut mapper, 0x2001, 0b1_1000);
+    render_frame(&mut ppu, &mut mapper);
+    assert!(!ppu.status.contains(Status::SPRITE_0_HIT));
+}
+
+#[test]
+fn sprite_limit() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+
+    for index in 0..9 {
+        sprite(&mut ppu, index, 49, 1, 0b10, index as u8 * 10);
+    }
+
+    render_frame(&mut ppu, &mut mapper);
+    assert_eq!(pixel(&ppu, 70, 50), 0x2A);
+    assert_eq!(pixel(&ppu, 80, 50), 0x0F);
+    assert!(ppu.status.contains(Status::SPRITE_OVERFLOW));
+
+    run_until(&mut ppu, &mut mapper, 261, 2);
+    assert!(!ppu.status.contains(Status::SPRITE_OVERFLOW));
+
+    ppu.set_sprite_limit(false);
+    render_frame(&mut ppu, &mut mapper);
+    assert_eq!(pixel(&ppu, 80, 50), 0x2A);
+    assert!(ppu.status.contains(Status::SPRITE_OVERFLOW));
+}
+
+#[test]
+fn sprite_limit_off_planes() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+    ppu.set_sprite_limit(false);
+
+    for index in 0..10 {
+        sprite(&mut ppu, index, 49, 1, 0b10, index as u8 * 10);
+    }
+
+    // the eighth sprite uses both planes, the last one only the low plane
+    sprite(&mut ppu, 7, 49, 2, 0b10, 70);
+    render_frame(&mut ppu, &mut mapper);
+    assert_eq!(pixel(&ppu, 70, 50), 0x2C);
+    assert_eq!(pixel(&ppu, 80, 50), 0x2A);
+    assert_eq!(pixel(&ppu, 90, 50), 0x2A);
+}
+
+#[test]
+fn sprite_overflow_bug() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    sprites(&mut ppu, &mut mapper);
+    ppu.oam = [0xF0; OAM_LEN];
+
+    for index in 0..8 {
+        sprite(&mut ppu, index, 49, 1, 0b10, 0);
+    }
+
+    // after the eighth sprite, the ninth is checked by its Y but the tenth by its tile
+    sprite(&mut ppu, 9, 0xF0, 50, 0xF0, 0xF0);
+    render_frame(&mut ppu, &mut mapper);
+    assert!(ppu.status.contains(Status::SPRITE_OVERFLOW));
+
+    sprite(&mut ppu, 9, 50, 0xF0, 0xF0, 0xF0);
+    render_frame(&mut ppu, &mut mapper);
+    assert!(!ppu.status.contains(Status::SPRITE_OVERFLOW));
+
+    // the flag still follows the hardware without the limit
+    ppu.set_sprite_limit(false);
+    render_frame(&mut ppu, &mut mapper);
+    assert!(!ppu.status.contains(Status::SPRITE_OVERFLOW));
+}
+
+#[test]
+fn nmi_output() {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    write(&mut ppu, &mut mapper, 0x2000, 0x80);
+    run_until(&mut ppu, &mut mapper, 241, 3);
+    assert!(ppu.status.contains(Status::VBLANK));
+    assert!(!ppu.nmi());
+    ppu.tick(&mut mapper);
+    assert!(ppu.nmi());
+
+    // toggling the enable bit during vertical blank drops and raises the line again
+    write(&mut ppu, &mut mapper, 0x2000, 0x00);
+    assert!(!ppu.nmi());
+    write(&mut ppu, &mut mapper, 0x2000, 0x80);
+    assert!(ppu.nmi());
+
+    run_until(&mut ppu, &mut mapper, 261, 1);
+    assert!(ppu.nmi());
+    ppu.tick(&mut mapper);
+    assert!(!ppu.nmi());
+
+    // reading the status clears the flag along with the line
+    run_until(&mut ppu, &mut mapper, 250, 0);
+    assert!(ppu.nmi());
+    assert_eq!(read(&mut ppu, &mut mapper, 0x2002) & 0x80, 0x80);
+    assert!(!ppu.nmi());
+    write(&mut ppu, &mut mapper, 0x2000, 0x00);
+    write(&mut ppu, &mut mapper, 0x2000, 0x80);
+    assert!(!ppu.nmi());
+}
+
+/// Reads the status at `dot` of the first vblank scanline, returning the vblank flag read and
+/// whether the NMI output was ever raised that frame.
+fn read_status_at(dot: u16) -> (bool, bool) {
+    let (mut ppu, mut mapper) = ppu(Mirroring::Horizontal);
+    write(&mut ppu, &mut mapper, 0x2000, 0x80);
+    run_until(&mut ppu, &mut mapper, 241, dot);
+
+    let mut nmi = ppu.nmi();
+    let vblank = read(&mut ppu, &mut mapper, 0x2002) & 0x80 != 0;
+
+    while ppu.scanline() != 261 {
+        ppu.tick(&mut mapper);
+        nmi |= ppu.nmi();
+    }
+
+    (vblank, nmi)
+}
+
+#[test]
+fn status_read_suppression() {
+    // one dot early, the flag is never set
+    assert_eq!(read_status_at(1), (false, false));
+    // at or one dot after the flag is set, it is read but the NMI is suppressed
+    assert_eq!(read_status_at(2), (true, false));
+    assert_eq!(read_status_at(3), (true, false));
+    // later, the NMI has happened already
+    assert_eq!(read_status_at(4), (true, true));
+    assert_eq!(read_status_at(0), (false, true));
+}
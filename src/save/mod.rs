@@ -0,0 +1,90 @@
+mod tests;
+
+use crate::types::Result;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often battery-backed RAM is written back while running.
+const STORE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The `.sav` file holding a cartridge's battery-backed PRG RAM, next to the ROM.
+#[derive(Getters)]
+pub struct SaveFile {
+    #[getset(get = "pub")]
+    path: PathBuf,
+    /// Contents of the file as last loaded or stored, to skip writing unchanged data.
+    stored: Option<Vec<u8>>,
+    stored_at: Instant,
+    /// Why the last store failed, until one succeeds again.
+    #[getset(get = "pub")]
+    store_error: Option<anyhow::Error>,
+}
+
+impl SaveFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), stored: None, stored_at: Instant::now(), store_error: None }
+    }
+
+    pub fn for_rom(rom: impl AsRef<Path>) -> Self {
+        Self::new(rom.as_ref().with_extension("sav"))
+    }
+
+    /// Reads the save data, `None` if there is no save file yet.
+    pub fn load(&mut self) -> Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(data) => {
+                self.stored = Some(data.clone());
+                Ok(Some(data))
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(anyhow!("failed to read `{}`: {}", self.path.display(), error)),
+        }
+    }
+
+    /// Writes the save data if it changed since it was last loaded or stored.
+    pub fn store(&mut self, data: &[u8]) -> Result {
+        self.stored_at = Instant::now();
+
+        if self.stored.as_deref() == Some(data) {
+            return Ok(());
+        }
+
+        write_atomic(&self.path, data)?;
+        self.stored = Some(data.to_vec());
+        self.store_error = None;
+        Ok(())
+    }
+
+    /// Stores the save data if the store interval has passed, to be called while running.
+    /// A failure is kept in `store_error` and retried on the next interval.
+    pub fn store_periodically(&mut self, data: &[u8]) {
+        if self.stored_at.elapsed() >= STORE_INTERVAL {
+            if let Err(error) = self.store(data) {
+                self.store_error = Some(error);
+            }
+        }
+    }
+}
+
+/// Replaces the file at `path` with `bytes` by writing them to a temporary file next to it
+/// first, so the file holds either the old or the new contents if the process dies midway.
+pub fn write_atomic(path: impl AsRef<Path>, bytes: &[u8]) -> Result {
+    let path = path.as_ref();
+    let mut path_temp = path.as_os_str().to_owned();
+    path_temp.push(".tmp");
+    let path_temp = PathBuf::from(path_temp);
+
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create(&path_temp)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&path_temp, path)
+    };
+
+    write().map_err(|error| {
+        let _ = fs::remove_file(&path_temp);
+        anyhow!("failed to write `{}`: {}", path.display(), error)
+    })
+}
@@ -0,0 +1,111 @@
+#![cfg(test)]
+
+use super::*;
+use std::env;
+use std::process;
+
+/// Empty directory for one test, removed again when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("nes-save-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn for_rom() {
+    let save = SaveFile::for_rom("roms/zelda.nes");
+    assert_eq!(save.path(), Path::new("roms/zelda.sav"));
+}
+
+#[test]
+fn load_missing() {
+    let dir = TempDir::new("missing");
+    let mut save = SaveFile::new(dir.0.join("game.sav"));
+    assert_eq!(save.load().unwrap(), None);
+}
+
+#[test]
+fn store_and_load() {
+    let dir = TempDir::new("store");
+    let path = dir.0.join("game.sav");
+
+    let mut save = SaveFile::new(&path);
+    save.store(&[1, 2, 3]).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), vec![1, 2, 3]);
+    assert!(!dir.0.join("game.sav.tmp").exists());
+
+    let mut save = SaveFile::new(&path);
+    assert_eq!(save.load().unwrap(), Some(vec![1, 2, 3]));
+}
+
+#[test]
+fn store_skips_unchanged() {
+    let dir = TempDir::new("unchanged");
+    let path = dir.0.join("game.sav");
+    fs::write(&path, [1, 2, 3]).unwrap();
+
+    let mut save = SaveFile::new(&path);
+    save.load().unwrap();
+    fs::remove_file(&path).unwrap();
+    save.store(&[1, 2, 3]).unwrap();
+    assert!(!path.exists());
+
+    save.store(&[4, 5, 6]).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), vec![4, 5, 6]);
+}
+
+#[test]
+fn store_periodically() {
+    let dir = TempDir::new("periodically");
+    let path = dir.0.join("game.sav");
+
+    let mut save = SaveFile::new(&path);
+    save.store_periodically(&[1]);
+    assert!(!path.exists());
+
+    save.stored_at -= STORE_INTERVAL;
+    save.store_periodically(&[1]);
+    assert_eq!(fs::read(&path).unwrap(), vec![1]);
+}
+
+#[test]
+fn store_periodically_keeps_error() {
+    let dir = TempDir::new("periodically-error");
+    let mut save = SaveFile::new(dir.0.join("missing/game.sav"));
+
+    save.stored_at -= STORE_INTERVAL;
+    save.store_periodically(&[1]);
+    assert!(save.store_error().is_some());
+
+    // retried on the next interval, and cleared once a store succeeds
+    save.stored_at -= STORE_INTERVAL;
+    fs::create_dir(dir.0.join("missing")).unwrap();
+    save.store_periodically(&[1]);
+    assert!(save.store_error().is_none());
+    assert_eq!(fs::read(dir.0.join("missing/game.sav")).unwrap(), vec![1]);
+}
+
+#[test]
+fn write_atomic_replaces() {
+    let dir = TempDir::new("atomic");
+    let path = dir.0.join("game.sav");
+    fs::write(&path, [1, 2, 3, 4]).unwrap();
+
+    write_atomic(&path, &[5, 6]).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), vec![5, 6]);
+
+    // a failed write leaves no temporary file behind
+    assert!(write_atomic(dir.0.join("missing/game.sav"), &[1]).is_err());
+    assert!(!dir.0.join("missing").exists());
+}